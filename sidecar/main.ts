@@ -21,6 +21,8 @@ import { ToolRegistry } from './core/ToolRegistry.js';
 import { AgentDispatcher, type AgentResult } from './core/AgentDispatcher.js';
 
 const WEBVIEW_AUTOMATION_ENABLED = false;
+// Set by the host: 'content-length' selects LSP-style frames instead of one message per line.
+const CONTENT_LENGTH_FRAMING = process.env.CLAW_SIDECAR_FRAMING === 'content-length';
 
 // JSON-RPC 2.0 types
 interface JsonRpcRequest {
@@ -48,6 +50,9 @@ type Handler = (params: Record<string, unknown>) => Promise<unknown>;
 
 const startTime = Date.now();
 const handlers = new Map<string, Handler>();
+// Ids of requests still being handled, and those the host has cancelled.
+const inFlightRequests = new Set<number | string>();
+const cancelledRequests = new Set<number | string>();
 const HOSTED_PROVIDERS = new Set<Provider>(['openai', 'anthropic', 'groq']);
 
 function providerRequiresApiKey(provider: Provider): boolean {
@@ -82,6 +87,42 @@ let commandExecutor: CommandExecutor;
 let toolRegistry: ToolRegistry;
 let agentDispatcher: AgentDispatcher;
 
+// Write one JSON-RPC message to stdout in the host's framing
+function writeMessage(message: JsonRpcResponse | JsonRpcNotification): void {
+  const body = JSON.stringify(message);
+  if (CONTENT_LENGTH_FRAMING) {
+    process.stdout.write(`Content-Length: ${Buffer.byteLength(body)}\r\n\r\n${body}`);
+  } else {
+    process.stdout.write(body + '\n');
+  }
+}
+
+/** Call `onMessage` with the body of each complete `Content-Length` frame read from stdin. */
+function readContentLengthFrames(onMessage: (body: string) => void, onClose: () => void): void {
+  let buffer = Buffer.alloc(0);
+  process.stdin.on('data', (chunk: Buffer) => {
+    buffer = Buffer.concat([buffer, chunk]);
+    for (;;) {
+      const headerEnd = buffer.indexOf('\r\n\r\n');
+      if (headerEnd === -1) return;
+      const header = buffer.subarray(0, headerEnd).toString('utf8');
+      const match = /^content-length:\s*(\d+)\s*$/im.exec(header);
+      if (!match) {
+        console.error('[sidecar] Frame without Content-Length:', header);
+        buffer = buffer.subarray(headerEnd + 4);
+        continue;
+      }
+      const bodyStart = headerEnd + 4;
+      const bodyEnd = bodyStart + Number(match[1]);
+      if (buffer.length < bodyEnd) return;
+      const body = buffer.subarray(bodyStart, bodyEnd).toString('utf8');
+      buffer = buffer.subarray(bodyEnd);
+      onMessage(body);
+    }
+  });
+  process.stdin.on('end', onClose);
+}
+
 // Send a JSON-RPC notification (no id, fire-and-forget)
 function sendNotification(method: string, params?: Record<string, unknown>): void {
   const notification: JsonRpcNotification = {
@@ -89,17 +130,19 @@ function sendNotification(method: string, params?: Record<string, unknown>): voi
     method,
     params,
   };
-  process.stdout.write(JSON.stringify(notification) + '\n');
+  writeMessage(notification);
 }
 
 // Send a JSON-RPC response
 function sendResponse(id: number | string | null, result: unknown): void {
+  // JSON.stringify drops an undefined result, which would leave the host an
+  // envelope with neither `result` nor `error`.
   const response: JsonRpcResponse = {
     jsonrpc: '2.0',
-    result,
+    result: result === undefined ? null : result,
     id,
   };
-  process.stdout.write(JSON.stringify(response) + '\n');
+  writeMessage(response);
 }
 
 // Send a JSON-RPC error response
@@ -109,7 +152,7 @@ function sendError(id: number | string | null, code: number, message: string): v
     error: { code, message },
     id,
   };
-  process.stdout.write(JSON.stringify(response) + '\n');
+  writeMessage(response);
 }
 
 function isDomAutomationResult(value: unknown): value is DomAutomationResult {
@@ -297,6 +340,17 @@ function registerHandlers(): void {
 
   handlers.set('getConfig', async () => appConfig);
 
+  // The host already failed a cancelled request, so its eventual result is dropped.
+  handlers.set('$/cancelRequest', async (params) => {
+    const id = params.id as number | string | undefined;
+    if (id !== undefined && inFlightRequests.has(id)) {
+      cancelledRequests.add(id);
+    }
+  });
+
+  // Sent by the host as a notification before it quits.
+  handlers.set('shutdown', async () => shutdown('shutdown requested'));
+
   handlers.set('updateConfig', async (params) => {
     const incoming = (params || {}) as Partial<AppConfig>;
     if (incoming.commandAllowlist) {
@@ -593,6 +647,16 @@ function registerHandlers(): void {
   });
 }
 
+/** Stop background subsystems and exit. */
+function shutdown(reason: string): void {
+  console.error(`[sidecar] ${reason}, shutting down`);
+  heartbeat.stop();
+  reflection.stop();
+  qmdMemory.close();
+  void stagehandBridge?.close();
+  process.exit(0);
+}
+
 // Process a single JSON-RPC request
 async function processRequest(request: JsonRpcRequest): Promise<void> {
   const { method, params, id } = request;
@@ -616,12 +680,16 @@ async function processRequest(request: JsonRpcRequest): Promise<void> {
     return;
   }
 
+  inFlightRequests.add(id);
   try {
     const result = await handler(params || {});
-    sendResponse(id, result);
+    if (!cancelledRequests.has(id)) sendResponse(id, result);
   } catch (err) {
     const message = err instanceof Error ? err.message : String(err);
-    sendError(id, -32000, message);
+    if (!cancelledRequests.has(id)) sendError(id, -32000, message);
+  } finally {
+    inFlightRequests.delete(id);
+    cancelledRequests.delete(id);
   }
 }
 
@@ -636,47 +704,41 @@ async function main(): Promise<void> {
     // Continue anyway with degraded functionality
   }
 
-  const rl = readline.createInterface({
-    input: process.stdin,
-    terminal: false,
-  });
-
-  // Announce readiness
-  sendNotification('agentReady', { version: '0.1.0' });
-
-  rl.on('line', async (line: string) => {
-    const trimmed = line.trim();
+  const onMessage = async (message: string) => {
+    const trimmed = message.trim();
     if (!trimmed) return;
 
     try {
-      const request = JSON.parse(trimmed) as JsonRpcRequest;
-      if (request.jsonrpc !== '2.0') {
-        console.error('[sidecar] Invalid JSON-RPC version:', trimmed);
-        return;
-      }
-      await processRequest(request);
+      const parsed = JSON.parse(trimmed) as JsonRpcRequest | JsonRpcRequest[];
+      // Batches are processed concurrently; each response is written as its own message.
+      const requests = Array.isArray(parsed) ? parsed : [parsed];
+      await Promise.all(requests.map(async (request) => {
+        if (request?.jsonrpc !== '2.0') {
+          console.error('[sidecar] Invalid JSON-RPC version:', JSON.stringify(request));
+          return;
+        }
+        await processRequest(request);
+      }));
     } catch (err) {
       console.error('[sidecar] Failed to parse JSON-RPC:', trimmed, err);
     }
-  });
+  };
 
-  rl.on('close', () => {
-    console.error('[sidecar] stdin closed, shutting down');
-    heartbeat.stop();
-    reflection.stop();
-    qmdMemory.close();
-    void stagehandBridge?.close();
-    process.exit(0);
-  });
+  if (CONTENT_LENGTH_FRAMING) {
+    readContentLengthFrames(onMessage, () => shutdown('stdin closed'));
+  } else {
+    const rl = readline.createInterface({
+      input: process.stdin,
+      terminal: false,
+    });
+    rl.on('line', onMessage);
+    rl.on('close', () => shutdown('stdin closed'));
+  }
 
-  process.on('SIGTERM', () => {
-    console.error('[sidecar] SIGTERM received, shutting down');
-    heartbeat.stop();
-    reflection.stop();
-    qmdMemory.close();
-    void stagehandBridge?.close();
-    process.exit(0);
-  });
+  // Announce readiness
+  sendNotification('agentReady', { version: '0.1.0' });
+
+  process.on('SIGTERM', () => shutdown('SIGTERM received'));
 }
 
 // Export for use by other modules
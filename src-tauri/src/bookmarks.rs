@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::workspace;
+
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+
+/// The bookmarks file's contents, read on first use. Also serializes
+/// read-modify-write cycles on the file.
+static BOOKMARKS: Mutex<Option<Vec<Bookmark>>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub id: String,
+    pub url: String,
+    pub title: String,
+    /// Folder name; `None` for unfiled bookmarks.
+    #[serde(default)]
+    pub folder: Option<String>,
+    /// Unix milliseconds.
+    pub created_at: u64,
+}
+
+/// Fields to change with `update_bookmark`; omitted ones are kept. An empty
+/// `folder` moves the bookmark out of its folder.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BookmarkUpdate {
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub folder: Option<String>,
+}
+
+fn bookmarks_path() -> Option<PathBuf> {
+    Some(workspace::workspace_dir()?.join(BOOKMARKS_FILE))
+}
+
+fn read_bookmarks() -> Vec<Bookmark> {
+    bookmarks_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Run `f` on the bookmarks in their saved order.
+pub fn with_bookmarks<T>(f: impl FnOnce(&[Bookmark]) -> T) -> Result<T, String> {
+    let mut cache = BOOKMARKS.lock().map_err(|e| e.to_string())?;
+    Ok(f(cache.get_or_insert_with(read_bookmarks)))
+}
+
+fn write_bookmarks(bookmarks: &[Bookmark]) -> Result<(), String> {
+    let path = bookmarks_path().ok_or("Workspace directory not found")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(bookmarks).map_err(|e| e.to_string())?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, raw).map_err(|e| format!("Failed to write bookmarks: {}", e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save bookmarks: {}", e))
+}
+
+/// Apply `change` to the stored bookmarks, save them atomically and emit
+/// `bookmarks-changed` with the full list.
+fn modify<T, F>(app: &AppHandle, change: F) -> Result<T, String>
+where
+    F: FnOnce(&mut Vec<Bookmark>) -> Result<T, String>,
+{
+    let mut cache = BOOKMARKS.lock().map_err(|e| e.to_string())?;
+    let mut bookmarks = cache.get_or_insert_with(read_bookmarks).clone();
+    let result = change(&mut bookmarks)?;
+    write_bookmarks(&bookmarks)?;
+    let _ = app.emit("bookmarks-changed", &bookmarks);
+    *cache = Some(bookmarks);
+    Ok(result)
+}
+
+fn folder_name(folder: Option<String>) -> Option<String> {
+    folder
+        .map(|folder| folder.trim().to_string())
+        .filter(|folder| !folder.is_empty())
+}
+
+fn find_index(bookmarks: &[Bookmark], id: &str) -> Result<usize, String> {
+    bookmarks
+        .iter()
+        .position(|bookmark| bookmark.id == id)
+        .ok_or_else(|| format!("Bookmark {} not found", id))
+}
+
+/// Bookmark `url` at the end of the list, optionally inside `folder`.
+#[tauri::command]
+pub fn add_bookmark(
+    app: AppHandle,
+    url: String,
+    title: String,
+    folder: Option<String>,
+) -> Result<Bookmark, String> {
+    if url.trim().is_empty() {
+        return Err("Bookmark URL is empty".to_string());
+    }
+    let bookmark = Bookmark {
+        id: uuid::Uuid::new_v4().to_string(),
+        url,
+        title,
+        folder: folder_name(folder),
+        created_at: crate::tabs::now_ms(),
+    };
+    modify(&app, |bookmarks| {
+        bookmarks.push(bookmark.clone());
+        Ok(bookmark)
+    })
+}
+
+#[tauri::command]
+pub fn remove_bookmark(app: AppHandle, id: String) -> Result<(), String> {
+    modify(&app, |bookmarks| {
+        let index = find_index(bookmarks, &id)?;
+        bookmarks.remove(index);
+        Ok(())
+    })
+}
+
+/// Bookmarks in their saved order, only those in `folder` when given.
+#[tauri::command]
+pub fn list_bookmarks(folder: Option<String>) -> Result<Vec<Bookmark>, String> {
+    let folder = folder_name(folder);
+    with_bookmarks(|bookmarks| {
+        bookmarks
+            .iter()
+            .filter(|bookmark| folder.is_none() || bookmark.folder == folder)
+            .cloned()
+            .collect()
+    })
+}
+
+#[tauri::command]
+pub fn update_bookmark(app: AppHandle, id: String, update: BookmarkUpdate) -> Result<Bookmark, String> {
+    modify(&app, |bookmarks| {
+        let index = find_index(bookmarks, &id)?;
+        let bookmark = &mut bookmarks[index];
+        if let Some(url) = update.url {
+            if url.trim().is_empty() {
+                return Err("Bookmark URL is empty".to_string());
+            }
+            bookmark.url = url;
+        }
+        if let Some(title) = update.title {
+            bookmark.title = title;
+        }
+        if update.folder.is_some() {
+            bookmark.folder = folder_name(update.folder);
+        }
+        Ok(bookmark.clone())
+    })
+}
+
+/// Move a bookmark to `new_index` in the full list, clamped to the last position.
+#[tauri::command]
+pub fn move_bookmark(app: AppHandle, id: String, new_index: usize) -> Result<(), String> {
+    modify(&app, |bookmarks| {
+        let from = find_index(bookmarks, &id)?;
+        let bookmark = bookmarks.remove(from);
+        let to = new_index.min(bookmarks.len());
+        bookmarks.insert(to, bookmark);
+        Ok(())
+    })
+}
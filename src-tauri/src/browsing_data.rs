@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+/// Kinds of site data `clear_browsing_data` removes.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BrowsingDataTypes {
+    pub cookies: bool,
+    pub local_storage: bool,
+    pub indexed_db: bool,
+    /// HTTP disk and memory cache.
+    pub cache: bool,
+}
+
+impl BrowsingDataTypes {
+    fn is_empty(&self) -> bool {
+        !(self.cookies || self.local_storage || self.indexed_db || self.cache)
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    fn is_all(&self) -> bool {
+        self.cookies && self.local_storage && self.indexed_db && self.cache
+    }
+}
+
+/// Whether a data record named after a site belongs to `domain` or one of its subdomains.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn record_in_domain(record_name: &str, domain: &str) -> bool {
+    let record_name = record_name.trim_start_matches('.').to_ascii_lowercase();
+    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+    record_name == domain || record_name.ends_with(&format!(".{}", domain))
+}
+
+/// Clear the persistent WebKit data store that regular tabs share.
+#[cfg(target_os = "macos")]
+async fn clear_data_store(
+    app: &AppHandle,
+    types: BrowsingDataTypes,
+    domain: Option<String>,
+    since_ms: Option<i64>,
+) -> Result<(), String> {
+    use block2::RcBlock;
+    use objc2::MainThreadMarker;
+    use objc2_foundation::{NSArray, NSDate, NSSet, NSString};
+    use objc2_web_kit::{
+        WKWebsiteDataRecord, WKWebsiteDataStore, WKWebsiteDataTypeCookies, WKWebsiteDataTypeDiskCache,
+        WKWebsiteDataTypeIndexedDBDatabases, WKWebsiteDataTypeLocalStorage, WKWebsiteDataTypeMemoryCache,
+    };
+    use std::ptr::NonNull;
+    use std::sync::Mutex;
+
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    app.run_on_main_thread(move || unsafe {
+        let Some(mtm) = MainThreadMarker::new() else {
+            let _ = sender.send(Err("Not on the main thread".to_string()));
+            return;
+        };
+        let mut kinds: Vec<&NSString> = Vec::new();
+        if types.cookies {
+            kinds.push(WKWebsiteDataTypeCookies);
+        }
+        if types.local_storage {
+            kinds.push(WKWebsiteDataTypeLocalStorage);
+        }
+        if types.indexed_db {
+            kinds.push(WKWebsiteDataTypeIndexedDBDatabases);
+        }
+        if types.cache {
+            kinds.push(WKWebsiteDataTypeDiskCache);
+            kinds.push(WKWebsiteDataTypeMemoryCache);
+        }
+        let kinds = NSSet::from_slice(&kinds);
+        // Completion blocks are `Fn`, so the sender is taken out on first call.
+        let sender = Mutex::new(Some(sender));
+        let done = RcBlock::new(move || {
+            if let Some(sender) = sender.lock().ok().and_then(|mut sender| sender.take()) {
+                let _ = sender.send(Ok(()));
+            }
+        });
+
+        let store = WKWebsiteDataStore::defaultDataStore(mtm);
+        match domain {
+            None => {
+                let since = match since_ms {
+                    Some(ms) => NSDate::dateWithTimeIntervalSince1970(ms as f64 / 1000.0),
+                    None => NSDate::distantPast(),
+                };
+                store.removeDataOfTypes_modifiedSince_completionHandler(&kinds, &since, &done);
+            }
+            Some(domain) => {
+                // WebKit can't combine a record filter with a time range, so
+                // domain-scoped clears remove the site's data regardless of age.
+                let remove_store = store.clone();
+                let remove_kinds = kinds.clone();
+                let fetched = RcBlock::new(move |records: NonNull<NSArray<WKWebsiteDataRecord>>| unsafe {
+                    let matching: Vec<_> = records
+                        .as_ref()
+                        .iter()
+                        .filter(|record| record_in_domain(&record.displayName().to_string(), &domain))
+                        .collect();
+                    let matching = NSArray::from_retained_slice(&matching);
+                    remove_store.removeDataOfTypes_forDataRecords_completionHandler(&remove_kinds, &matching, &done);
+                });
+                store.fetchDataRecordsOfTypes_completionHandler(&kinds, &fetched);
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to clear browsing data: {}", e))?;
+    receiver
+        .await
+        .map_err(|_| "Browsing data clear was dropped".to_string())?
+}
+
+/// Clear the WebKitGTK data manager of the main window's web context, which
+/// regular tabs share.
+#[cfg(target_os = "linux")]
+async fn clear_data_store(
+    app: &AppHandle,
+    types: BrowsingDataTypes,
+    domain: Option<String>,
+    since_ms: Option<i64>,
+) -> Result<(), String> {
+    use tauri::Manager;
+    use webkit2gtk::gio::Cancellable;
+    use webkit2gtk::{WebContextExt, WebViewExt, WebsiteDataManagerExt, WebsiteDataManagerExtManual, WebsiteDataTypes};
+
+    let mut kinds = WebsiteDataTypes::empty();
+    if types.cookies {
+        kinds |= WebsiteDataTypes::COOKIES;
+    }
+    if types.local_storage {
+        kinds |= WebsiteDataTypes::LOCAL_STORAGE;
+    }
+    if types.indexed_db {
+        kinds |= WebsiteDataTypes::INDEXEDDB_DATABASES;
+    }
+    if types.cache {
+        kinds |= WebsiteDataTypes::DISK_CACHE | WebsiteDataTypes::MEMORY_CACHE;
+    }
+    // WebKit takes how far back to clear, in microseconds; 0 means everything.
+    let timespan = since_ms
+        .map(|since| (crate::tabs::now_ms() as i64 - since).max(1) * 1000)
+        .unwrap_or(0);
+
+    let webview = app
+        .get_webview(crate::tabs::MAIN_WINDOW)
+        .ok_or("Main window not found")?;
+    let (sender, receiver) = tokio::sync::oneshot::channel();
+    webview
+        .with_webview(move |platform| {
+            let Some(manager) = platform.inner().context().and_then(|context| context.website_data_manager()) else {
+                let _ = sender.send(Err("Webview has no website data manager".to_string()));
+                return;
+            };
+            let finish = move |result: Result<(), webkit2gtk::glib::Error>| {
+                let _ = sender.send(result.map_err(|e| e.to_string()));
+            };
+            match domain {
+                None => manager.clear(kinds, webkit2gtk::glib::TimeSpan(timespan), None::<&Cancellable>, finish),
+                Some(domain) => {
+                    // Records carry no modification time, so domain-scoped
+                    // clears remove the site's data regardless of age.
+                    let remover = manager.clone();
+                    manager.fetch(kinds, None::<&Cancellable>, move |records| match records {
+                        Ok(records) => {
+                            let matching: Vec<_> = records
+                                .iter()
+                                .filter(|record| record.name().is_some_and(|name| record_in_domain(&name, &domain)))
+                                .collect();
+                            remover.remove(kinds, &matching, None::<&Cancellable>, finish);
+                        }
+                        Err(e) => finish(Err(e)),
+                    });
+                }
+            }
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))?;
+    receiver
+        .await
+        .map_err(|_| "Browsing data clear was dropped".to_string())?
+}
+
+/// WebView2's profile API isn't reachable without the COM bindings, so only
+/// a full clear is possible here, through Tauri.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+async fn clear_data_store(
+    app: &AppHandle,
+    types: BrowsingDataTypes,
+    domain: Option<String>,
+    since_ms: Option<i64>,
+) -> Result<(), String> {
+    use tauri::Manager;
+
+    if domain.is_some() || since_ms.is_some() || !types.is_all() {
+        return Err("Only clearing all browsing data is supported on this platform".to_string());
+    }
+    app.get_webview(crate::tabs::MAIN_WINDOW)
+        .ok_or("Main window not found")?
+        .clear_all_browsing_data()
+        .map_err(|e| format!("Failed to clear browsing data: {}", e))
+}
+
+/// Clear cookies, localStorage, IndexedDB and/or the HTTP cache, for every
+/// site or only `domain` (and its subdomains), optionally limited to data
+/// modified since `since_ms` (Unix milliseconds). Incognito tabs use their
+/// own ephemeral stores and are left alone. Emits `browsing-data-cleared`.
+#[tauri::command]
+pub async fn clear_browsing_data(
+    app: AppHandle,
+    types: BrowsingDataTypes,
+    domain: Option<String>,
+    since_ms: Option<i64>,
+) -> Result<(), String> {
+    if types.is_empty() {
+        return Err("No browsing data types selected".to_string());
+    }
+    let domain = domain
+        .map(|domain| domain.trim().to_string())
+        .filter(|domain| !domain.is_empty());
+    clear_data_store(&app, types, domain.clone(), since_ms).await?;
+    log::info!("[BrowsingData] Cleared {:?} domain={:?} since={:?}", types, domain, since_ms);
+    let _ = app.emit(
+        "browsing-data-cleared",
+        serde_json::json!({
+            "types": types,
+            "domain": domain,
+            "sinceMs": since_ms,
+        }),
+    );
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often the on-disk cache is measured against the configured limit.
+const CACHE_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// Disk-cache policy for content webviews.
+pub struct CacheState {
+    /// Upper bound for the webview disk cache. `None` means unbounded.
+    pub max_disk_cache_bytes: Option<u64>,
+    watcher_running: bool,
+}
+
+impl CacheState {
+    pub fn new() -> Self {
+        Self {
+            max_disk_cache_bytes: None,
+            watcher_running: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    pub disk_bytes: u64,
+    pub max_disk_cache_bytes: Option<u64>,
+    pub over_limit: bool,
+}
+
+/// Directories the platform webview uses for its HTTP/disk cache.
+fn cache_dirs(app: &AppHandle) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Ok(dir) = app.path().app_cache_dir() {
+        dirs.push(dir);
+    }
+    #[cfg(target_os = "windows")]
+    if let Ok(dir) = app.path().app_local_data_dir() {
+        dirs.push(dir.join("EBWebView").join("Default").join("Cache"));
+    }
+    dirs
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn measure(app: &AppHandle, max_disk_cache_bytes: Option<u64>) -> CacheUsage {
+    let disk_bytes = cache_dirs(app).iter().map(|dir| dir_size(dir)).sum();
+    CacheUsage {
+        disk_bytes,
+        max_disk_cache_bytes,
+        over_limit: max_disk_cache_bytes.is_some_and(|max| disk_bytes > max),
+    }
+}
+
+/// Evict the shared webview disk and memory caches, leaving cookies and storage alone.
+#[cfg(target_os = "macos")]
+fn purge_disk_cache(app: &AppHandle) -> Result<(), String> {
+    use block2::RcBlock;
+    use objc2::MainThreadMarker;
+    use objc2_foundation::{NSDate, NSSet};
+    use objc2_web_kit::{WKWebsiteDataStore, WKWebsiteDataTypeDiskCache, WKWebsiteDataTypeMemoryCache};
+
+    app.run_on_main_thread(|| unsafe {
+        let Some(mtm) = MainThreadMarker::new() else {
+            return;
+        };
+        let store = WKWebsiteDataStore::defaultDataStore(mtm);
+        let types = NSSet::from_slice(&[WKWebsiteDataTypeDiskCache, WKWebsiteDataTypeMemoryCache]);
+        let since = NSDate::distantPast();
+        let done = RcBlock::new(|| {});
+        store.removeDataOfTypes_modifiedSince_completionHandler(&types, &since, &done);
+    })
+    .map_err(|e| format!("Failed to purge disk cache: {}", e))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn purge_disk_cache(_app: &AppHandle) -> Result<(), String> {
+    Err("Disk cache eviction is not supported on this platform".to_string())
+}
+
+/// Measure the cache and evict it if it has grown past the limit.
+/// Emits `cache-limit-exceeded` whenever the limit is crossed so operators
+/// can react on platforms where eviction is unavailable.
+fn enforce_cache_limit(app: &AppHandle, max_disk_cache_bytes: Option<u64>) -> CacheUsage {
+    let usage = measure(app, max_disk_cache_bytes);
+    if usage.over_limit {
+        let purged = purge_disk_cache(app);
+        if let Err(ref e) = purged {
+            log::error!("[Cache] {}", e);
+        }
+        let _ = app.emit(
+            "cache-limit-exceeded",
+            serde_json::json!({
+                "diskBytes": usage.disk_bytes,
+                "maxDiskCacheBytes": max_disk_cache_bytes,
+                "purged": purged.is_ok(),
+            }),
+        );
+    }
+    usage
+}
+
+fn spawn_cache_watcher(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(CACHE_CHECK_INTERVAL_SECS)).await;
+            let limit = {
+                let state = app.state::<Mutex<CacheState>>();
+                let mut state = match state.lock() {
+                    Ok(state) => state,
+                    Err(_) => break,
+                };
+                if state.max_disk_cache_bytes.is_none() {
+                    state.watcher_running = false;
+                    break;
+                }
+                state.max_disk_cache_bytes
+            };
+            enforce_cache_limit(&app, limit);
+        }
+    });
+}
+
+/// Set (or with `None`, remove) the disk cache limit for content webviews.
+/// The cache is checked immediately and then periodically while a limit is set.
+#[tauri::command]
+pub fn set_cache_policy(
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<CacheState>>,
+    max_disk_cache_bytes: Option<u64>,
+) -> Result<CacheUsage, String> {
+    let start_watcher = {
+        let mut cache_state = state.lock().map_err(|e| e.to_string())?;
+        cache_state.max_disk_cache_bytes = max_disk_cache_bytes;
+        let start = max_disk_cache_bytes.is_some() && !cache_state.watcher_running;
+        if start {
+            cache_state.watcher_running = true;
+        }
+        start
+    };
+    if start_watcher {
+        spawn_cache_watcher(app.clone());
+    }
+    Ok(enforce_cache_limit(&app, max_disk_cache_bytes))
+}
+
+/// Report how much disk the webview cache currently uses.
+#[tauri::command]
+pub fn get_cache_usage(
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<CacheState>>,
+) -> Result<CacheUsage, String> {
+    let limit = state.lock().map_err(|e| e.to_string())?.max_disk_cache_bytes;
+    Ok(measure(&app, limit))
+}
@@ -0,0 +1,565 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::Engine;
+use serde::Deserialize;
+use tauri::{AppHandle, Emitter, Manager, Webview};
+use tokio::sync::oneshot;
+
+use crate::tabs;
+
+/// Heartbeat frames are downscaled to this width to keep events small.
+const HEARTBEAT_MAX_WIDTH: u32 = 480;
+const MIN_HEARTBEAT_INTERVAL_MS: u64 = 250;
+/// Upper bound on a single native snapshot before it is treated as failed.
+const CAPTURE_TIMEOUT_MS: u64 = 5_000;
+
+/// PDF rendering lays out the whole document, so it gets far longer than a snapshot.
+const PDF_TIMEOUT_MS: u64 = 60_000;
+/// Paper sizes accepted by `PdfOptions::paper_size`.
+pub const PDF_PAPER_SIZES: &[&str] = &["a3", "a4", "a5", "letter", "legal"];
+
+type SnapshotSender = oneshot::Sender<Result<Vec<u8>, String>>;
+/// Resolves with the PDF bytes to write, or `None` when the platform wrote the file itself.
+type PdfSender = oneshot::Sender<Result<Option<Vec<u8>>, String>>;
+
+/// Layout options for `render_webview_pdf`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PdfOptions {
+    /// One of `PDF_PAPER_SIZES`; the platform default when unset.
+    pub paper_size: Option<String>,
+    pub landscape: bool,
+    /// Print CSS backgrounds and images.
+    pub print_background: bool,
+}
+
+/// Active-tab screenshot heartbeat for monitoring unattended agents.
+pub struct CaptureState {
+    /// Bumped on every start/stop so a superseded loop exits on its next tick.
+    heartbeat_generation: u64,
+    heartbeat_running: bool,
+    /// Set when a frame is emitted and cleared by `ack_screenshot_heartbeat_frame`.
+    /// No new frame is captured while it is set, so slow consumers never queue frames.
+    awaiting_ack: bool,
+    last_frame_hash: Option<u64>,
+    next_sequence: u64,
+}
+
+impl CaptureState {
+    pub fn new() -> Self {
+        Self {
+            heartbeat_generation: 0,
+            heartbeat_running: false,
+            awaiting_ack: false,
+            last_frame_hash: None,
+            next_sequence: 0,
+        }
+    }
+}
+
+/// Capture the visible area of a webview as PNG, scaled down to `max_width` if wider.
+pub async fn capture_webview_png(
+    webview: &Webview,
+    max_width: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let (sender, receiver) = oneshot::channel();
+    start_snapshot(webview, max_width, sender)?;
+    match tokio::time::timeout(Duration::from_millis(CAPTURE_TIMEOUT_MS), receiver).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(_)) => Err("Snapshot was cancelled".to_string()),
+        Err(_) => Err(format!("Snapshot timed out after {}ms", CAPTURE_TIMEOUT_MS)),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn start_snapshot(
+    webview: &Webview,
+    max_width: Option<u32>,
+    sender: SnapshotSender,
+) -> Result<(), String> {
+    use std::cell::Cell;
+
+    use block2::RcBlock;
+    use objc2::MainThreadMarker;
+    use objc2_app_kit::NSImage;
+    use objc2_foundation::{NSError, NSNumber};
+    use objc2_web_kit::{WKSnapshotConfiguration, WKWebView};
+
+    webview
+        .with_webview(move |platform| {
+            let Some(mtm) = MainThreadMarker::new() else {
+                let _ = sender.send(Err("Snapshot must start on the main thread".to_string()));
+                return;
+            };
+            // The completion handler is a `Fn` block, so the one-shot sender is taken on first call.
+            let sender = Cell::new(Some(sender));
+            let handler = RcBlock::new(move |image: *mut NSImage, _error: *mut NSError| {
+                let Some(sender) = sender.take() else {
+                    return;
+                };
+                let result = match unsafe { image.as_ref() } {
+                    Some(image) => png_from_image(image),
+                    None => Err("WKWebView returned no snapshot".to_string()),
+                };
+                let _ = sender.send(result);
+            });
+            unsafe {
+                let view: &WKWebView = &*platform.inner().cast();
+                let config = WKSnapshotConfiguration::new(mtm);
+                if let Some(width) = max_width {
+                    let current = view.frame().size.width;
+                    if current > width as f64 {
+                        config.setSnapshotWidth(Some(&NSNumber::numberWithDouble(width as f64)));
+                    }
+                }
+                view.takeSnapshotWithConfiguration_completionHandler(Some(&config), &handler);
+            }
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))
+}
+
+#[cfg(target_os = "macos")]
+fn png_from_image(image: &objc2_app_kit::NSImage) -> Result<Vec<u8>, String> {
+    use objc2::runtime::AnyObject;
+    use objc2_app_kit::{NSBitmapImageFileType, NSBitmapImageRep};
+    use objc2_foundation::{NSDictionary, NSString};
+
+    let tiff = image
+        .TIFFRepresentation()
+        .ok_or("Snapshot has no bitmap data")?;
+    let bitmap = NSBitmapImageRep::imageRepWithData(&tiff).ok_or("Failed to decode snapshot")?;
+    let properties = NSDictionary::<NSString, AnyObject>::new();
+    let png = unsafe {
+        bitmap.representationUsingType_properties(NSBitmapImageFileType::PNG, &properties)
+    }
+    .ok_or("Failed to encode snapshot as PNG")?;
+    Ok(png.to_vec())
+}
+
+#[cfg(target_os = "linux")]
+fn start_snapshot(
+    webview: &Webview,
+    max_width: Option<u32>,
+    sender: SnapshotSender,
+) -> Result<(), String> {
+    use webkit2gtk::{SnapshotOptions, SnapshotRegion, WebViewExt};
+
+    webview
+        .with_webview(move |platform| {
+            platform.inner().snapshot(
+                SnapshotRegion::Visible,
+                SnapshotOptions::NONE,
+                None::<&webkit2gtk::gio::Cancellable>,
+                move |result| {
+                    let png = result
+                        .map_err(|e| format!("Snapshot failed: {}", e))
+                        .and_then(|surface| png_from_surface(surface, max_width));
+                    let _ = sender.send(png);
+                },
+            );
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn png_from_surface(surface: cairo::Surface, max_width: Option<u32>) -> Result<Vec<u8>, String> {
+    let image = cairo::ImageSurface::try_from(surface)
+        .map_err(|_| "Snapshot is not an image surface".to_string())?;
+    let width = image.width();
+    let image = match max_width {
+        Some(max) if width > max as i32 && width > 0 => {
+            let scale = max as f64 / width as f64;
+            let height = ((image.height() as f64 * scale).round() as i32).max(1);
+            let scaled = cairo::ImageSurface::create(cairo::Format::ARgb32, max as i32, height)
+                .map_err(|e| format!("Failed to allocate snapshot: {}", e))?;
+            let cr = cairo::Context::new(&scaled)
+                .map_err(|e| format!("Failed to scale snapshot: {}", e))?;
+            cr.scale(scale, scale);
+            cr.set_source_surface(&image, 0.0, 0.0)
+                .and_then(|_| cr.paint())
+                .map_err(|e| format!("Failed to scale snapshot: {}", e))?;
+            drop(cr);
+            scaled
+        }
+        _ => image,
+    };
+    let mut png = Vec::new();
+    image
+        .write_to_png(&mut png)
+        .map_err(|e| format!("Failed to encode snapshot as PNG: {}", e))?;
+    Ok(png)
+}
+
+/// Render a webview's document to a PDF at `path`.
+pub async fn render_webview_pdf(
+    webview: &Webview,
+    path: &Path,
+    options: &PdfOptions,
+) -> Result<(), String> {
+    let (sender, receiver) = oneshot::channel();
+    start_pdf(webview, path.to_path_buf(), options.clone(), sender)?;
+    let rendered = match tokio::time::timeout(Duration::from_millis(PDF_TIMEOUT_MS), receiver).await {
+        Ok(Ok(result)) => result?,
+        Ok(Err(_)) => return Err("PDF rendering was cancelled".to_string()),
+        Err(_) => return Err(format!("PDF rendering timed out after {}ms", PDF_TIMEOUT_MS)),
+    };
+    if let Some(bytes) = rendered {
+        let path = path.to_path_buf();
+        tauri::async_runtime::spawn_blocking(move || std::fs::write(&path, bytes))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to write PDF: {}", e))?;
+    }
+    Ok(())
+}
+
+/// WKWebView renders the document as a single continuous page, so the paper
+/// size and orientation don't apply, and backgrounds are always included.
+#[cfg(target_os = "macos")]
+fn start_pdf(
+    webview: &Webview,
+    _path: PathBuf,
+    _options: PdfOptions,
+    sender: PdfSender,
+) -> Result<(), String> {
+    use std::cell::Cell;
+
+    use block2::RcBlock;
+    use objc2_foundation::{NSData, NSError};
+    use objc2_web_kit::WKWebView;
+
+    webview
+        .with_webview(move |platform| {
+            let sender = Cell::new(Some(sender));
+            let handler = RcBlock::new(move |data: *mut NSData, error: *mut NSError| {
+                let Some(sender) = sender.take() else {
+                    return;
+                };
+                let result = match unsafe { data.as_ref() } {
+                    Some(data) => Ok(Some(data.to_vec())),
+                    None => Err(match unsafe { error.as_ref() } {
+                        Some(error) => format!("PDF rendering failed: {}", error.localizedDescription()),
+                        None => "WKWebView returned no PDF".to_string(),
+                    }),
+                };
+                let _ = sender.send(result);
+            });
+            unsafe {
+                let view: &WKWebView = &*platform.inner().cast();
+                view.createPDFWithConfiguration_completionHandler(None, &handler);
+            }
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))
+}
+
+/// Prints through GTK's "Print to File" backend, which writes the PDF itself.
+#[cfg(target_os = "linux")]
+fn start_pdf(
+    webview: &Webview,
+    path: PathBuf,
+    options: PdfOptions,
+    sender: PdfSender,
+) -> Result<(), String> {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use webkit2gtk::{PrintOperation, PrintOperationExt, SettingsExt, WebViewExt};
+
+    let uri = url::Url::from_file_path(&path)
+        .map_err(|_| format!("Invalid PDF path: {}", path.display()))?
+        .to_string();
+    webview
+        .with_webview(move |platform| {
+            let view = platform.inner();
+            if let Some(settings) = WebViewExt::settings(&view) {
+                settings.set_print_backgrounds(options.print_background);
+            }
+
+            let print_settings = gtk::PrintSettings::new();
+            print_settings.set_printer("Print to File");
+            print_settings.set(gtk::PRINT_SETTINGS_OUTPUT_FILE_FORMAT, Some("pdf"));
+            print_settings.set(gtk::PRINT_SETTINGS_OUTPUT_URI, Some(&uri));
+            let page_setup = gtk::PageSetup::new();
+            if let Some(name) = options.paper_size.as_deref().and_then(gtk_paper_name) {
+                let paper = gtk::PaperSize::new(Some(name));
+                print_settings.set_paper_size(&paper);
+                page_setup.set_paper_size_and_default_margins(&paper);
+            }
+            let orientation = if options.landscape {
+                gtk::PageOrientation::Landscape
+            } else {
+                gtk::PageOrientation::Portrait
+            };
+            print_settings.set_orientation(orientation);
+            page_setup.set_orientation(orientation);
+
+            let operation = PrintOperation::new(&view);
+            operation.set_print_settings(&print_settings);
+            operation.set_page_setup(&page_setup);
+            // `failed` is always followed by `finished`; whichever fires first reports.
+            let sender = Rc::new(Cell::new(Some(sender)));
+            let failed_sender = Rc::clone(&sender);
+            operation.connect_failed(move |_, error| {
+                if let Some(sender) = failed_sender.take() {
+                    let _ = sender.send(Err(format!("PDF rendering failed: {}", error)));
+                }
+            });
+            operation.connect_finished(move |_| {
+                if let Some(sender) = sender.take() {
+                    let _ = sender.send(Ok(None));
+                }
+            });
+            operation.print();
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn gtk_paper_name(paper_size: &str) -> Option<&'static str> {
+    let name = match paper_size {
+        "a3" => gtk::PAPER_NAME_A3,
+        "a4" => gtk::PAPER_NAME_A4,
+        "a5" => gtk::PAPER_NAME_A5,
+        "letter" => gtk::PAPER_NAME_LETTER,
+        "legal" => gtk::PAPER_NAME_LEGAL,
+        _ => return None,
+    };
+    Some(name.as_str())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn start_pdf(
+    _webview: &Webview,
+    _path: PathBuf,
+    _options: PdfOptions,
+    _sender: PdfSender,
+) -> Result<(), String> {
+    Err("PDF export is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn start_snapshot(
+    _webview: &Webview,
+    _max_width: Option<u32>,
+    _sender: SnapshotSender,
+) -> Result<(), String> {
+    Err("Tab capture is not supported on this platform".to_string())
+}
+
+/// Pixel height of a PNG, read from its header.
+pub fn png_height(png: &[u8]) -> Result<u32, String> {
+    let reader = png::Decoder::new(std::io::Cursor::new(png))
+        .read_info()
+        .map_err(|e| format!("Failed to decode snapshot: {}", e))?;
+    Ok(reader.info().height)
+}
+
+/// Decode a PNG into 8-bit RGBA rows.
+fn decode_rgba(png: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    let mut decoder = png::Decoder::new(std::io::Cursor::new(png));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| format!("Failed to decode snapshot: {}", e))?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buf)
+        .map_err(|e| format!("Failed to decode snapshot: {}", e))?;
+    buf.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => buf
+            .chunks_exact(3)
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buf
+            .chunks_exact(2)
+            .flat_map(|px| [px[0], px[0], px[0], px[1]])
+            .collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        png::ColorType::Indexed => return Err("Unexpected indexed snapshot".to_string()),
+    };
+    Ok((info.width, info.height, rgba))
+}
+
+fn encode_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to encode screenshot: {}", e))?;
+    writer
+        .write_image_data(rgba)
+        .map_err(|e| format!("Failed to encode screenshot: {}", e))?;
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to encode screenshot: {}", e))?;
+    Ok(png)
+}
+
+/// Stitch viewport captures into one PNG. Each segment is the CSS scroll offset
+/// it was taken at plus its PNG; device pixels per CSS pixel are derived from
+/// the first segment's height against `viewport_height`. Output is capped at
+/// `max_height` pixels; segments starting past the cap are dropped.
+pub fn stitch_segments(
+    segments: &[(f64, Vec<u8>)],
+    page_height: f64,
+    viewport_height: f64,
+    max_height: u32,
+) -> Result<Vec<u8>, String> {
+    let Some((_, first)) = segments.first() else {
+        return Err("No segments to stitch".to_string());
+    };
+    let (width, first_height, _) = decode_rgba(first)?;
+    let scale = if viewport_height > 0.0 {
+        first_height as f64 / viewport_height
+    } else {
+        1.0
+    };
+    let height = ((page_height * scale).round() as u32).clamp(1, max_height);
+    let row_bytes = width as usize * 4;
+    let mut canvas = vec![0u8; row_bytes * height as usize];
+
+    for (offset, png) in segments {
+        let (segment_width, segment_height, rgba) = decode_rgba(png)?;
+        if segment_width != width {
+            return Err("Viewport width changed while capturing".to_string());
+        }
+        let top = (offset * scale).round().max(0.0) as u32;
+        if top >= height {
+            break;
+        }
+        let rows = segment_height.min(height - top);
+        let start = top as usize * row_bytes;
+        let len = rows as usize * row_bytes;
+        canvas[start..start + len].copy_from_slice(&rgba[..len]);
+    }
+
+    encode_rgba(width, height, &canvas)
+}
+
+fn frame_hash(png: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    png.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn heartbeat_is_current(app: &AppHandle, generation: u64) -> bool {
+    match app.state::<Mutex<CaptureState>>().lock() {
+        Ok(state) => state.heartbeat_running && state.heartbeat_generation == generation,
+        Err(_) => false,
+    }
+}
+
+/// The focused window's active tab webview, unless there is none or it is
+/// still loading.
+fn heartbeat_target(app: &AppHandle) -> Option<(String, Webview)> {
+    let state = tabs::window_state(app, &tabs::focused_window_label(app)).ok()?;
+    let state = state.lock().ok()?;
+    let tab_id = state.active_tab.clone()?;
+    if state.loading.contains(&tab_id) {
+        return None;
+    }
+    let webview = app.get_webview(&format!("tab-{}", tab_id))?;
+    Some((tab_id, webview))
+}
+
+async fn heartbeat_tick(app: &AppHandle) {
+    let awaiting_ack = match app.state::<Mutex<CaptureState>>().lock() {
+        Ok(state) => state.awaiting_ack,
+        Err(_) => return,
+    };
+    if awaiting_ack {
+        return;
+    }
+    let Some((tab_id, webview)) = heartbeat_target(app) else {
+        return;
+    };
+    let png = match capture_webview_png(&webview, Some(HEARTBEAT_MAX_WIDTH)).await {
+        Ok(png) => png,
+        Err(e) => {
+            log::warn!("[Capture] heartbeat frame for {} failed: {}", tab_id, e);
+            return;
+        }
+    };
+    let hash = frame_hash(&png);
+    let sequence = {
+        let state = app.state::<Mutex<CaptureState>>();
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        if state.last_frame_hash == Some(hash) {
+            return;
+        }
+        state.last_frame_hash = Some(hash);
+        state.awaiting_ack = true;
+        state.next_sequence += 1;
+        state.next_sequence
+    };
+    let _ = app.emit(
+        "tab-heartbeat-frame",
+        serde_json::json!({
+            "tabId": tab_id,
+            "sequence": sequence,
+            "png": base64::engine::general_purpose::STANDARD.encode(&png),
+        }),
+    );
+}
+
+/// Periodically emit a downscaled PNG of the active tab as `tab-heartbeat-frame`.
+/// Restarting replaces the previous heartbeat.
+#[tauri::command]
+pub fn start_screenshot_heartbeat(
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<CaptureState>>,
+    interval_ms: u64,
+) -> Result<(), String> {
+    let interval = Duration::from_millis(interval_ms.max(MIN_HEARTBEAT_INTERVAL_MS));
+    let generation = {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        state.heartbeat_generation += 1;
+        state.heartbeat_running = true;
+        state.awaiting_ack = false;
+        state.last_frame_hash = None;
+        state.heartbeat_generation
+    };
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+            if !heartbeat_is_current(&app, generation) {
+                break;
+            }
+            heartbeat_tick(&app).await;
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_screenshot_heartbeat(
+    state: tauri::State<'_, Mutex<CaptureState>>,
+) -> Result<(), String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    state.heartbeat_generation += 1;
+    state.heartbeat_running = false;
+    state.awaiting_ack = false;
+    Ok(())
+}
+
+/// Mark the last `tab-heartbeat-frame` as consumed so the next one can be sent.
+#[tauri::command]
+pub fn ack_screenshot_heartbeat_frame(
+    state: tauri::State<'_, Mutex<CaptureState>>,
+) -> Result<(), String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    state.awaiting_ack = false;
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+use tauri::webview::Cookie;
+use tauri::{AppHandle, Manager, Webview};
+use time::OffsetDateTime;
+
+/// A cookie as exchanged with the frontend. `expires` is a Unix timestamp in
+/// seconds; `None` means a session cookie.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CookieInfo {
+    pub name: String,
+    pub value: String,
+    pub domain: Option<String>,
+    pub path: Option<String>,
+    pub expires: Option<i64>,
+    pub http_only: bool,
+    pub secure: bool,
+}
+
+impl From<&Cookie<'_>> for CookieInfo {
+    fn from(cookie: &Cookie<'_>) -> Self {
+        Self {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: cookie.domain().map(str::to_string),
+            path: cookie.path().map(str::to_string),
+            expires: cookie.expires_datetime().map(|at| at.unix_timestamp()),
+            http_only: cookie.http_only().unwrap_or(false),
+            secure: cookie.secure().unwrap_or(false),
+        }
+    }
+}
+
+fn tab_webview(app: &AppHandle, tab_id: &str) -> Result<Webview, String> {
+    app.get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Tab {} not found", tab_id))
+}
+
+/// Whether a cookie's domain attribute covers `domain` or one of its subdomains.
+fn cookie_in_domain(cookie: &Cookie<'_>, domain: &str) -> bool {
+    let Some(cookie_domain) = cookie.domain() else {
+        return false;
+    };
+    let cookie_domain = cookie_domain.trim_start_matches('.').to_ascii_lowercase();
+    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+    cookie_domain == domain || cookie_domain.ends_with(&format!(".{}", domain))
+}
+
+// The cookie commands are async because WebView2 deadlocks when its cookie
+// manager is used from the main thread.
+
+/// Cookies the platform store would send to the tab's current URL,
+/// including HTTP-only ones.
+#[tauri::command]
+pub async fn get_tab_cookies(app: AppHandle, tab_id: String) -> Result<Vec<CookieInfo>, String> {
+    let webview = tab_webview(&app, &tab_id)?;
+    let url = webview.url().map_err(|e| e.to_string())?;
+    let cookies = webview
+        .cookies_for_url(url)
+        .map_err(|e| format!("Failed to read cookies: {}", e))?;
+    Ok(cookies.iter().map(CookieInfo::from).collect())
+}
+
+/// Set a cookie in the tab's cookie store. The domain defaults to the host of
+/// the tab's current URL and the path to `/`.
+#[tauri::command]
+pub async fn set_tab_cookie(app: AppHandle, tab_id: String, cookie: CookieInfo) -> Result<(), String> {
+    if cookie.name.is_empty() {
+        return Err("Cookie name must not be empty".to_string());
+    }
+    let webview = tab_webview(&app, &tab_id)?;
+    let domain = match cookie.domain {
+        Some(domain) => domain,
+        None => webview
+            .url()
+            .map_err(|e| e.to_string())?
+            .host_str()
+            .ok_or("Tab has no host to scope the cookie to")?
+            .to_string(),
+    };
+
+    let mut builder = Cookie::build((cookie.name, cookie.value))
+        .domain(domain)
+        .path(cookie.path.unwrap_or_else(|| "/".to_string()))
+        .http_only(cookie.http_only)
+        .secure(cookie.secure);
+    if let Some(expires) = cookie.expires {
+        let at = OffsetDateTime::from_unix_timestamp(expires)
+            .map_err(|e| format!("Invalid cookie expiry: {}", e))?;
+        builder = builder.expires(at);
+    }
+    webview
+        .set_cookie(builder.build())
+        .map_err(|e| format!("Failed to set cookie: {}", e))
+}
+
+/// Delete the cookies in the tab's cookie store, or only those scoped to
+/// `domain` (and its subdomains). Returns how many were removed.
+#[tauri::command]
+pub async fn clear_tab_cookies(
+    app: AppHandle,
+    tab_id: String,
+    domain: Option<String>,
+) -> Result<usize, String> {
+    let webview = tab_webview(&app, &tab_id)?;
+    let cookies = webview
+        .cookies()
+        .map_err(|e| format!("Failed to read cookies: {}", e))?;
+    let mut removed = 0;
+    for cookie in cookies {
+        if domain.as_deref().is_some_and(|domain| !cookie_in_domain(&cookie, domain)) {
+            continue;
+        }
+        webview
+            .delete_cookie(cookie)
+            .map_err(|e| format!("Failed to delete cookie: {}", e))?;
+        removed += 1;
+    }
+    Ok(removed)
+}
@@ -1,6 +1,9 @@
+#[cfg(any(debug_assertions, feature = "devtools"))]
 use std::time::Duration;
 
-use tauri::{AppHandle, Manager};
+use tauri::AppHandle;
+#[cfg(any(debug_assertions, feature = "devtools"))]
+use tauri::Manager;
 
 #[cfg(all(target_os = "macos", any(debug_assertions, feature = "devtools")))]
 use objc2::rc::Retained;
@@ -10,8 +13,17 @@ use objc2::runtime::{AnyObject, Bool};
 use objc2::{msg_send, sel};
 #[cfg(all(target_os = "macos", any(debug_assertions, feature = "devtools")))]
 use objc2_web_kit::WKWebView;
-#[cfg(all(target_os = "macos", any(debug_assertions, feature = "devtools")))]
+#[cfg(any(debug_assertions, feature = "devtools"))]
 use tauri::Webview;
+#[cfg(all(any(target_os = "macos", target_os = "linux"), any(debug_assertions, feature = "devtools")))]
+use tokio::sync::oneshot;
+
+#[cfg(any(debug_assertions, feature = "devtools"))]
+use crate::tabs::{self, WebviewDebugInfo};
+
+/// How long `native_visibility` waits for the platform view to answer.
+#[cfg(all(any(target_os = "macos", target_os = "linux"), any(debug_assertions, feature = "devtools")))]
+const NATIVE_QUERY_TIMEOUT_MS: u64 = 1_000;
 
 #[cfg(all(target_os = "macos", any(debug_assertions, feature = "devtools")))]
 fn detach_inspector(webview: &Webview) {
@@ -48,3 +60,90 @@ pub fn watch_webview_devtools(app: AppHandle, label: String) {
 
 #[cfg(not(all(target_os = "macos", any(debug_assertions, feature = "devtools"))))]
 pub fn watch_webview_devtools(_app: AppHandle, _label: String) {}
+
+/// Open or close the Web Inspector of a content tab.
+#[cfg(any(debug_assertions, feature = "devtools"))]
+pub fn set_tab_devtools_open(app: &AppHandle, tab_id: &str, open: bool) -> Result<(), String> {
+    let webview = app
+        .get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    if open {
+        webview.open_devtools();
+    } else {
+        webview.close_devtools();
+    }
+    Ok(())
+}
+
+#[cfg(not(any(debug_assertions, feature = "devtools")))]
+pub fn set_tab_devtools_open(_app: &AppHandle, _tab_id: &str, _open: bool) -> Result<(), String> {
+    Err("DevTools are not available in this build".to_string())
+}
+
+/// Whether the platform view of `webview` is shown, asked of the view itself
+/// rather than taken from `TabState`.
+#[cfg(all(target_os = "macos", any(debug_assertions, feature = "devtools")))]
+pub async fn native_visibility(webview: &Webview) -> Option<bool> {
+    let (sender, receiver) = oneshot::channel();
+    webview
+        .with_webview(move |platform| unsafe {
+            let view: &WKWebView = &*platform.inner().cast();
+            let hidden: Bool = msg_send![view, isHidden];
+            let _ = sender.send(!hidden.as_bool());
+        })
+        .ok()?;
+    tokio::time::timeout(Duration::from_millis(NATIVE_QUERY_TIMEOUT_MS), receiver)
+        .await
+        .ok()?
+        .ok()
+}
+
+#[cfg(all(target_os = "linux", any(debug_assertions, feature = "devtools")))]
+pub async fn native_visibility(webview: &Webview) -> Option<bool> {
+    use gtk::prelude::WidgetExt;
+
+    let (sender, receiver) = oneshot::channel();
+    webview
+        .with_webview(move |platform| {
+            let _ = sender.send(platform.inner().is_visible());
+        })
+        .ok()?;
+    tokio::time::timeout(Duration::from_millis(NATIVE_QUERY_TIMEOUT_MS), receiver)
+        .await
+        .ok()?
+        .ok()
+}
+
+#[cfg(all(
+    not(any(target_os = "macos", target_os = "linux")),
+    any(debug_assertions, feature = "devtools")
+))]
+pub async fn native_visibility(_webview: &Webview) -> Option<bool> {
+    None
+}
+
+/// Each tab's native webview state in a window next to `TabState`'s view of it.
+#[cfg(any(debug_assertions, feature = "devtools"))]
+pub async fn list_webviews(app: &AppHandle, window_label: &str) -> Result<Vec<WebviewDebugInfo>, String> {
+    tabs::debug_webviews(app, window_label).await
+}
+
+#[cfg(not(any(debug_assertions, feature = "devtools")))]
+pub async fn list_webviews(_app: &AppHandle, _window_label: &str) -> Result<Vec<serde_json::Value>, String> {
+    Err("DevTools are not available in this build".to_string())
+}
+
+/// Toggle the Web Inspector of a content tab.
+#[cfg(any(debug_assertions, feature = "devtools"))]
+pub fn toggle_tab_devtools(app: &AppHandle, tab_id: &str) -> Result<(), String> {
+    let open = app
+        .get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?
+        .is_devtools_open();
+    set_tab_devtools_open(app, tab_id, !open)
+}
+
+#[cfg(not(any(debug_assertions, feature = "devtools")))]
+pub fn toggle_tab_devtools(_app: &AppHandle, _tab_id: &str) -> Result<(), String> {
+    Err("DevTools are not available in this build".to_string())
+}
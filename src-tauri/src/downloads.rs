@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::webview::DownloadEvent;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::workspace;
+
+/// How often an in-flight download's file size is reported.
+const PROGRESS_INTERVAL_MS: u64 = 500;
+
+/// Downloads started from content webviews, keyed by download id.
+pub struct DownloadState {
+    next_id: u64,
+    active: BTreeMap<u64, Download>,
+}
+
+struct Download {
+    tab_id: String,
+    url: String,
+    path: PathBuf,
+    /// Set by `cancel_download`. The platform webview can't abort a transfer
+    /// through Tauri, so the file is discarded once it finishes instead.
+    cancelled: bool,
+}
+
+impl DownloadState {
+    pub fn new() -> Self {
+        Self {
+            next_id: 1,
+            active: BTreeMap::new(),
+        }
+    }
+}
+
+/// `name` inside `dir`, with ` (1)`, ` (2)`, ... appended to the stem until
+/// it doesn't collide with an existing file.
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+    let name_path = Path::new(name);
+    let stem = name_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(name);
+    let ext = name_path
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| format!(".{ext}"))
+        .unwrap_or_default();
+    let mut counter = 1;
+    loop {
+        let candidate = dir.join(format!("{stem} ({counter}){ext}"));
+        if !candidate.exists() {
+            return candidate;
+        }
+        counter += 1;
+    }
+}
+
+/// The platform's suggested file name, falling back to the URL's last path segment.
+fn suggested_filename(url: &tauri::Url, destination: &Path) -> String {
+    destination
+        .file_name()
+        .and_then(|s| s.to_str())
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            url.path_segments()
+                .and_then(|mut segments| segments.next_back())
+                .filter(|segment| !segment.is_empty())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// Handle a content webview's download event: redirect the file into
+/// `<workspace>/downloads` and report `tab-download-started`,
+/// `tab-download-progress` and `tab-download-finished`. Always lets the
+/// download proceed.
+pub fn handle_download_event(app: &AppHandle, tab_id: &str, event: DownloadEvent<'_>) -> bool {
+    match event {
+        DownloadEvent::Requested { url, destination } => {
+            let filename = suggested_filename(&url, destination);
+            if let Some(dir) = workspace::workspace_dir().map(|dir| dir.join("downloads")) {
+                match fs::create_dir_all(&dir) {
+                    Ok(()) => *destination = unique_path(&dir, &filename),
+                    Err(e) => log::error!("[Downloads] Failed to create {}: {}", dir.display(), e),
+                }
+            }
+            download_started(app, tab_id, url.as_str(), &filename, destination.clone());
+        }
+        DownloadEvent::Finished { url, path, success } => {
+            download_finished(app, tab_id, url.as_str(), path, success);
+        }
+        _ => {}
+    }
+    true
+}
+
+fn download_started(app: &AppHandle, tab_id: &str, url: &str, filename: &str, path: PathBuf) {
+    let id = {
+        let state = app.state::<Mutex<DownloadState>>();
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        let id = state.next_id;
+        state.next_id += 1;
+        state.active.insert(
+            id,
+            Download {
+                tab_id: tab_id.to_string(),
+                url: url.to_string(),
+                path: path.clone(),
+                cancelled: false,
+            },
+        );
+        id
+    };
+
+    // No platform reports the content length through Tauri, so `totalBytes` is unknown.
+    let _ = app.emit(
+        "tab-download-started",
+        serde_json::json!({
+            "downloadId": id,
+            "tabId": tab_id,
+            "url": url,
+            "suggestedFilename": filename,
+            "path": path,
+            "totalBytes": null,
+        }),
+    );
+    spawn_progress_watcher(app.clone(), id, tab_id.to_string(), path);
+}
+
+/// Report the growing file size until the download leaves the active set.
+fn spawn_progress_watcher(app: AppHandle, id: u64, tab_id: String, path: PathBuf) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_bytes = 0;
+        loop {
+            tokio::time::sleep(Duration::from_millis(PROGRESS_INTERVAL_MS)).await;
+            let active = match app.state::<Mutex<DownloadState>>().lock() {
+                Ok(state) => state.active.get(&id).is_some_and(|d| !d.cancelled),
+                Err(_) => false,
+            };
+            if !active {
+                break;
+            }
+            let received = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+            if received == last_bytes {
+                continue;
+            }
+            last_bytes = received;
+            let _ = app.emit(
+                "tab-download-progress",
+                serde_json::json!({
+                    "downloadId": id,
+                    "tabId": tab_id,
+                    "receivedBytes": received,
+                    "totalBytes": null,
+                }),
+            );
+        }
+    });
+}
+
+fn download_finished(app: &AppHandle, tab_id: &str, url: &str, path: Option<PathBuf>, success: bool) {
+    // Finish events only carry the URL, so match the oldest active download of it.
+    let finished = {
+        let state = app.state::<Mutex<DownloadState>>();
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        let id = state
+            .active
+            .iter()
+            .find(|(_, d)| d.tab_id == tab_id && d.url == url)
+            .map(|(id, _)| *id);
+        id.and_then(|id| state.active.remove(&id).map(|download| (id, download)))
+    };
+    let Some((id, download)) = finished else {
+        return;
+    };
+
+    if download.cancelled {
+        // `tab-download-finished` was already emitted by `cancel_download`.
+        let _ = fs::remove_file(&download.path);
+        return;
+    }
+    let _ = app.emit(
+        "tab-download-finished",
+        serde_json::json!({
+            "downloadId": id,
+            "tabId": tab_id,
+            "url": url,
+            "path": path.unwrap_or(download.path),
+            "success": success,
+            "cancelled": false,
+        }),
+    );
+}
+
+/// Cancel a download by id. The transfer may run to completion in the
+/// background, but its file is deleted and it is reported as cancelled now.
+#[tauri::command]
+pub fn cancel_download(
+    app: AppHandle,
+    state: tauri::State<'_, Mutex<DownloadState>>,
+    download_id: u64,
+) -> Result<(), String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let download = state
+        .active
+        .get_mut(&download_id)
+        .filter(|d| !d.cancelled)
+        .ok_or_else(|| format!("Download {} not found", download_id))?;
+    download.cancelled = true;
+    let _ = fs::remove_file(&download.path);
+
+    let _ = app.emit(
+        "tab-download-finished",
+        serde_json::json!({
+            "downloadId": download_id,
+            "tabId": download.tab_id,
+            "url": download.url,
+            "path": download.path,
+            "success": false,
+            "cancelled": true,
+        }),
+    );
+    Ok(())
+}
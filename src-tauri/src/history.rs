@@ -0,0 +1,347 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::{settings, workspace};
+
+const HISTORY_FILE: &str = "history.jsonl";
+const DEFAULT_QUERY_LIMIT: usize = 10;
+const MAX_SUGGESTIONS: usize = 8;
+/// The log is rewritten once it holds this many superseded lines.
+const COMPACT_AFTER_LINES: usize = 1000;
+const MS_PER_DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// One visited URL. The history file is a log of these, one per line; a later
+/// line for the same URL supersedes earlier ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub url: String,
+    pub title: String,
+    /// Unix milliseconds of the most recent visit.
+    pub visited_at: u64,
+    pub visit_count: u32,
+}
+
+/// Browsing history, loaded from disk on first use and kept in visit order.
+pub struct HistoryState {
+    entries: Option<IndexMap<String, HistoryEntry>>,
+    /// Lines in the history file that a later line supersedes.
+    stale_lines: usize,
+}
+
+impl HistoryState {
+    pub fn new() -> Self {
+        Self {
+            entries: None,
+            stale_lines: 0,
+        }
+    }
+
+    fn entries(&mut self) -> &mut IndexMap<String, HistoryEntry> {
+        if self.entries.is_none() {
+            let mut lines = 0;
+            let mut entries = IndexMap::new();
+            if let Some(raw) = history_path().and_then(|path| fs::read_to_string(path).ok()) {
+                for entry in raw.lines().filter_map(|line| serde_json::from_str::<HistoryEntry>(line).ok()) {
+                    lines += 1;
+                    entries.shift_remove(&entry.url);
+                    entries.insert(entry.url.clone(), entry);
+                }
+            }
+            let unpruned = entries.len();
+            prune_expired(&mut entries);
+            let pruned = entries.len() != unpruned;
+            self.stale_lines = lines - entries.len();
+            self.entries = Some(entries);
+            if pruned {
+                if let Err(e) = self.compact() {
+                    log::error!("[History] {}", e);
+                }
+            }
+        }
+        self.entries.get_or_insert_with(IndexMap::new)
+    }
+
+    /// Rewrite the history file with one line per entry.
+    fn compact(&mut self) -> Result<(), String> {
+        let Some(entries) = &self.entries else {
+            return Ok(());
+        };
+        let raw: String = entries
+            .values()
+            .filter_map(|entry| serde_json::to_string(entry).ok())
+            .map(|line| line + "\n")
+            .collect();
+        replace_file(&raw)?;
+        self.stale_lines = 0;
+        Ok(())
+    }
+
+    /// Save the latest state of one entry, compacting when the log has grown stale.
+    fn persist(&mut self, url: &str, superseded: bool) {
+        if superseded {
+            self.stale_lines += 1;
+        }
+        if self.stale_lines >= COMPACT_AFTER_LINES {
+            if let Some(entries) = &mut self.entries {
+                prune_expired(entries);
+            }
+            if let Err(e) = self.compact() {
+                log::error!("[History] {}", e);
+            }
+            return;
+        }
+        let Some(entry) = self.entries.as_ref().and_then(|entries| entries.get(url)) else {
+            return;
+        };
+        if let Err(e) = append_line(entry) {
+            log::error!("[History] {}", e);
+        }
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    Some(workspace::workspace_dir()?.join(HISTORY_FILE))
+}
+
+fn append_line(entry: &HistoryEntry) -> Result<(), String> {
+    let path = history_path().ok_or("Workspace directory not found")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", line))
+        .map_err(|e| format!("Failed to write history: {}", e))
+}
+
+fn replace_file(raw: &str) -> Result<(), String> {
+    let path = history_path().ok_or("Workspace directory not found")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let tmp_path = path.with_extension("jsonl.tmp");
+    fs::write(&tmp_path, raw).map_err(|e| format!("Failed to write history: {}", e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save history: {}", e))
+}
+
+/// Drop entries last visited before the configured retention horizon.
+fn prune_expired(entries: &mut IndexMap<String, HistoryEntry>) {
+    let days = settings::load().history_retention_days;
+    if days == 0 {
+        return;
+    }
+    let cutoff = crate::tabs::now_ms().saturating_sub(u64::from(days) * MS_PER_DAY);
+    entries.retain(|_, entry| entry.visited_at >= cutoff);
+}
+
+/// Only web pages are worth remembering.
+fn is_recordable(url: &str) -> bool {
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+/// Record a finished navigation, bumping the visit count of a known URL.
+/// Callers must skip incognito tabs.
+pub fn record_visit(app: &tauri::AppHandle, url: &str, title: &str) {
+    if !is_recordable(url) {
+        return;
+    }
+    let state = app.state::<Mutex<HistoryState>>();
+    let Ok(mut state) = state.lock() else {
+        return;
+    };
+    let entries = state.entries();
+    let mut entry = entries.shift_remove(url).unwrap_or_else(|| HistoryEntry {
+        url: url.to_string(),
+        title: String::new(),
+        visited_at: 0,
+        visit_count: 0,
+    });
+    let superseded = entry.visit_count > 0;
+    entry.visit_count += 1;
+    entry.visited_at = crate::tabs::now_ms();
+    if !title.is_empty() {
+        entry.title = title.to_string();
+    }
+    entries.insert(url.to_string(), entry);
+    state.persist(url, superseded);
+}
+
+/// Update the title of a recorded URL once the page reports it.
+pub fn update_title(app: &tauri::AppHandle, url: &str, title: &str) {
+    if title.is_empty() || !is_recordable(url) {
+        return;
+    }
+    let state = app.state::<Mutex<HistoryState>>();
+    let Ok(mut state) = state.lock() else {
+        return;
+    };
+    let Some(entry) = state.entries().get_mut(url) else {
+        return;
+    };
+    if entry.title == title {
+        return;
+    }
+    entry.title = title.to_string();
+    state.persist(url, true);
+}
+
+/// `url` without its scheme and a leading `www.`, for prefix matching.
+fn url_key(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.strip_prefix("www.").unwrap_or(rest)
+}
+
+/// History entries whose URL starts with `prefix` (ignoring the scheme and
+/// `www.`) or whose title contains it, most visited first, for address-bar
+/// autocomplete. An empty prefix lists the most visited pages.
+#[tauri::command]
+pub fn query_history(
+    state: tauri::State<'_, Mutex<HistoryState>>,
+    prefix: String,
+    limit: Option<usize>,
+) -> Result<Vec<HistoryEntry>, String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let prefix = prefix.trim().to_lowercase();
+    let mut matches: Vec<HistoryEntry> = state
+        .entries()
+        .values()
+        .filter(|entry| {
+            let url = entry.url.to_lowercase();
+            url.starts_with(&prefix)
+                || url_key(&url).starts_with(url_key(&prefix))
+                || entry.title.to_lowercase().contains(&prefix)
+        })
+        .cloned()
+        .collect();
+    matches.sort_by(|a, b| {
+        b.visit_count
+            .cmp(&a.visit_count)
+            .then(b.visited_at.cmp(&a.visited_at))
+    });
+    matches.truncate(limit.unwrap_or(DEFAULT_QUERY_LIMIT));
+    Ok(matches)
+}
+
+/// Forget pages last visited at or after `since` (Unix milliseconds), or all
+/// history when `since` is omitted.
+#[tauri::command]
+pub fn clear_history(state: tauri::State<'_, Mutex<HistoryState>>, since: Option<u64>) -> Result<(), String> {
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let since = since.unwrap_or(0);
+    state.entries().retain(|_, entry| entry.visited_at < since);
+    state.compact()?;
+    log::info!("[History] Cleared history since {}", since);
+    Ok(())
+}
+
+/// Where a `suggest_url` result came from.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionSource {
+    History,
+    Bookmark,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UrlSuggestion {
+    pub url: String,
+    pub title: String,
+    pub source: SuggestionSource,
+}
+
+/// How well a URL matches the typed input; lower ranks sort first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchRank {
+    /// The host starts with the input, e.g. "git" for github.com.
+    Host,
+    /// The URL, minus scheme and `www.`, starts with the input.
+    Url,
+    /// The title contains the input.
+    Title,
+}
+
+fn starts_with_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.len() >= needle.len() && haystack.as_bytes()[..needle.len()].eq_ignore_ascii_case(needle.as_bytes())
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    needle.is_empty()
+        || haystack
+            .as_bytes()
+            .windows(needle.len())
+            .any(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn match_rank(url: &str, title: &str, input: &str) -> Option<MatchRank> {
+    let key = url_key(url);
+    if starts_with_ignore_case(key, input) {
+        let host_len = key.find(['/', ':', '?', '#']).unwrap_or(key.len());
+        return Some(if input.len() <= host_len { MatchRank::Host } else { MatchRank::Url });
+    }
+    contains_ignore_case(title, input).then_some(MatchRank::Title)
+}
+
+/// Visit count decayed by a week-long half-life, so recent pages outrank
+/// ones that were popular long ago.
+fn frecency(entry: &HistoryEntry, now: u64) -> f64 {
+    let age_weeks = now.saturating_sub(entry.visited_at) as f64 / (7 * MS_PER_DAY) as f64;
+    f64::from(entry.visit_count) * 0.5f64.powf(age_weeks)
+}
+
+/// Ranked address-bar suggestions for `input` from history and bookmarks.
+/// Host-prefix matches come first, then URL and title matches, each ordered
+/// by frecency; a bookmark ranks as if visited once more. Only the returned
+/// suggestions are copied, so this is cheap enough for every keystroke.
+#[tauri::command]
+pub fn suggest_url(
+    state: tauri::State<'_, Mutex<HistoryState>>,
+    input: String,
+) -> Result<Vec<UrlSuggestion>, String> {
+    let input = url_key(input.trim());
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let entries = state.entries();
+    let now = crate::tabs::now_ms();
+
+    crate::bookmarks::with_bookmarks(|bookmarks| {
+        let bookmarked: HashSet<&str> = bookmarks.iter().map(|bookmark| bookmark.url.as_str()).collect();
+        let mut candidates: Vec<(MatchRank, f64, &str, &str, SuggestionSource)> = Vec::new();
+        for bookmark in bookmarks {
+            if let Some(rank) = match_rank(&bookmark.url, &bookmark.title, input) {
+                let score = entries.get(&bookmark.url).map_or(0.0, |entry| frecency(entry, now)) + 1.0;
+                candidates.push((rank, score, &bookmark.url, &bookmark.title, SuggestionSource::Bookmark));
+            }
+        }
+        for entry in entries.values() {
+            if bookmarked.contains(entry.url.as_str()) {
+                continue;
+            }
+            if let Some(rank) = match_rank(&entry.url, &entry.title, input) {
+                candidates.push((rank, frecency(entry, now), &entry.url, &entry.title, SuggestionSource::History));
+            }
+        }
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then(b.1.total_cmp(&a.1)));
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, _, url, title, source)| UrlSuggestion {
+                url: url.to_string(),
+                title: title.to_string(),
+                source,
+            })
+            .collect()
+    })
+}
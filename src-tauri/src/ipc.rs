@@ -1,33 +1,161 @@
-use std::sync::Mutex;
-use tauri::State;
-use crate::tabs::{self, ContentBounds, TabInfo, TabState};
+use std::collections::HashMap;
+use crate::capture::PdfOptions;
+use crate::devtools;
+use crate::settings::{self, LinkInterceptConfig};
+use crate::tabs::{self, ContentBounds, ContextMenuAction, ContextMenuTarget, ElementRect, FindState, OpenTabOptions, ReadableContent, ReadingStats, ScriptRunAt, TabInfo, TabScroll, TabSelection, TabResourceUsage};
+
+/// Open another browser window. Async because creating a window from a
+/// synchronous command deadlocks on Windows.
+#[tauri::command]
+pub async fn new_window(app: tauri::AppHandle) -> Result<String, String> {
+    tabs::new_window(&app)
+}
 
 #[tauri::command]
 pub fn create_tab(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
+    window: tauri::Window,
+    url: String,
+    index: Option<usize>,
+    activate: Option<bool>,
+    background: Option<bool>,
+) -> Result<String, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    let options = OpenTabOptions {
+        index,
+        activate: activate.unwrap_or(true),
+        background: background.unwrap_or(false),
+    };
+    tabs::create_tab(&app, &mut state, &url, options)
+}
+
+#[tauri::command]
+pub fn create_incognito_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
     url: String,
 ) -> Result<String, String> {
+    let state = tabs::window_state(&app, window.label())?;
     let mut state = state.lock().map_err(|e| e.to_string())?;
-    tabs::create_tab(&app, &mut state, &url)
+    tabs::create_incognito_tab(&app, &mut state, &url)
 }
 
 #[tauri::command]
 pub fn close_tab(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
+    window: tauri::Window,
     tab_id: String,
 ) -> Result<Option<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
     let mut state = state.lock().map_err(|e| e.to_string())?;
     tabs::close_tab(&app, &mut state, &tab_id)
 }
 
+#[tauri::command]
+pub async fn request_close_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<bool, String> {
+    tabs::request_close_tab(&app, window.label(), &tab_id).await
+}
+
+#[tauri::command]
+pub fn confirm_close_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+    proceed: bool,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::confirm_close_tab(&app, &mut state, &tab_id, proceed)
+}
+
+#[tauri::command]
+pub fn close_other_tabs(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    keep_tab_id: String,
+) -> Result<Vec<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::close_other_tabs(&app, &mut state, &keep_tab_id)
+}
+
+#[tauri::command]
+pub fn close_tabs_to_right(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<Vec<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::close_tabs_to_right(&app, &mut state, &tab_id)
+}
+
+#[tauri::command]
+pub fn pin_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_tab_pinned(&app, &mut state, &tab_id, true)
+}
+
+#[tauri::command]
+pub fn unpin_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_tab_pinned(&app, &mut state, &tab_id, false)
+}
+
+#[tauri::command]
+pub fn reorder_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+    new_index: usize,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::reorder_tab(&app, &mut state, &tab_id, new_index)
+}
+
+#[tauri::command]
+pub fn reopen_closed_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+) -> Result<Option<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::reopen_closed_tab(&app, &mut state)
+}
+
+#[tauri::command]
+pub fn close_active_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+) -> Result<Option<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::close_active_tab(&app, &mut state)
+}
+
 #[tauri::command]
 pub fn switch_tab(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
+    window: tauri::Window,
     tab_id: String,
 ) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
     let mut state = state.lock().map_err(|e| e.to_string())?;
     tabs::switch_tab(&app, &mut state, &tab_id)
 }
@@ -35,44 +163,194 @@ pub fn switch_tab(
 #[tauri::command]
 pub fn hide_all_tabs(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
-) -> Result<(), String> {
+    window: tauri::Window,
+) -> Result<Option<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
     let state = state.lock().map_err(|e| e.to_string())?;
     tabs::hide_all_tabs(&app, &state)
 }
 
+#[tauri::command]
+pub fn focus_content_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    tabs::focus_content_tab(&app, &state, &tab_id)
+}
+
+#[tauri::command]
+pub fn focus_chrome(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    tabs::focus_chrome(&app, &state)
+}
+
 #[tauri::command]
 pub fn navigate_tab(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
+    window: tauri::Window,
     tab_id: String,
     url: String,
-) -> Result<(), String> {
+) -> Result<String, String> {
+    let state = tabs::window_state(&app, window.label())?;
     let mut state = state.lock().map_err(|e| e.to_string())?;
     tabs::navigate_tab(&app, &mut state, &tab_id, &url)
 }
 
 #[tauri::command]
-pub fn run_js_in_tab(
+pub async fn tab_back(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    tabs::tab_back(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn tab_forward(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    tabs::tab_forward(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn can_go_back(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<bool, String> {
+    tabs::can_go_back(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn can_go_forward(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<bool, String> {
+    tabs::can_go_forward(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn reload_tab(
+    app: tauri::AppHandle,
+    tab_id: String,
+    bypass_cache: Option<bool>,
+) -> Result<(), String> {
+    tabs::reload_tab(&app, &tab_id, bypass_cache.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn stop_tab_loading(
     app: tauri::AppHandle,
     tab_id: String,
-    code: String,
 ) -> Result<(), String> {
-    tabs::run_js_in_tab(&app, &tab_id, &code)
+    tabs::stop_tab_loading(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn capture_tab_screenshot(
+    app: tauri::AppHandle,
+    tab_id: String,
+    full_page: Option<bool>,
+) -> Result<String, String> {
+    tabs::capture_tab_screenshot(&app, &tab_id, full_page.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn print_tab_to_pdf(
+    app: tauri::AppHandle,
+    tab_id: String,
+    path: Option<String>,
+    options: Option<PdfOptions>,
+) -> Result<String, String> {
+    tabs::print_tab_to_pdf(&app, &tab_id, path, options.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub fn open_devtools(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    devtools::set_tab_devtools_open(&app, &tab_id, true)
+}
+
+#[tauri::command]
+pub fn close_devtools(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    devtools::set_tab_devtools_open(&app, &tab_id, false)
+}
+
+#[tauri::command]
+pub async fn debug_list_webviews(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+) -> Result<Vec<impl serde::Serialize>, String> {
+    devtools::list_webviews(&app, window.label()).await
+}
+
+#[tauri::command]
+pub async fn run_js_in_tab(
+    app: tauri::AppHandle,
+    tab_id: String,
+    code: String,
+    timeout_ms: Option<u64>,
+) -> Result<serde_json::Value, String> {
+    tabs::run_js_in_tab(&app, &tab_id, &code, timeout_ms).await
+}
+
+#[tauri::command]
+pub async fn run_js_in_tab_json(
+    app: tauri::AppHandle,
+    tab_id: String,
+    code: String,
+    timeout_ms: Option<u64>,
+) -> Result<tabs::JsEvalOutcome, String> {
+    tabs::run_js_in_tab_json(&app, &tab_id, &code, timeout_ms).await
+}
+
+#[tauri::command]
+pub async fn run_js_file_in_tab(
+    app: tauri::AppHandle,
+    tab_id: String,
+    relative_path: String,
+) -> Result<serde_json::Value, String> {
+    tabs::run_js_file_in_tab(&app, &tab_id, &relative_path).await
 }
 
 #[tauri::command]
 pub fn list_tabs(
-    state: State<'_, Mutex<TabState>>,
+    app: tauri::AppHandle,
+    window: tauri::Window,
 ) -> Result<Vec<TabInfo>, String> {
+    let state = tabs::window_state(&app, window.label())?;
     let state = state.lock().map_err(|e| e.to_string())?;
     Ok(state.tabs.values().cloned().collect())
 }
 
+#[tauri::command]
+pub fn get_tab_info(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<Option<TabInfo>, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    Ok(state.tabs.get(&tab_id).cloned())
+}
+
 #[tauri::command]
 pub fn get_active_tab(
-    state: State<'_, Mutex<TabState>>,
+    app: tauri::AppHandle,
+    window: tauri::Window,
 ) -> Result<Option<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
     let state = state.lock().map_err(|e| e.to_string())?;
     Ok(state.active_tab.clone())
 }
@@ -80,18 +358,366 @@ pub fn get_active_tab(
 #[tauri::command]
 pub fn reposition_tabs(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
+    window: tauri::Window,
+    all_tabs: Option<bool>,
 ) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
     let state = state.lock().map_err(|e| e.to_string())?;
-    tabs::reposition_webviews(&app, &state)
+    tabs::reposition_webviews(&app, &state, all_tabs.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn toggle_focus_mode(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+) -> Result<bool, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::toggle_focus_mode(&app, &mut state)
+}
+
+#[tauri::command]
+pub fn set_ui_zoom(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    factor: f64,
+) -> Result<f64, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_ui_zoom(&app, &mut state, factor)
+}
+
+#[tauri::command]
+pub fn set_link_intercept_config(
+    app: tauri::AppHandle,
+    config: LinkInterceptConfig,
+) -> Result<LinkInterceptConfig, String> {
+    tabs::set_link_intercept_config(&app, config)
+}
+
+#[tauri::command]
+pub async fn get_selection(app: tauri::AppHandle, tab_id: String) -> Result<TabSelection, String> {
+    tabs::get_selection(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub fn set_context_menu_intercept(app: tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    tabs::set_context_menu_intercept(&app, enabled)
+}
+
+#[tauri::command]
+pub async fn context_menu_action(
+    app: tauri::AppHandle,
+    tab_id: String,
+    action: ContextMenuAction,
+    target: ContextMenuTarget,
+) -> Result<(), String> {
+    tabs::context_menu_action(&app, &tab_id, action, target).await
+}
+
+#[tauri::command]
+pub fn set_navigation_blocklist(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    patterns: Vec<String>,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_navigation_blocklist(&state, patterns)
+}
+
+#[tauri::command]
+pub fn clear_navigation_blocklist(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    tabs::clear_navigation_blocklist(&state)
+}
+
+#[tauri::command]
+pub fn set_tab_headers(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+    headers: HashMap<String, String>,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_tab_headers(&mut state, &tab_id, headers)
+}
+
+#[tauri::command]
+pub fn set_tab_zoom(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+    factor: f64,
+) -> Result<f64, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_tab_zoom(&app, &mut state, &tab_id, factor)
+}
+
+#[tauri::command]
+pub async fn hibernate_tab(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    tabs::hibernate_tab(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub fn wake_tab(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::wake_tab(&app, &mut state, &tab_id)
+}
+
+/// Hibernate background tabs idle for `minutes`; 0 turns auto-hibernation off.
+#[tauri::command]
+pub fn set_auto_hibernate_minutes(minutes: u32) -> Result<(), String> {
+    settings::update(|s| s.auto_hibernate_minutes = minutes).map(|_| ())
+}
+
+#[tauri::command]
+pub fn set_tab_muted(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+    muted: bool,
+) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_tab_muted(&app, &mut state, &tab_id, muted)
+}
+
+#[tauri::command]
+pub fn set_user_agent(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+    user_agent: Option<String>,
+) -> Result<Option<String>, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    tabs::set_user_agent(&app, &mut state, &tab_id, user_agent.as_deref())
+}
+
+#[tauri::command]
+pub fn get_tab_zoom(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    tab_id: String,
+) -> Result<f64, String> {
+    let state = tabs::window_state(&app, window.label())?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    tabs::get_tab_zoom(&state, &tab_id)
 }
 
 #[tauri::command]
 pub fn set_content_bounds(
     app: tauri::AppHandle,
-    state: State<'_, Mutex<TabState>>,
+    window: tauri::Window,
     bounds: ContentBounds,
 ) -> Result<(), String> {
+    let state = tabs::window_state(&app, window.label())?;
     let mut state = state.lock().map_err(|e| e.to_string())?;
     tabs::set_content_bounds(&app, &mut state, bounds)
 }
+
+#[tauri::command]
+pub async fn find_in_page(
+    app: tauri::AppHandle,
+    tab_id: String,
+    query: String,
+    forward: Option<bool>,
+    match_case: Option<bool>,
+) -> Result<FindState, String> {
+    tabs::find_in_page(
+        &app,
+        &tab_id,
+        &query,
+        forward.unwrap_or(true),
+        match_case.unwrap_or(false),
+    )
+    .await
+}
+
+#[tauri::command]
+pub async fn find_next(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<FindState, String> {
+    tabs::find_next(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn find_prev(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<FindState, String> {
+    tabs::find_prev(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn find_stop(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    tabs::find_stop(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn get_tab_reading_stats(
+    app: tauri::AppHandle,
+    tab_id: String,
+    wpm: Option<u32>,
+) -> Result<ReadingStats, String> {
+    tabs::get_tab_reading_stats(&app, &tab_id, wpm).await
+}
+
+#[tauri::command]
+pub async fn extract_readable(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<ReadableContent, String> {
+    tabs::extract_readable(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn tab_resource_usage(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<TabResourceUsage, String> {
+    tabs::tab_resource_usage(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn get_scroll(app: tauri::AppHandle, tab_id: String) -> Result<TabScroll, String> {
+    tabs::get_scroll(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn set_scroll(
+    app: tauri::AppHandle,
+    tab_id: String,
+    x: f64,
+    y: f64,
+    smooth: Option<bool>,
+) -> Result<TabScroll, String> {
+    tabs::set_scroll(&app, &tab_id, x, y, smooth.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn scroll_to_element(
+    app: tauri::AppHandle,
+    tab_id: String,
+    selector: String,
+) -> Result<ElementRect, String> {
+    tabs::scroll_to_element(&app, &tab_id, &selector).await
+}
+
+#[tauri::command]
+pub async fn click_element(
+    app: tauri::AppHandle,
+    tab_id: String,
+    selector: String,
+) -> Result<ElementRect, String> {
+    tabs::click_element(&app, &tab_id, &selector).await
+}
+
+#[tauri::command]
+pub async fn fill_input(
+    app: tauri::AppHandle,
+    tab_id: String,
+    selector: String,
+    text: String,
+    simulate_typing: Option<bool>,
+) -> Result<String, String> {
+    tabs::fill_input(&app, &tab_id, &selector, &text, simulate_typing.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn proceed_anyway(
+    app: tauri::AppHandle,
+    tab_id: String,
+) -> Result<(), String> {
+    tabs::proceed_anyway(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn respond_permission(
+    app: tauri::AppHandle,
+    request_id: String,
+    allow: bool,
+    remember: Option<bool>,
+) -> Result<(), String> {
+    tabs::respond_permission(&app, &request_id, allow, remember.unwrap_or(false)).await
+}
+
+/// Without `tab_id`, the style applies to every current and future tab.
+#[tauri::command]
+pub fn add_user_style(
+    app: tauri::AppHandle,
+    tab_id: Option<String>,
+    css: String,
+) -> Result<String, String> {
+    tabs::add_user_style(&app, tab_id.as_deref(), css)
+}
+
+#[tauri::command]
+pub fn remove_user_style(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    tabs::remove_user_style(&app, &id)
+}
+
+/// Without `tab_id`, the script applies to every current and future tab.
+#[tauri::command]
+pub fn add_user_script(
+    app: tauri::AppHandle,
+    tab_id: Option<String>,
+    js: String,
+    run_at: Option<ScriptRunAt>,
+) -> Result<String, String> {
+    tabs::add_user_script(&app, tab_id.as_deref(), js, run_at.unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn toggle_reader_mode(app: tauri::AppHandle, tab_id: String) -> Result<bool, String> {
+    tabs::toggle_reader_mode(&app, &tab_id).await
+}
+
+#[tauri::command]
+pub async fn wait_for_selector(
+    app: tauri::AppHandle,
+    tab_id: String,
+    selector: String,
+    timeout_ms: Option<u64>,
+    visible: Option<bool>,
+) -> Result<ElementRect, String> {
+    tabs::wait_for_selector(&app, &tab_id, &selector, timeout_ms, visible.unwrap_or(false)).await
+}
+
+#[tauri::command]
+pub async fn wait_for_network_idle(
+    app: tauri::AppHandle,
+    tab_id: String,
+    idle_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<u64, String> {
+    tabs::wait_for_network_idle(&app, &tab_id, idle_ms, timeout_ms).await
+}
+
+#[tauri::command]
+pub async fn wait_for_dom_content_loaded(
+    app: tauri::AppHandle,
+    tab_id: String,
+    timeout_ms: Option<u64>,
+) -> Result<(), String> {
+    tabs::wait_for_dom_content_loaded(&app, &tab_id, timeout_ms).await
+}
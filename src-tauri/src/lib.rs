@@ -1,18 +1,32 @@
 use std::sync::Mutex;
-use tauri::{Emitter, Manager};
+use tauri::{Manager, RunEvent};
 use tauri::menu::{AboutMetadata, Menu, MenuItem, PredefinedMenuItem, Submenu};
 mod tabs;
 mod ipc;
 mod sidecar;
 mod devtools;
 mod logger;
+mod bookmarks;
+mod browsing_data;
+mod cache;
+mod capture;
+mod cookies;
+mod downloads;
+mod history;
+mod workspace;
+mod settings;
 
 pub fn run() {
     logger::init_system_logger();
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
-        .manage(Mutex::new(tabs::TabState::new()))
-        .manage(Mutex::new(sidecar::SidecarState::new()))
+        .manage(Mutex::new(tabs::WindowTabs::new()))
+        .manage(Mutex::new(tabs::EvalState::new()))
+        .manage(Mutex::new(sidecar::SidecarRegistry::new()))
+        .manage(Mutex::new(cache::CacheState::new()))
+        .manage(Mutex::new(capture::CaptureState::new()))
+        .manage(Mutex::new(downloads::DownloadState::new()))
+        .manage(Mutex::new(history::HistoryState::new()))
         .menu(|app| {
             let handle = app.app_handle();
             let pkg_info = app.package_info();
@@ -25,14 +39,33 @@ pub fn run() {
                 ..Default::default()
             };
 
+            let new_tab = MenuItem::with_id(handle, "new_tab", "New Tab", true, Some("CmdOrCtrl+T"))?;
+            let new_tab_right = MenuItem::with_id(handle, "new_tab_right", "New Tab to the Right", true, None::<&str>)?;
+            let new_window = MenuItem::with_id(handle, "new_window", "New Window", true, Some("CmdOrCtrl+N"))?;
             let close_tab = MenuItem::with_id(handle, "close_tab", "Close Tab", true, Some("CmdOrCtrl+W"))?;
+            let reopen_tab = MenuItem::with_id(handle, "reopen_closed_tab", "Reopen Closed Tab", true, Some("CmdOrCtrl+Shift+T"))?;
+            // Menu accelerators are handled natively before any webview sees
+            // the key, so these work while a page has focus. Pages therefore
+            // never receive Cmd/Ctrl+L, Alt+D or Cmd/Ctrl+F.
+            let open_location = MenuItem::with_id(handle, "open_location", "Open Location", true, Some("CmdOrCtrl+L"))?;
+            #[cfg(not(target_os = "macos"))]
+            let open_location_alt = MenuItem::with_id(handle, "open_location_alt", "Focus Address Bar", true, Some("Alt+D"))?;
+            let find = MenuItem::with_id(handle, "find", "Find...", true, Some("CmdOrCtrl+F"))?;
 
             let file_menu = Submenu::with_items(
                 handle,
                 "File",
                 true,
                 &[
+                    &new_tab,
+                    &new_tab_right,
+                    &new_window,
+                    &open_location,
+                    #[cfg(not(target_os = "macos"))]
+                    &open_location_alt,
+                    &PredefinedMenuItem::separator(handle)?,
                     &close_tab,
+                    &reopen_tab,
                     #[cfg(not(target_os = "macos"))]
                     &PredefinedMenuItem::close_window(handle, None)?,
                     #[cfg(not(target_os = "macos"))]
@@ -52,15 +85,41 @@ pub fn run() {
                     &PredefinedMenuItem::copy(handle, None)?,
                     &PredefinedMenuItem::paste(handle, None)?,
                     &PredefinedMenuItem::select_all(handle, None)?,
+                    &PredefinedMenuItem::separator(handle)?,
+                    &find,
                 ],
             )?;
 
-            #[cfg(target_os = "macos")]
+            let focus_mode = MenuItem::with_id(handle, "focus_mode", "Focus Mode", true, Some("CmdOrCtrl+Shift+F"))?;
+            let reader_mode = MenuItem::with_id(handle, "reader_mode", "Reader Mode", true, Some("CmdOrCtrl+Shift+R"))?;
+            let zoom_in = MenuItem::with_id(handle, "zoom_in", "Zoom In", true, Some("CmdOrCtrl+="))?;
+            let zoom_out = MenuItem::with_id(handle, "zoom_out", "Zoom Out", true, Some("CmdOrCtrl+-"))?;
+            let zoom_reset = MenuItem::with_id(handle, "zoom_reset", "Actual Size", true, Some("CmdOrCtrl+0"))?;
+
             let view_menu = Submenu::with_items(
                 handle,
                 "View",
                 true,
-                &[&PredefinedMenuItem::fullscreen(handle, None)?],
+                &[
+                    &focus_mode,
+                    &reader_mode,
+                    &PredefinedMenuItem::separator(handle)?,
+                    &zoom_in,
+                    &zoom_out,
+                    &zoom_reset,
+                    #[cfg(target_os = "macos")]
+                    &PredefinedMenuItem::separator(handle)?,
+                    #[cfg(target_os = "macos")]
+                    &PredefinedMenuItem::fullscreen(handle, None)?,
+                ],
+            )?;
+
+            #[cfg(any(debug_assertions, feature = "devtools"))]
+            let developer_menu = Submenu::with_items(
+                handle,
+                "Developer",
+                true,
+                &[&MenuItem::with_id(handle, "toggle_devtools", "Developer Tools", true, Some("CmdOrCtrl+Alt+I"))?],
             )?;
 
             let window_menu = Submenu::with_items(
@@ -75,6 +134,26 @@ pub fn run() {
                 ],
             )?;
 
+            // Tab selection: next/previous, Cmd/Ctrl+1..8 for the Nth tab and 9 for the last.
+            let mut tab_items = vec![
+                MenuItem::with_id(handle, "next_tab", "Select Next Tab", true, Some("CmdOrCtrl+Shift+]"))?,
+                MenuItem::with_id(handle, "previous_tab", "Select Previous Tab", true, Some("CmdOrCtrl+Shift+["))?,
+            ];
+            for n in 1..=8 {
+                tab_items.push(MenuItem::with_id(
+                    handle,
+                    format!("select_tab_{}", n),
+                    format!("Tab {}", n),
+                    true,
+                    Some(format!("CmdOrCtrl+{}", n)),
+                )?);
+            }
+            tab_items.push(MenuItem::with_id(handle, "select_last_tab", "Last Tab", true, Some("CmdOrCtrl+9"))?);
+            window_menu.prepend(&PredefinedMenuItem::separator(handle)?)?;
+            for item in tab_items.iter().rev() {
+                window_menu.prepend(item)?;
+            }
+
             let help_menu = Submenu::with_items(
                 handle,
                 "Help",
@@ -106,8 +185,9 @@ pub fn run() {
                     )?,
                     &file_menu,
                     &edit_menu,
-                    #[cfg(target_os = "macos")]
                     &view_menu,
+                    #[cfg(any(debug_assertions, feature = "devtools"))]
+                    &developer_menu,
                     &window_menu,
                     &help_menu,
                 ],
@@ -116,46 +196,234 @@ pub fn run() {
             Ok(menu)
         })
         .on_menu_event(|app, event| {
-            if event.id() == "close_tab" {
-                if let Some(window) = app.get_webview_window("main") {
-                    let _ = window.emit("close-active-tab", ());
+            let Ok(state) = tabs::window_state(app, &tabs::focused_window_label(app)) else {
+                return;
+            };
+            if event.id() == "new_tab" || event.id() == "new_tab_right" {
+                let guard = state.lock();
+                if let Ok(state) = guard {
+                    let _ = tabs::request_new_tab(app, &state, event.id() == "new_tab_right");
+                }
+            } else if event.id() == "close_tab" {
+                let guard = state.lock();
+                if let Ok(state) = guard {
+                    tabs::request_close_active_tab(app, &state);
+                }
+            } else if event.id() == "reopen_closed_tab" {
+                let guard = state.lock();
+                if let Ok(mut state) = guard {
+                    let _ = tabs::reopen_closed_tab(app, &mut state);
+                }
+            } else if event.id() == "open_location" || event.id() == "open_location_alt" {
+                let guard = state.lock();
+                if let Ok(state) = guard {
+                    let _ = tabs::focus_address_bar(app, &state);
+                }
+            } else if event.id() == "find" {
+                let guard = state.lock();
+                if let Ok(state) = guard {
+                    let _ = tabs::request_find(app, &state);
+                }
+            } else if event.id() == "new_window" {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = tabs::new_window(&app) {
+                        log::error!("[Tabs] Failed to open window: {}", e);
+                    }
+                });
+            } else if event.id() == "toggle_devtools" {
+                let active_tab = state.lock().ok().and_then(|state| state.active_tab.clone());
+                if let Some(tab_id) = active_tab {
+                    let _ = devtools::toggle_tab_devtools(app, &tab_id);
+                }
+            } else if event.id() == "reader_mode" {
+                let active_tab = state.lock().ok().and_then(|state| state.active_tab.clone());
+                if let Some(tab_id) = active_tab {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = tabs::toggle_reader_mode(&app, &tab_id).await {
+                            log::info!("[Tabs] Reader mode unavailable: {}", e);
+                        }
+                    });
+                }
+            } else if event.id() == "focus_mode" {
+                let guard = state.lock();
+                if let Ok(mut state) = guard {
+                    let _ = tabs::toggle_focus_mode(app, &mut state);
+                }
+            } else if let Some(step) = match event.id().as_ref() {
+                "next_tab" => Some(1),
+                "previous_tab" => Some(-1),
+                _ => None,
+            } {
+                let guard = state.lock();
+                if let Ok(mut state) = guard {
+                    let _ = tabs::cycle_tab(app, &mut state, step);
+                }
+            } else if let Some(index) = match event.id().as_ref() {
+                "select_last_tab" => Some(None),
+                id => id
+                    .strip_prefix("select_tab_")
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .map(|n| Some(n - 1)),
+            } {
+                let guard = state.lock();
+                if let Ok(mut state) = guard {
+                    let _ = tabs::switch_to_tab_index(app, &mut state, index);
+                }
+            } else if let Some(steps) = match event.id().as_ref() {
+                "zoom_in" => Some(1),
+                "zoom_out" => Some(-1),
+                "zoom_reset" => Some(0),
+                _ => None,
+            } {
+                let guard = state.lock();
+                if let Ok(mut state) = guard {
+                    let _ = tabs::zoom_active_tab(app, &mut state, steps);
                 }
             }
         })
         .setup(|app| {
-            let window = app.get_webview_window("main").unwrap();
+            let window = app.get_webview_window(tabs::MAIN_WINDOW).unwrap();
             println!("ClawBrowser started: {:?}", window.title());
-            devtools::watch_webview_devtools(app.handle().clone(), "main".to_string());
+            devtools::watch_webview_devtools(app.handle().clone(), tabs::MAIN_WINDOW.to_string());
+            tabs::listen_for_eval_results(app.handle());
+            tabs::listen_for_favicons(app.handle());
+            tabs::listen_for_titles(app.handle());
+            tabs::listen_for_audio_ready(app.handle());
+            tabs::listen_for_debug_renders(app.handle());
+            tabs::start_resource_monitor(app.handle());
+            tabs::start_hibernation_monitor(app.handle());
+            logger::listen_for_page_errors(app.handle());
+            logger::attach_log_emitter(app.handle());
+            if let Ok(state) = tabs::window_state(app.handle(), tabs::MAIN_WINDOW) {
+                if let Ok(state) = state.lock() {
+                    let _ = tabs::apply_ui_zoom(app.handle(), &state);
+                }
+            }
 
             // Listen for window resize to reposition content webviews
-            let app_handle = app.handle().clone();
-            window.on_window_event(move |event| {
-                if let tauri::WindowEvent::Resized(_) = event {
-                    let state_mutex = app_handle.state::<Mutex<tabs::TabState>>();
-                    let guard = state_mutex.lock();
-                    if let Ok(state) = guard {
-                        let _ = tabs::reposition_webviews(&app_handle, &state);
-                    }
-                }
-            });
+            tabs::watch_window(app.handle(), &window.as_ref().window());
 
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            ipc::new_window,
             ipc::create_tab,
+            ipc::create_incognito_tab,
             ipc::close_tab,
+            ipc::close_active_tab,
+            ipc::request_close_tab,
+            ipc::confirm_close_tab,
+            ipc::close_other_tabs,
+            ipc::close_tabs_to_right,
+            ipc::reopen_closed_tab,
+            ipc::reorder_tab,
+            ipc::pin_tab,
+            ipc::unpin_tab,
             ipc::switch_tab,
             ipc::hide_all_tabs,
+            ipc::focus_content_tab,
+            ipc::focus_chrome,
             ipc::navigate_tab,
+            ipc::tab_back,
+            ipc::tab_forward,
+            ipc::can_go_back,
+            ipc::can_go_forward,
+            ipc::reload_tab,
+            ipc::stop_tab_loading,
+            ipc::capture_tab_screenshot,
+            ipc::print_tab_to_pdf,
+            ipc::open_devtools,
+            ipc::close_devtools,
+            ipc::debug_list_webviews,
             ipc::run_js_in_tab,
+            ipc::run_js_in_tab_json,
+            ipc::run_js_file_in_tab,
             ipc::list_tabs,
+            ipc::get_tab_info,
             ipc::get_active_tab,
             ipc::reposition_tabs,
             ipc::set_content_bounds,
+            ipc::toggle_focus_mode,
+            ipc::set_ui_zoom,
+            ipc::set_tab_zoom,
+            ipc::set_link_intercept_config,
+            ipc::set_context_menu_intercept,
+            ipc::context_menu_action,
+            ipc::get_selection,
+            ipc::set_navigation_blocklist,
+            ipc::clear_navigation_blocklist,
+            ipc::set_tab_headers,
+            ipc::get_tab_zoom,
+            ipc::set_tab_muted,
+            ipc::set_user_agent,
+            ipc::hibernate_tab,
+            ipc::wake_tab,
+            ipc::set_auto_hibernate_minutes,
+            ipc::find_in_page,
+            ipc::find_next,
+            ipc::find_prev,
+            ipc::find_stop,
+            ipc::get_tab_reading_stats,
+            ipc::extract_readable,
+            ipc::tab_resource_usage,
+            ipc::get_scroll,
+            ipc::set_scroll,
+            ipc::scroll_to_element,
+            ipc::click_element,
+            ipc::fill_input,
+            ipc::wait_for_selector,
+            ipc::wait_for_network_idle,
+            ipc::wait_for_dom_content_loaded,
+            ipc::proceed_anyway,
+            ipc::respond_permission,
+            ipc::add_user_style,
+            ipc::remove_user_style,
+            ipc::add_user_script,
+            ipc::toggle_reader_mode,
             sidecar::start_sidecar,
+            sidecar::stop_sidecar,
+            sidecar::restart_sidecar,
             sidecar::sidecar_send,
+            sidecar::sidecar_notify,
+            sidecar::sidecar_cancel,
+            sidecar::sidecar_request,
+            sidecar::sidecar_send_batch,
+            sidecar::sidecar_health,
+            sidecar::sidecar_in_flight,
+            sidecar::set_sidecar_trace,
+            logger::set_log_level,
             sidecar::sidecar_receive,
+            cache::set_cache_policy,
+            cache::get_cache_usage,
+            bookmarks::add_bookmark,
+            bookmarks::remove_bookmark,
+            bookmarks::list_bookmarks,
+            bookmarks::update_bookmark,
+            bookmarks::move_bookmark,
+            browsing_data::clear_browsing_data,
+            history::query_history,
+            history::clear_history,
+            history::suggest_url,
+            capture::start_screenshot_heartbeat,
+            capture::stop_screenshot_heartbeat,
+            capture::ack_screenshot_heartbeat_frame,
+            downloads::cancel_download,
+            cookies::get_tab_cookies,
+            cookies::set_tab_cookie,
+            cookies::clear_tab_cookies,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running ClawBrowser");
+        .build(tauri::generate_context!())
+        .expect("error while building ClawBrowser")
+        .run(|app, event| {
+            // Fires after the last window is destroyed and on macOS quit alike,
+            // so the sidecar never outlives the app.
+            if let RunEvent::Exit = event {
+                if let Err(e) = tauri::async_runtime::block_on(sidecar::shutdown_all_sidecars(app)) {
+                    log::error!("[Sidecar] shutdown failed: {}", e);
+                }
+                log::logger().flush();
+            }
+        });
 }
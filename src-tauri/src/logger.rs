@@ -1,20 +1,63 @@
-use std::fs::{self, OpenOptions};
-use std::io::Write;
-use std::path::PathBuf;
-use std::sync::{Mutex, OnceLock};
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Instant;
 
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use log::kv::{self, VisitSource};
 use log::{Level, LevelFilter, Log, Metadata, Record};
-use serde_json::Value;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::{AppHandle, Emitter, Listener, Manager};
 use time::format_description::FormatItem;
 use time::macros::format_description;
 use time::{Duration, OffsetDateTime};
 
-const RETENTION_DAYS: i64 = 7;
+use crate::capture;
+use crate::workspace;
+
+const DEFAULT_RETENTION_DAYS: i64 = 7;
+/// Bounds for `CLAW_LOG_RETENTION_DAYS`; values outside are clamped.
+const MIN_RETENTION_DAYS: i64 = 1;
+const MAX_RETENTION_DAYS: i64 = 365;
+const DEFAULT_MAX_FILE_MB: u64 = 10;
+/// Days a compressed log is kept past retention; 0 disables compression.
+const DEFAULT_ARCHIVE_DAYS: i64 = 30;
+/// Page errors persisted per window; the rest are counted and summarized.
+const PAGE_ERROR_BURST: u32 = 20;
+const PAGE_ERROR_WINDOW_SECS: u64 = 10;
+/// Error screenshots saved per tab per window, with `CLAW_LOG_ERROR_SCREENSHOTS`.
+const ERROR_SCREENSHOT_BURST: u32 = 3;
+const ERROR_SCREENSHOT_WINDOW_SECS: u64 = 60;
+/// Lines queued for the writer thread before new ones are dropped.
+const LOG_QUEUE_CAPACITY: usize = 1024;
+/// How often the writer flushes buffered lines when the queue is idle.
+const LOG_FLUSH_INTERVAL_MS: u64 = 1000;
+/// How long `flush` waits for the writer to catch up.
+const LOG_FLUSH_TIMEOUT_MS: u64 = 2000;
+/// `system-log` events kept until `attach_log_emitter` is called.
+const PENDING_EVENT_CAPACITY: usize = 200;
+const WRITER_THREAD_NAME: &str = "claw-log-writer";
 const DATE_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
 const TS_FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]T[hour]:[minute]:[second]Z");
+const SCREENSHOT_TIME_FORMAT: &[FormatItem<'static>] = format_description!("[hour][minute][second]");
 
 static LOGGER: OnceLock<SystemLogger> = OnceLock::new();
 
+thread_local! {
+    /// Set on the writer thread while it emits a `system-log` event.
+    static EMITTING: Cell<bool> = const { Cell::new(false) };
+}
+
 pub fn init_system_logger() {
     if LOGGER.get().is_some() {
         return;
@@ -25,73 +68,522 @@ pub fn init_system_logger() {
         None => return,
     };
 
-    let logger = SystemLogger::new(logs_dir);
+    let level = env_log_level().unwrap_or(LevelFilter::Error);
+    let max_file_bytes = env_max_file_mb().unwrap_or(DEFAULT_MAX_FILE_MB) * 1024 * 1024;
+    let retention_days = env_retention_days().unwrap_or(DEFAULT_RETENTION_DAYS);
+    let archive_days = env_archive_days().unwrap_or(DEFAULT_ARCHIVE_DAYS);
+    let format = env_log_format().unwrap_or_default();
+    let Some(logger) = SystemLogger::new(logs_dir, level, format, max_file_bytes, retention_days, archive_days) else {
+        return;
+    };
     if LOGGER.set(logger).is_err() {
         return;
     }
 
     if let Some(logger_ref) = LOGGER.get() {
-        logger_ref.prune_old_logs();
         let _ = log::set_logger(logger_ref);
-        log::set_max_level(LevelFilter::Error);
+        log::set_max_level(level);
+    }
+    install_panic_hook();
+}
+
+/// Forward log entries to the frontend as `system-log` events, starting with
+/// the recent entries buffered before any window existed.
+pub fn attach_log_emitter(app: &AppHandle) {
+    if let Some(logger) = LOGGER.get() {
+        let _ = logger.sender.send(WriterCommand::AttachEmitter(app.clone()));
+    }
+}
+
+/// Write panics to the system log, whatever the configured level, before
+/// handing them on to the default hook.
+fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(logger) = LOGGER.get() {
+            logger.write_entry(Level::Error, "panic", format_args!("{}", panic_report(info)), Map::new());
+            // The writer can't wait on itself; its own panic is lost with the thread.
+            if std::thread::current().name() != Some(WRITER_THREAD_NAME) {
+                logger.flush();
+            }
+        }
+        previous(info);
+    }));
+}
+
+/// "[Panic] thread 'name' panicked at file:line:col: message", then the backtrace.
+fn panic_report(info: &PanicHookInfo) -> String {
+    let thread = std::thread::current();
+    let thread_name = thread.name().unwrap_or("<unnamed>");
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("Box<dyn Any>");
+    let location = info
+        .location()
+        .map(|location| location.to_string())
+        .unwrap_or_else(|| "<unknown>".to_string());
+    format!(
+        "[Panic] thread '{}' panicked at {}: {}\n{}",
+        thread_name,
+        location,
+        message,
+        Backtrace::force_capture()
+    )
+}
+
+/// Change the system log level without restarting. Accepts `off`, `error`,
+/// `warn`, `info`, `debug` or `trace`.
+#[tauri::command]
+pub fn set_log_level(level: String) -> Result<(), String> {
+    let filter = parse_level(&level).ok_or_else(|| format!("Unknown log level: {}", level))?;
+    let logger = LOGGER.get().ok_or("System logger not initialized")?;
+    logger.level.store(filter as u8, Ordering::Relaxed);
+    log::set_max_level(filter);
+    Ok(())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageErrorPayload {
+    #[serde(rename = "type")]
+    kind: String,
+    tab_id: Option<String>,
+    url: Option<String>,
+    message: Option<String>,
+    reason: Option<String>,
+    filename: Option<String>,
+    lineno: Option<u64>,
+    colno: Option<u64>,
+}
+
+/// Fixed-window limiter so a source logging in a loop (a page throwing, a
+/// chatty sidecar) can't fill the disk.
+pub struct LogBudget {
+    burst: u32,
+    window_secs: u64,
+    window_start: Instant,
+    logged: u32,
+    suppressed: u32,
+}
+
+impl LogBudget {
+    /// Allow `burst` entries per `window_secs`-second window.
+    pub fn new(burst: u32, window_secs: u64) -> Self {
+        Self {
+            burst,
+            window_secs,
+            window_start: Instant::now(),
+            logged: 0,
+            suppressed: 0,
+        }
     }
+
+    /// Returns whether this entry may be logged, plus how many were dropped
+    /// in the window that just ended.
+    pub fn admit(&mut self) -> (bool, u32) {
+        let mut dropped = 0;
+        if self.window_start.elapsed().as_secs() >= self.window_secs {
+            dropped = self.suppressed;
+            self.window_start = Instant::now();
+            self.logged = 0;
+            self.suppressed = 0;
+        }
+        if self.logged < self.burst {
+            self.logged += 1;
+            (true, dropped)
+        } else {
+            self.suppressed += 1;
+            (false, dropped)
+        }
+    }
+}
+
+/// Persist `error` and `unhandledrejection` events from the debug capture
+/// script to the system log, tagged with the tab id and URL. With
+/// `CLAW_LOG_ERROR_SCREENSHOTS` set, a screenshot of the tab is saved next to
+/// the log and named in the entry.
+pub fn listen_for_page_errors(app: &tauri::AppHandle) {
+    let budget = Mutex::new(LogBudget::new(PAGE_ERROR_BURST, PAGE_ERROR_WINDOW_SECS));
+    let screenshots = env_error_screenshots().then(|| Mutex::new(HashMap::<String, LogBudget>::new()));
+    let app_handle = app.clone();
+    app.listen("claw-debug", move |event| {
+        let payload: PageErrorPayload = match serde_json::from_str(event.payload()) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        if payload.kind != "error" && payload.kind != "unhandledrejection" {
+            return;
+        }
+        let (admitted, dropped) = match budget.lock() {
+            Ok(mut budget) => budget.admit(),
+            Err(_) => return,
+        };
+        if dropped > 0 {
+            log::error!("[Page] Suppressed {} page errors in the last {}s", dropped, PAGE_ERROR_WINDOW_SECS);
+        }
+        if !admitted {
+            return;
+        }
+
+        let tab_id = payload.tab_id.as_deref().unwrap_or("unknown");
+        let url = payload.url.as_deref().unwrap_or("");
+        let message = payload.message.or(payload.reason).unwrap_or_default();
+        let entry = match payload.filename.filter(|f| !f.is_empty()) {
+            Some(filename) => format!(
+                "{} tab={} url={} {} ({}:{}:{})",
+                payload.kind,
+                tab_id,
+                url,
+                message,
+                filename,
+                payload.lineno.unwrap_or(0),
+                payload.colno.unwrap_or(0)
+            ),
+            None => format!("{} tab={} url={} {}", payload.kind, tab_id, url, message),
+        };
+
+        let screenshot = payload.tab_id.as_deref().filter(|tab_id| {
+            let Some(Ok(mut budgets)) = screenshots.as_ref().map(Mutex::lock) else {
+                return false;
+            };
+            budgets.retain(|_, budget| budget.window_start.elapsed().as_secs() < budget.window_secs);
+            budgets
+                .entry(tab_id.to_string())
+                .or_insert_with(|| LogBudget::new(ERROR_SCREENSHOT_BURST, ERROR_SCREENSHOT_WINDOW_SECS))
+                .admit()
+                .0
+        });
+        match screenshot {
+            Some(tab_id) => {
+                let app = app_handle.clone();
+                let tab_id = tab_id.to_string();
+                tauri::async_runtime::spawn(async move {
+                    match save_error_screenshot(&app, &tab_id).await {
+                        Ok(name) => log::error!("[Page] {} screenshot={}", entry, name),
+                        Err(e) => {
+                            log::error!("[Page] {}", entry);
+                            log::warn!("[Page] Error screenshot of tab {} failed: {}", tab_id, e);
+                        }
+                    }
+                });
+            }
+            None => log::error!("[Page] {}", entry),
+        }
+    });
+}
+
+/// Capture the tab's viewport into the system log directory as
+/// `{date}.error-{time}-{tab}.png`, pruned with the logs of that date.
+/// Returns the file name.
+async fn save_error_screenshot(app: &AppHandle, tab_id: &str) -> Result<String, String> {
+    let dir = LOGGER.get().ok_or("System logger not initialized")?.logs_dir.clone();
+    let webview = app
+        .get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Webview for tab {} not found", tab_id))?;
+    let png = capture::capture_webview_png(&webview, None).await?;
+    let now = OffsetDateTime::now_utc();
+    let time = now.format(SCREENSHOT_TIME_FORMAT).map_err(|e| e.to_string())?;
+    let tab: String = tab_id.chars().filter(char::is_ascii_alphanumeric).take(8).collect();
+    let name = format!("{}.error-{}-{}.png", date_string(now), time, tab);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    fs::write(dir.join(&name), png).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    Ok(name)
+}
+
+/// How each entry is rendered on disk.
+#[derive(Clone, Copy, Default)]
+enum LogFormat {
+    /// `[timestamp] LEVEL message key=value ...`
+    #[default]
+    Text,
+    /// One JSON object per line with `ts`, `level`, `target`, `message` and
+    /// the record's key/value fields.
+    Json,
 }
 
 struct SystemLogger {
+    /// Most verbose level written to disk, stored as a `LevelFilter` discriminant.
+    level: AtomicU8,
+    /// Feeds the writer thread, which owns the log files.
+    sender: SyncSender<WriterCommand>,
+    /// Lines dropped because the queue was full; the writer notes the count.
+    dropped: Arc<AtomicU64>,
+    /// Where the writer keeps the log files; error screenshots go here too.
     logs_dir: PathBuf,
-    last_prune_date: Mutex<Option<String>>,
+}
+
+enum WriterCommand {
+    Line(LogEntry),
+    /// Write out everything queued so far, then acknowledge.
+    Flush(mpsc::Sender<()>),
+    /// Start emitting entries as `system-log` events, beginning with the backlog.
+    AttachEmitter(AppHandle),
 }
 
 impl SystemLogger {
-    fn new(logs_dir: PathBuf) -> Self {
-        Self {
+    /// Start the writer thread. Returns `None` if it can't be spawned.
+    fn new(
+        logs_dir: PathBuf,
+        level: LevelFilter,
+        format: LogFormat,
+        max_file_bytes: u64,
+        retention_days: i64,
+        archive_days: i64,
+    ) -> Option<Self> {
+        let (sender, receiver) = mpsc::sync_channel(LOG_QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = LogWriter {
+            logs_dir: logs_dir.clone(),
+            format,
+            last_prune_date: None,
+            max_file_bytes,
+            retention_days,
+            archive_days,
+            current: None,
+            emitter: None,
+            pending_events: VecDeque::new(),
+        };
+        let writer_dropped = Arc::clone(&dropped);
+        std::thread::Builder::new()
+            .name(WRITER_THREAD_NAME.to_string())
+            .spawn(move || writer.run(receiver, writer_dropped))
+            .ok()?;
+        Some(Self {
+            level: AtomicU8::new(level as u8),
+            sender,
+            dropped,
             logs_dir,
-            last_prune_date: Mutex::new(None),
+        })
+    }
+
+    /// Format and queue one entry, regardless of the level filter. Never
+    /// blocks: when the queue is full the entry is dropped and counted.
+    fn write_entry(&self, level: Level, target: &str, message: fmt::Arguments, fields: Map<String, Value>) {
+        let entry = LogEntry {
+            now: OffsetDateTime::now_utc(),
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+            fields,
+        };
+        if let Err(TrySendError::Full(_)) = self.sender.try_send(WriterCommand::Line(entry)) {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A record captured on the logging thread, formatted by the writer.
+struct LogEntry {
+    now: OffsetDateTime,
+    level: Level,
+    target: String,
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl LogEntry {
+    fn format(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Text => {
+                let mut line = format!("[{}] {} {}", timestamp_string(self.now), self.level, self.message);
+                for (key, value) in &self.fields {
+                    match value {
+                        Value::String(text) => line.push_str(&format!(" {}={}", key, text)),
+                        other => line.push_str(&format!(" {}={}", key, other)),
+                    }
+                }
+                line.push('\n');
+                line
+            }
+            LogFormat::Json => {
+                let mut entry = json!({
+                    "ts": timestamp_string(self.now),
+                    "level": self.level.as_str(),
+                    "target": self.target,
+                    "message": self.message,
+                });
+                if let Value::Object(entry) = &mut entry {
+                    for (key, value) in &self.fields {
+                        entry.entry(key.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+                format!("{}\n", entry)
+            }
+        }
+    }
+}
+
+/// Payload of the `system-log` event.
+#[derive(Clone, Serialize)]
+struct SystemLogEvent {
+    ts: String,
+    level: &'static str,
+    message: String,
+}
+
+/// Owns the open log file on the writer thread: appends, rotates by date and
+/// size, and prunes old files.
+struct LogWriter {
+    logs_dir: PathBuf,
+    format: LogFormat,
+    last_prune_date: Option<String>,
+    /// Size at which the current file rolls over to the next numbered sibling.
+    max_file_bytes: u64,
+    /// Days a plain log is kept before it is archived or deleted.
+    retention_days: i64,
+    /// Days to keep gzipped logs past retention; 0 deletes them outright.
+    archive_days: i64,
+    current: Option<CurrentFile>,
+    emitter: Option<AppHandle>,
+    /// Recent events kept until a window can receive them.
+    pending_events: VecDeque<SystemLogEvent>,
+}
+
+/// The file being appended to, with its size tracked so rotation doesn't
+/// need a `stat` per line.
+struct CurrentFile {
+    date: String,
+    index: u32,
+    size: u64,
+    file: BufWriter<File>,
+}
+
+impl LogWriter {
+    fn run(mut self, receiver: Receiver<WriterCommand>, dropped: Arc<AtomicU64>) {
+        self.prune_if_needed(&date_string(OffsetDateTime::now_utc()));
+        loop {
+            match receiver.recv_timeout(std::time::Duration::from_millis(LOG_FLUSH_INTERVAL_MS)) {
+                Ok(WriterCommand::Line(entry)) => {
+                    let count = dropped.swap(0, Ordering::Relaxed);
+                    if count > 0 {
+                        self.write_entry(LogEntry {
+                            now: entry.now,
+                            level: Level::Warn,
+                            target: module_path!().to_string(),
+                            message: format!("[Logger] Dropped {} log lines while the queue was full", count),
+                            fields: Map::new(),
+                        });
+                    }
+                    self.write_entry(entry);
+                }
+                Ok(WriterCommand::Flush(ack)) => {
+                    self.flush();
+                    let _ = ack.send(());
+                }
+                Ok(WriterCommand::AttachEmitter(app)) => {
+                    for event in std::mem::take(&mut self.pending_events) {
+                        emit_system_log(&app, &event);
+                    }
+                    self.emitter = Some(app);
+                }
+                Err(RecvTimeoutError::Timeout) => self.flush(),
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush();
+                    return;
+                }
+            }
         }
     }
 
-    fn date_string(&self, now: OffsetDateTime) -> String {
-        now.format(DATE_FORMAT).unwrap_or_else(|_| "unknown".to_string())
+    fn write_entry(&mut self, entry: LogEntry) {
+        let line = entry.format(self.format);
+        self.write_line(&date_string(entry.now), &line);
+
+        let event = SystemLogEvent {
+            ts: timestamp_string(entry.now),
+            level: entry.level.as_str(),
+            message: entry.message,
+        };
+        match &self.emitter {
+            Some(app) => emit_system_log(app, &event),
+            None => {
+                if self.pending_events.len() == PENDING_EVENT_CAPACITY {
+                    self.pending_events.pop_front();
+                }
+                self.pending_events.push_back(event);
+            }
+        }
     }
 
-    fn timestamp_string(&self, now: OffsetDateTime) -> String {
-        now.format(TS_FORMAT)
-            .unwrap_or_else(|_| now.unix_timestamp().to_string())
+    /// `{date}.log` for the first file of a day, `{date}.{index}.log` after rollover.
+    fn file_path(&self, date_str: &str, index: u32) -> PathBuf {
+        if index == 0 {
+            self.logs_dir.join(format!("{date_str}.log"))
+        } else {
+            self.logs_dir.join(format!("{date_str}.{index}.log"))
+        }
     }
 
-    fn file_path(&self, date_str: &str) -> PathBuf {
-        self.logs_dir.join(format!("{date_str}.log"))
+    /// Index and size of the newest existing file for `date_str`.
+    fn latest_file(&self, date_str: &str) -> (u32, u64) {
+        let mut index = 0;
+        while self.file_path(date_str, index + 1).exists() {
+            index += 1;
+        }
+        let size = fs::metadata(self.file_path(date_str, index))
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+        (index, size)
     }
 
-    fn write_line(&self, date_str: &str, line: &str) {
-        if fs::create_dir_all(&self.logs_dir).is_err() {
-            return;
+    fn open(&self, date_str: &str, index: u32, size: u64) -> Option<CurrentFile> {
+        fs::create_dir_all(&self.logs_dir).ok()?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.file_path(date_str, index))
+            .ok()?;
+        Some(CurrentFile {
+            date: date_str.to_string(),
+            index,
+            size,
+            file: BufWriter::new(file),
+        })
+    }
+
+    fn write_line(&mut self, date_str: &str, line: &str) {
+        self.prune_if_needed(date_str);
+        let len = line.len() as u64;
+        let reopen = match &self.current {
+            Some(current) if current.date == date_str => {
+                (current.size > 0 && current.size + len > self.max_file_bytes).then_some((current.index + 1, 0))
+            }
+            _ => Some(self.latest_file(date_str)),
+        };
+        if let Some((index, size)) = reopen {
+            self.flush();
+            self.current = self.open(date_str, index, size);
         }
-        let path = self.file_path(date_str);
-        let mut file = match OpenOptions::new().create(true).append(true).open(path) {
-            Ok(file) => file,
-            Err(_) => return,
+        let Some(current) = self.current.as_mut() else {
+            return;
         };
-        let _ = file.write_all(line.as_bytes());
+        if current.file.write_all(line.as_bytes()).is_ok() {
+            current.size += len;
+        }
     }
 
-    fn prune_if_needed(&self, today: &str) {
-        let mut should_prune = true;
-        if let Ok(mut guard) = self.last_prune_date.lock() {
-            if guard.as_deref() == Some(today) {
-                should_prune = false;
-            } else {
-                *guard = Some(today.to_string());
-            }
+    fn flush(&mut self) {
+        if let Some(current) = self.current.as_mut() {
+            let _ = current.file.flush();
         }
-        if !should_prune {
+    }
+
+    fn prune_if_needed(&mut self, today: &str) {
+        if self.last_prune_date.as_deref() == Some(today) {
             return;
         }
+        self.last_prune_date = Some(today.to_string());
         self.prune_old_logs();
     }
 
+    /// Gzip logs past the retention cutoff (or delete them when archiving is
+    /// off) and delete archives once they are `archive_days` past retention.
     fn prune_old_logs(&self) {
-        let cutoff = self.cutoff_date();
+        let cutoff = cutoff_date(self.retention_days);
+        let archive_cutoff = cutoff_date(self.retention_days + self.archive_days);
         let entries = match fs::read_dir(&self.logs_dir) {
             Ok(entries) => entries,
             Err(_) => return,
@@ -99,50 +591,115 @@ impl SystemLogger {
 
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) != Some("log") {
-                continue;
-            }
-            let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else {
+            let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
                 continue;
             };
-            if !is_date_str(stem) {
+            if let Some(date) = screenshot_file_date(name) {
+                if date < cutoff.as_str() {
+                    let _ = fs::remove_file(&path);
+                }
                 continue;
             }
-            if stem < cutoff.as_str() {
-                let _ = fs::remove_file(path);
+            let Some((date, compressed)) = log_file_date(name) else {
+                continue;
+            };
+            if compressed {
+                if date < archive_cutoff.as_str() {
+                    let _ = fs::remove_file(&path);
+                }
+            } else if date < cutoff.as_str() {
+                if self.archive_days > 0 && date >= archive_cutoff.as_str() && compress_file(&path).is_err() {
+                    continue;
+                }
+                let _ = fs::remove_file(&path);
             }
         }
     }
+}
 
-    fn cutoff_date(&self) -> String {
-        let today = OffsetDateTime::now_utc().date();
-        let cutoff = today - Duration::days(RETENTION_DAYS - 1);
-        cutoff.format(DATE_FORMAT).unwrap_or_else(|_| "unknown".to_string())
+/// Emit with `EMITTING` set so anything Tauri logs along the way is dropped
+/// instead of feeding back into another event.
+fn emit_system_log(app: &AppHandle, event: &SystemLogEvent) {
+    EMITTING.with(|emitting| emitting.set(true));
+    let _ = app.emit("system-log", event);
+    EMITTING.with(|emitting| emitting.set(false));
+}
+
+fn date_string(now: OffsetDateTime) -> String {
+    now.format(DATE_FORMAT).unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn timestamp_string(now: OffsetDateTime) -> String {
+    now.format(TS_FORMAT)
+        .unwrap_or_else(|_| now.unix_timestamp().to_string())
+}
+
+fn cutoff_date(days: i64) -> String {
+    let today = OffsetDateTime::now_utc().date();
+    let cutoff = today - Duration::days(days - 1);
+    cutoff.format(DATE_FORMAT).unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Write `path` gzipped to `{path}.gz`. The original is left for the caller to remove.
+fn compress_file(path: &Path) -> io::Result<()> {
+    let mut gz_name = path.as_os_str().to_owned();
+    gz_name.push(".gz");
+    let gz_path = PathBuf::from(gz_name);
+
+    let result = (|| {
+        let mut input = File::open(path)?;
+        let mut encoder = GzEncoder::new(File::create(&gz_path)?, Compression::default());
+        io::copy(&mut input, &mut encoder)?;
+        encoder.finish()?.sync_all()
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&gz_path);
     }
+    result
 }
 
 impl Log for SystemLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        matches!(metadata.level(), Level::Error)
+        metadata.level() as u8 <= self.level.load(Ordering::Relaxed)
     }
 
     fn log(&self, record: &Record) {
-        if !self.enabled(record.metadata()) {
+        if !self.enabled(record.metadata()) || EMITTING.with(Cell::get) {
             return;
         }
-        let now = OffsetDateTime::now_utc();
-        let date_str = self.date_string(now);
-        self.prune_if_needed(&date_str);
-        let line = format!(
-            "[{}] {} {}\n",
-            self.timestamp_string(now),
-            record.level(),
-            record.args()
-        );
-        self.write_line(&date_str, &line);
+        let mut fields = FieldCollector(Map::new());
+        let _ = record.key_values().visit(&mut fields);
+        self.write_entry(record.level(), record.target(), *record.args(), fields.0);
+    }
+
+    /// Block until everything queued so far is on disk, or the timeout passes.
+    fn flush(&self) {
+        let (ack, done) = mpsc::channel();
+        if self.sender.send(WriterCommand::Flush(ack)).is_ok() {
+            let _ = done.recv_timeout(std::time::Duration::from_millis(LOG_FLUSH_TIMEOUT_MS));
+        }
     }
+}
 
-    fn flush(&self) {}
+/// Gathers a record's key/value pairs as JSON, keeping numbers and booleans typed.
+struct FieldCollector(Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for FieldCollector {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(flag) = value.to_bool() {
+            Value::Bool(flag)
+        } else if let Some(number) = value.to_i64() {
+            Value::from(number)
+        } else if let Some(number) = value.to_u64() {
+            Value::from(number)
+        } else if let Some(number) = value.to_f64() {
+            Value::from(number)
+        } else {
+            Value::String(value.to_string())
+        };
+        self.0.insert(key.as_str().to_string(), value);
+        Ok(())
+    }
 }
 
 fn is_date_str(value: &str) -> bool {
@@ -160,22 +717,89 @@ fn is_date_str(value: &str) -> bool {
         })
 }
 
+/// The date of a `{date}.log` or numbered `{date}.{n}.log` file name, either
+/// of which may carry a `.gz` suffix; the flag is true for compressed files.
+fn log_file_date(name: &str) -> Option<(&str, bool)> {
+    let (name, compressed) = match name.strip_suffix(".gz") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let stem = name.strip_suffix(".log")?;
+    let (date, index) = match stem.split_once('.') {
+        Some((date, index)) => (date, Some(index)),
+        None => (stem, None),
+    };
+    if !is_date_str(date) {
+        return None;
+    }
+    match index {
+        Some(index) if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) => None,
+        _ => Some((date, compressed)),
+    }
+}
+
+/// Date of an error screenshot, `{date}.error-*.png`.
+fn screenshot_file_date(name: &str) -> Option<&str> {
+    let (date, rest) = name.split_once('.')?;
+    (is_date_str(date) && rest.starts_with("error-") && rest.ends_with(".png")).then_some(date)
+}
+
+fn parse_level(value: &str) -> Option<LevelFilter> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" | "warning" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+fn env_log_level() -> Option<LevelFilter> {
+    parse_level(&std::env::var("CLAW_LOG_LEVEL").ok()?)
+}
+
+fn env_log_format() -> Option<LogFormat> {
+    match std::env::var("CLAW_LOG_FORMAT").ok()?.trim().to_ascii_lowercase().as_str() {
+        "json" => Some(LogFormat::Json),
+        "text" => Some(LogFormat::Text),
+        _ => None,
+    }
+}
+
+fn env_max_file_mb() -> Option<u64> {
+    let raw = std::env::var("CLAW_LOG_MAX_FILE_MB").ok()?;
+    raw.trim().parse::<u64>().ok().filter(|mb| *mb > 0)
+}
+
+fn env_retention_days() -> Option<i64> {
+    let raw = std::env::var("CLAW_LOG_RETENTION_DAYS").ok()?;
+    let days = raw.trim().parse::<i64>().ok()?;
+    Some(days.clamp(MIN_RETENTION_DAYS, MAX_RETENTION_DAYS))
+}
+
+fn env_error_screenshots() -> bool {
+    std::env::var("CLAW_LOG_ERROR_SCREENSHOTS")
+        .map(|raw| matches!(raw.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+fn env_archive_days() -> Option<i64> {
+    let raw = std::env::var("CLAW_LOG_ARCHIVE_DAYS").ok()?;
+    raw.trim().parse::<i64>().ok().filter(|days| *days >= 0)
+}
+
 fn default_logs_dir() -> Option<PathBuf> {
+    Some(logs_base_dir()?.join("system"))
+}
+
+/// The workspace `logs/` directory, or `CLAW_LOG_DIR` when that is set.
+pub fn logs_base_dir() -> Option<PathBuf> {
     if let Some(dir) = env_logs_base_dir() {
-        return Some(dir.join("system"));
-    }
-    let home = home_dir()?;
-    let config_path = home.join(".clawbrowser").join("config.json");
-    if let Ok(raw) = fs::read_to_string(config_path) {
-        if let Ok(value) = serde_json::from_str::<Value>(&raw) {
-            if let Some(workspace_path) = value.get("workspacePath").and_then(|path| path.as_str()) {
-                if !workspace_path.is_empty() {
-                    return Some(PathBuf::from(workspace_path).join("logs").join("system"));
-                }
-            }
-        }
+        return Some(dir);
     }
-    Some(home.join(".clawbrowser").join("workspace").join("logs").join("system"))
+    Some(workspace::workspace_dir()?.join("logs"))
 }
 
 fn env_logs_base_dir() -> Option<PathBuf> {
@@ -193,24 +817,3 @@ fn env_logs_base_dir() -> Option<PathBuf> {
         Err(_) => Some(path),
     }
 }
-
-fn home_dir() -> Option<PathBuf> {
-    if let Ok(home) = std::env::var("HOME") {
-        if !home.is_empty() {
-            return Some(PathBuf::from(home));
-        }
-    }
-    if let Ok(home) = std::env::var("USERPROFILE") {
-        if !home.is_empty() {
-            return Some(PathBuf::from(home));
-        }
-    }
-    let drive = std::env::var("HOMEDRIVE").ok();
-    let path = std::env::var("HOMEPATH").ok();
-    match (drive, path) {
-        (Some(drive), Some(path)) if !drive.is_empty() && !path.is_empty() => {
-            Some(PathBuf::from(format!("{drive}{path}")))
-        }
-        _ => None,
-    }
-}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::workspace;
+
+const SETTINGS_FILE: &str = "browser-settings.json";
+
+/// Serializes read-modify-write cycles on the settings file.
+static SETTINGS_LOCK: Mutex<()> = Mutex::new(());
+
+/// Browser preferences owned by the Rust backend. Stored separately from the
+/// sidecar's `config.json` so neither side clobbers the other's keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BrowserSettings {
+    /// Zoom factor applied to the ClawBrowser chrome (not page content).
+    pub ui_zoom: f64,
+    /// How many times a crashed sidecar is restarted before giving up.
+    pub sidecar_max_restarts: u32,
+    /// Requests a sidecar may have awaiting a response before new ones are
+    /// rejected as busy.
+    pub sidecar_max_in_flight: u32,
+    /// Which click modifiers open a link in a new tab.
+    pub link_intercept: LinkInterceptConfig,
+    /// Right-clicks in pages are reported as `tab-context-menu` for the app
+    /// to render its own menu, instead of showing the native one. Off until a
+    /// frontend that renders the menu opts in.
+    pub context_menu_intercept: bool,
+    /// Background tabs idle for this many minutes are hibernated; 0 disables it.
+    pub auto_hibernate_minutes: u32,
+    /// Lets `proceed_anyway` load pages whose certificate failed validation.
+    pub allow_certificate_bypass: bool,
+    /// History entries not visited for this many days are pruned; 0 keeps them forever.
+    pub history_retention_days: u32,
+    /// Search URL for address-bar input that isn't a URL; `{query}` is
+    /// replaced with the encoded input.
+    pub search_engine_template: String,
+    /// Page zoom remembered per origin (`scheme://host[:port]`) and restored
+    /// when a tab arrives there. Origins at 100% are left out.
+    pub site_zoom: HashMap<String, f64>,
+    /// App page shown in new and blank tabs, as a path into the frontend
+    /// build (a Vite input such as `newtab.html`). Being an app URL, it can
+    /// use the Tauri API. Empty uses `blank.html`. Read once at startup.
+    pub new_tab_page: String,
+}
+
+impl Default for BrowserSettings {
+    fn default() -> Self {
+        Self {
+            ui_zoom: 1.0,
+            sidecar_max_restarts: 5,
+            sidecar_max_in_flight: 256,
+            link_intercept: LinkInterceptConfig::default(),
+            context_menu_intercept: false,
+            auto_hibernate_minutes: 0,
+            allow_certificate_bypass: false,
+            history_retention_days: 90,
+            search_engine_template: "https://www.google.com/search?q={query}".to_string(),
+            site_zoom: HashMap::new(),
+            new_tab_page: String::new(),
+        }
+    }
+}
+
+/// Modifier keys that turn a link click into a new-tab request. Middle-click
+/// always opens a new tab unless the feature is disabled.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LinkInterceptConfig {
+    pub enabled: bool,
+    pub use_shift: bool,
+    pub use_ctrl: bool,
+    pub use_meta: bool,
+}
+
+impl Default for LinkInterceptConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            use_shift: true,
+            use_ctrl: false,
+            use_meta: cfg!(target_os = "macos"),
+        }
+    }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(workspace::workspace_dir()?.join(SETTINGS_FILE))
+}
+
+fn read_settings() -> BrowserSettings {
+    settings_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+}
+
+/// Load the persisted settings, falling back to defaults when missing or invalid.
+pub fn load() -> BrowserSettings {
+    let _guard = SETTINGS_LOCK.lock();
+    read_settings()
+}
+
+/// Apply `change` to the persisted settings and write them back atomically.
+pub fn update<F: FnOnce(&mut BrowserSettings)>(change: F) -> Result<BrowserSettings, String> {
+    let _guard = SETTINGS_LOCK.lock();
+    let mut settings = read_settings();
+    change(&mut settings);
+
+    let path = settings_path().ok_or("Workspace directory not found")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let raw = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    let tmp_path = path.with_extension("json.tmp");
+    fs::write(&tmp_path, raw).map_err(|e| format!("Failed to write settings: {}", e))?;
+    fs::rename(&tmp_path, &path).map_err(|e| format!("Failed to save settings: {}", e))?;
+    Ok(settings)
+}
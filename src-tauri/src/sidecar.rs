@@ -1,20 +1,211 @@
-use std::sync::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use serde::Serialize;
 use serde_json::Value;
-use tauri::Emitter;
+use tauri::{Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+use time::format_description::FormatItem;
+use time::macros::format_description;
+use time::OffsetDateTime;
+use tokio::sync::oneshot;
 
-/// State for the sidecar process lifecycle.
+use crate::logger::{self, LogBudget};
+use crate::settings;
+use crate::tabs;
+
+/// Instance used when a command doesn't name one. Its events keep their
+/// plain names so single-sidecar callers work unchanged.
+const DEFAULT_INSTANCE: &str = "default";
+/// Sidecar spawned when the caller doesn't name one.
+const DEFAULT_SIDECAR: &str = "sidecar/clawbrowser-agent";
+/// Sidecars declared under `bundle.externalBin`. Spawning from Rust bypasses the
+/// shell plugin's capability scope, so only these names are accepted.
+const ALLOWED_SIDECARS: &[&str] = &["sidecar/clawbrowser-agent", "sidecar/clawbrowser-agent-dev"];
+/// How long a request waits for its response unless the caller says otherwise.
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+/// How long `sidecar_health` waits for a `ping` reply before reporting the
+/// sidecar as unresponsive.
+const HEALTH_TIMEOUT_MS: u64 = 3_000;
+/// Crash restarts back off exponentially from this delay up to the cap.
+const RESTART_BASE_DELAY_MS: u64 = 1_000;
+const RESTART_MAX_DELAY_MS: u64 = 30_000;
+/// A sidecar that stayed up this long is considered healthy again, so its
+/// next crash starts the backoff over.
+const RESTART_RESET_SECS: u64 = 60;
+/// At most this many stderr lines per window reach the system log; all of
+/// them are still emitted as `sidecar-stderr`.
+const STDERR_LOG_BURST: u32 = 50;
+const STDERR_LOG_WINDOW_SECS: u64 = 10;
+/// How long a stopping sidecar gets to exit on its own before it is killed.
+const SHUTDOWN_GRACE_MS: u64 = 2_000;
+/// Selects the sidecar's message framing; passed through to the sidecar too.
+const FRAMING_ENV: &str = "CLAW_SIDECAR_FRAMING";
+/// JSON-RPC error code used when a request can never be answered.
+const SIDECAR_GONE_CODE: i64 = -32000;
+/// JSON-RPC error code for a request whose response was malformed.
+const INVALID_RESPONSE_CODE: i64 = -32603;
+/// JSON-RPC error code for a request cancelled by `sidecar_cancel` (as in LSP).
+const REQUEST_CANCELLED_CODE: i64 = -32800;
+/// Transcript written by `set_sidecar_trace`, in the logs directory.
+const TRACE_FILE_NAME: &str = "sidecar.log";
+/// `params`, `result` and `error.data` longer than this when serialized are
+/// cut short in the trace, with their full size noted.
+const TRACE_MAX_VALUE_BYTES: usize = 2_048;
+const TRACE_TS_FORMAT: &[FormatItem<'static>] =
+    format_description!("[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z");
+
+/// The open trace file while `set_sidecar_trace` is on. Shared by every
+/// instance, so one transcript shows all sidecar traffic in order.
+static TRACE_FILE: Mutex<Option<File>> = Mutex::new(None);
+
+/// How JSON-RPC messages are delimited on the sidecar's stdin and stdout.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Framing {
+    /// One JSON message per line. The default.
+    Newline,
+    /// LSP-style `Content-Length: N\r\n\r\n{json}` frames, for payloads that
+    /// are large or contain raw newlines.
+    ContentLength,
+}
+
+impl Framing {
+    /// `CLAW_SIDECAR_FRAMING=content-length` opts in to length-prefixed frames.
+    fn from_env() -> Self {
+        match std::env::var(FRAMING_ENV) {
+            Ok(value) if value.trim().eq_ignore_ascii_case("content-length") => Self::ContentLength,
+            _ => Self::Newline,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Newline => "newline",
+            Self::ContentLength => "content-length",
+        }
+    }
+}
+
+/// Bytes read from the sidecar in raw mode, where one chunk may hold part of
+/// a message or several of them.
+#[derive(Default)]
+struct FrameBuffer {
+    buffer: Vec<u8>,
+}
+
+impl FrameBuffer {
+    fn extend(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// The next complete line, without its terminator.
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        let end = self.buffer.iter().position(|&b| b == b'\n')?;
+        let mut line: Vec<u8> = self.buffer.drain(..=end).collect();
+        line.pop();
+        Some(line)
+    }
+
+    /// The body of the next complete `Content-Length` frame. A header without
+    /// a valid length is discarded and reported as an error.
+    fn next_frame(&mut self) -> Option<Result<Vec<u8>, String>> {
+        let header_end = self.buffer.windows(4).position(|w| w == b"\r\n\r\n")?;
+        let body_start = header_end + 4;
+        let header = String::from_utf8_lossy(&self.buffer[..header_end]);
+        let length = header.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if !name.trim().eq_ignore_ascii_case("content-length") {
+                return None;
+            }
+            value.trim().parse::<usize>().ok()
+        });
+        let Some(length) = length else {
+            let header = header.into_owned();
+            self.buffer.drain(..body_start);
+            return Some(Err(format!("Sidecar frame without a valid Content-Length: {:?}", header)));
+        };
+        if self.buffer.len() < body_start + length {
+            return None;
+        }
+        let body = self.buffer[body_start..body_start + length].to_vec();
+        self.buffer.drain(..body_start + length);
+        Some(Ok(body))
+    }
+
+    fn take_rest(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+/// Running sidecars keyed by instance name, e.g. one for the LLM and one
+/// for tooling. Each instance has its own lock, so they don't contend.
+#[derive(Default)]
+pub struct SidecarRegistry {
+    sidecars: HashMap<String, Arc<Mutex<SidecarState>>>,
+}
+
+impl SidecarRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// State for one sidecar instance's process lifecycle.
 pub struct SidecarState {
+    /// Registry key, used to namespace this instance's events.
+    instance: String,
     /// Whether the sidecar has been started.
     pub started: bool,
+    /// Set by `stop_sidecar` so a pending crash restart doesn't bring the
+    /// sidecar back; cleared by `start_sidecar` and `restart_sidecar`.
+    stopped: bool,
     /// Next JSON-RPC request ID.
     next_id: u64,
+    /// The running sidecar process; stdin requests are written here.
+    child: Option<CommandChild>,
+    /// Requests awaiting a response, keyed by JSON-RPC id. Each is resolved
+    /// with the full response message.
+    pending: HashMap<u64, oneshot::Sender<Value>>,
+    /// Sidecar binary name, remembered so crashes can be restarted.
+    name: Option<String>,
+    spawned_at: Option<Instant>,
+    /// Consecutive crash restarts since the sidecar was last healthy.
+    restart_attempts: u32,
+    /// Resolves once the running sidecar's output stream closes.
+    exited: Option<oneshot::Receiver<()>>,
+    /// Framing of the running sidecar's messages, fixed when it is spawned.
+    framing: Framing,
+    /// Ids of in-flight `sidecar_health` pings. They share the request id
+    /// space, but their replies are not emitted as `sidecar-message`.
+    health_checks: HashSet<u64>,
+    /// Unix time in milliseconds of the last answered health ping.
+    last_pong_ms: Option<u64>,
+    /// Cap on `pending`, from `sidecar_max_in_flight`; requests past it fail
+    /// as busy instead of queueing without bound.
+    max_in_flight: usize,
 }
 
 impl SidecarState {
-    pub fn new() -> Self {
+    fn new(instance: &str) -> Self {
         Self {
+            instance: instance.to_string(),
             started: false,
+            stopped: false,
             next_id: 1,
+            child: None,
+            pending: HashMap::new(),
+            name: None,
+            spawned_at: None,
+            restart_attempts: 0,
+            exited: None,
+            framing: Framing::Newline,
+            health_checks: HashSet::new(),
+            last_pong_ms: None,
+            max_in_flight: settings::load().sidecar_max_in_flight.max(1) as usize,
         }
     }
 
@@ -23,73 +214,984 @@ impl SidecarState {
         self.next_id += 1;
         id
     }
+
+    /// Fail as busy unless `count` more requests fit under `max_in_flight`.
+    fn ensure_capacity(&self, count: usize) -> Result<(), String> {
+        if self.pending.len() + count > self.max_in_flight {
+            return Err(format!(
+                "Sidecar busy: {} requests in flight (limit {})",
+                self.pending.len(),
+                self.max_in_flight
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The instance a command targets: `DEFAULT_INSTANCE` when unnamed. Names are
+/// restricted to characters that are valid in a Tauri event name.
+fn instance_name(instance: Option<String>) -> Result<String, String> {
+    let Some(instance) = instance else {
+        return Ok(DEFAULT_INSTANCE.to_string());
+    };
+    let valid = !instance.is_empty()
+        && instance
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid sidecar instance name: {:?}", instance));
+    }
+    Ok(instance)
+}
+
+/// The state of sidecar `instance`, created on first use.
+fn sidecar_state(app: &tauri::AppHandle, instance: &str) -> Result<Arc<Mutex<SidecarState>>, String> {
+    let registry = app.state::<Mutex<SidecarRegistry>>();
+    let mut registry = registry.lock().map_err(|e| e.to_string())?;
+    let state = registry
+        .sidecars
+        .entry(instance.to_string())
+        .or_insert_with(|| Arc::new(Mutex::new(SidecarState::new(instance))));
+    Ok(Arc::clone(state))
+}
+
+/// `base` for the default instance, `{base}:{instance}` for the others.
+fn event_name(base: &str, instance: &str) -> String {
+    if instance == DEFAULT_INSTANCE {
+        base.to_string()
+    } else {
+        format!("{}:{}", base, instance)
+    }
+}
+
+/// Emit one of `instance`'s events under its namespaced name.
+fn emit_event<S: Serialize + Clone>(
+    app: &tauri::AppHandle,
+    instance: &str,
+    base: &str,
+    payload: S,
+) -> Result<(), String> {
+    app.emit(&event_name(base, instance), payload)
+        .map_err(|e| format!("Failed to emit {}: {}", base, e))
+}
+
+/// What a well-formed message from the sidecar is.
+enum MessageKind {
+    /// A response, with its id when it is one of ours (a `u64`).
+    Response(Option<u64>),
+    /// A notification or a request from the sidecar; neither answers anything.
+    Notification,
+}
+
+/// Check the JSON-RPC 2.0 envelope: `jsonrpc: "2.0"`, a string `method` with
+/// object/array `params` for notifications, and for responses an id plus
+/// exactly one of `result` or an `error` with an integer `code` and a string
+/// `message`. Returns why the message is malformed otherwise.
+fn validate_message(message: &Value) -> Result<MessageKind, String> {
+    let Some(object) = message.as_object() else {
+        return Err("message is not an object".to_string());
+    };
+    if object.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+        return Err("missing or wrong \"jsonrpc\" version".to_string());
+    }
+    let id = object.get("id");
+    if id.is_some_and(|id| !(id.is_u64() || id.is_i64() || id.is_string() || id.is_null())) {
+        return Err("\"id\" must be a number, string or null".to_string());
+    }
+
+    if let Some(method) = object.get("method") {
+        if !method.is_string() {
+            return Err("\"method\" must be a string".to_string());
+        }
+        if object.get("params").is_some_and(|params| !(params.is_object() || params.is_array())) {
+            return Err("\"params\" must be an object or array".to_string());
+        }
+        return Ok(MessageKind::Notification);
+    }
+
+    let Some(id) = id else {
+        return Err("response without an \"id\"".to_string());
+    };
+    match (object.get("result"), object.get("error")) {
+        (Some(_), Some(_)) => return Err("response has both \"result\" and \"error\"".to_string()),
+        (None, None) => return Err("response has neither \"result\" nor \"error\"".to_string()),
+        (None, Some(error)) => {
+            let valid = error.get("code").is_some_and(Value::is_i64)
+                && error.get("message").is_some_and(Value::is_string);
+            if !valid {
+                return Err("\"error\" needs an integer \"code\" and a string \"message\"".to_string());
+            }
+        }
+        (Some(_), None) => {}
+    }
+    Ok(MessageKind::Response(id.as_u64()))
+}
+
+/// Take the pending request `id`, reporting whether it was a health ping.
+fn take_pending(app: &tauri::AppHandle, instance: &str, id: u64) -> Result<(Option<oneshot::Sender<Value>>, bool), String> {
+    let state = sidecar_state(app, instance)?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    Ok((state.pending.remove(&id), state.health_checks.remove(&id)))
+}
+
+/// Log a malformed message and emit it as `sidecar-protocol-error`. If it
+/// names one of our pending requests, that request fails now with
+/// `INVALID_RESPONSE_CODE` rather than waiting out its timeout.
+fn reject_malformed(app: &tauri::AppHandle, instance: &str, message: Value, reason: &str) -> Result<(), String> {
+    log::error!("[Sidecar] {}: malformed message ({}): {}", instance, reason, message);
+    let id = message.get("id").and_then(Value::as_u64);
+    let _ = emit_event(
+        app,
+        instance,
+        "sidecar-protocol-error",
+        serde_json::json!({ "reason": reason, "message": message }),
+    );
+
+    let Some(id) = id else {
+        return Ok(());
+    };
+    let (sender, health_check) = take_pending(app, instance, id)?;
+    let Some(sender) = sender else {
+        return Ok(());
+    };
+    let response = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {
+            "code": INVALID_RESPONSE_CODE,
+            "message": format!("Malformed response from sidecar: {}", reason),
+        },
+    });
+    let _ = sender.send(response.clone());
+    if health_check {
+        return Ok(());
+    }
+    emit_event(app, instance, "sidecar-message", &response)
+}
+
+/// Resolve the pending request a response answers, if any, and emit the
+/// message as `sidecar-message`. Error responses resolve it too, and
+/// `sidecar_request` turns them into an `Err`.
+fn route_message(app: &tauri::AppHandle, instance: &str, message: Value) -> Result<(), String> {
+    let kind = match validate_message(&message) {
+        Ok(kind) => kind,
+        Err(reason) => return reject_malformed(app, instance, message, &reason),
+    };
+    // Notifications never touch the pending map.
+    if let MessageKind::Response(Some(id)) = kind {
+        let (sender, health_check) = take_pending(app, instance, id)?;
+        if let Some(sender) = sender {
+            let _ = sender.send(message.clone());
+        }
+        if health_check {
+            return Ok(());
+        }
+    }
+
+    emit_event(app, instance, "sidecar-message", &message)
+}
+
+/// `s` cut to at most `max` bytes, on a character boundary.
+fn truncate_str(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Replace `value` with a prefix of its JSON and its size when it is too
+/// large to trace in full.
+fn truncate_trace_value(value: &mut Value) {
+    let serialized = value.to_string();
+    if serialized.len() <= TRACE_MAX_VALUE_BYTES {
+        return;
+    }
+    *value = Value::String(format!(
+        "{}... [truncated, {} bytes]",
+        truncate_str(&serialized, TRACE_MAX_VALUE_BYTES),
+        serialized.len()
+    ));
+}
+
+/// `message` as it goes in the trace: oversized `params`, `result` and
+/// `error.data` are truncated, batch elements each on their own.
+fn trace_summary(message: &Value) -> Value {
+    match message {
+        Value::Array(batch) => Value::Array(batch.iter().map(trace_summary).collect()),
+        Value::Object(object) => {
+            let mut object = object.clone();
+            for key in ["params", "result"] {
+                if let Some(value) = object.get_mut(key) {
+                    truncate_trace_value(value);
+                }
+            }
+            if let Some(data) = object.get_mut("error").and_then(|error| error.get_mut("data")) {
+                truncate_trace_value(data);
+            }
+            Value::Object(object)
+        }
+        other => other.clone(),
+    }
+}
+
+/// Append one line to the trace, if it is on: timestamp, instance, direction
+/// (`->` to the sidecar, `<-` from it), size in bytes and the message.
+/// `raw` is traced as text when it didn't parse.
+fn trace_message(instance: &str, direction: &str, raw: &str, message: Option<&Value>) {
+    let Ok(mut trace) = TRACE_FILE.lock() else {
+        return;
+    };
+    let Some(file) = trace.as_mut() else {
+        return;
+    };
+    let body = match message {
+        Some(message) => trace_summary(message).to_string(),
+        None => {
+            let text = truncate_str(raw, TRACE_MAX_VALUE_BYTES);
+            if text.len() < raw.len() {
+                format!("{}... [truncated]", text)
+            } else {
+                text.to_string()
+            }
+        }
+    };
+    let line = format!(
+        "[{}] {} {} ({} bytes) {}\n",
+        trace_timestamp(),
+        instance,
+        direction,
+        raw.len(),
+        body
+    );
+    if let Err(e) = file.write_all(line.as_bytes()) {
+        log::warn!("[Sidecar] Failed to write trace: {}", e);
+    }
+}
+
+fn trace_timestamp() -> String {
+    let now = OffsetDateTime::now_utc();
+    now.format(TRACE_TS_FORMAT)
+        .unwrap_or_else(|_| now.unix_timestamp().to_string())
+}
+
+/// Parse one JSON-RPC message (or batch) from the sidecar and route it.
+/// Batch responses are fanned out in order, one `sidecar-message` per element;
+/// requests the batch doesn't answer are left to their timeouts. Anything that
+/// isn't valid JSON-RPC is reported as `sidecar-protocol-error`.
+fn relay_message(app: &tauri::AppHandle, instance: &str, message: &str) -> Result<(), String> {
+    let parsed: Value = match serde_json::from_str(message) {
+        Ok(parsed) => {
+            trace_message(instance, "<-", message, Some(&parsed));
+            parsed
+        }
+        Err(e) => {
+            trace_message(instance, "<-", message, None);
+            let reason = format!("invalid JSON: {}", e);
+            let _ = emit_event(
+                app,
+                instance,
+                "sidecar-protocol-error",
+                serde_json::json!({ "reason": reason, "message": message }),
+            );
+            return Err(format!("Invalid JSON from sidecar: {}", e));
+        }
+    };
+
+    match parsed {
+        Value::Array(batch) if batch.is_empty() => reject_malformed(app, instance, Value::Array(batch), "empty batch"),
+        Value::Array(batch) => {
+            for item in batch {
+                route_message(app, instance, item)?;
+            }
+            Ok(())
+        }
+        message => route_message(app, instance, message),
+    }
+}
+
+fn build_request(state: &mut SidecarState, method: &str, params: Value) -> (u64, Value) {
+    let id = state.next_request_id();
+    let request = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": id,
+    });
+    (id, request)
+}
+
+/// A JSON-RPC notification: no `id`, so the sidecar sends no response.
+fn build_notification(method: &str, params: Option<Value>) -> Value {
+    let mut notification = serde_json::json!({ "jsonrpc": "2.0", "method": method });
+    if let Some(params) = params {
+        notification["params"] = params;
+    }
+    notification
+}
+
+/// Write one JSON message to the sidecar's stdin in its framing.
+fn write_message(state: &mut SidecarState, message: &Value) -> Result<(), String> {
+    let body = serde_json::to_string(message).map_err(|e| e.to_string())?;
+    trace_message(&state.instance, "->", &body, Some(message));
+    let data = match state.framing {
+        Framing::Newline => format!("{}\n", body),
+        Framing::ContentLength => format!("Content-Length: {}\r\n\r\n{}", body.len(), body),
+    };
+
+    let child = state.child.as_mut().ok_or("Sidecar not running")?;
+    child
+        .write(data.as_bytes())
+        .map_err(|e| format!("Failed to write to sidecar stdin: {}", e))
+}
+
+fn register_pending(state: &mut SidecarState, id: u64) -> oneshot::Receiver<Value> {
+    let (sender, receiver) = oneshot::channel();
+    state.pending.insert(id, sender);
+    receiver
+}
+
+/// Write a request to the sidecar's stdin and register it as pending.
+/// Returns the assigned id and a receiver for the response message.
+fn dispatch_request(
+    state: &mut SidecarState,
+    method: &str,
+    params: Value,
+) -> Result<(u64, oneshot::Receiver<Value>), String> {
+    let (id, request) = build_request(state, method, params);
+    write_message(state, &request)?;
+    Ok((id, register_pending(state, id)))
+}
+
+/// Time out a `sidecar_send` request in the background; the response itself
+/// reaches the caller as a `sidecar-message` event.
+fn watch_response(
+    app: &tauri::AppHandle,
+    instance: &str,
+    id: u64,
+    method: String,
+    receiver: oneshot::Receiver<Value>,
+    timeout_ms: Option<u64>,
+) {
+    let app = app.clone();
+    let instance = instance.to_string();
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS));
+    tauri::async_runtime::spawn(async move {
+        let _ = await_response(&app, &instance, id, &method, receiver, timeout).await;
+    });
+}
+
+/// Wait for a pending request's response. On timeout the entry is dropped and
+/// `sidecar-timeout` is emitted, so a lost reply never hangs the caller.
+async fn await_response(
+    app: &tauri::AppHandle,
+    instance: &str,
+    id: u64,
+    method: &str,
+    receiver: oneshot::Receiver<Value>,
+    timeout: Duration,
+) -> Result<Value, String> {
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(Ok(response)) => Ok(response),
+        Ok(Err(_)) => Err(format!("Sidecar request {} ({}) was dropped", id, method)),
+        Err(_) => {
+            if let Ok(state) = sidecar_state(app, instance) {
+                if let Ok(mut state) = state.lock() {
+                    state.pending.remove(&id);
+                }
+            }
+            let _ = emit_event(
+                app,
+                instance,
+                "sidecar-timeout",
+                serde_json::json!({
+                    "id": id,
+                    "method": method,
+                    "timeoutMs": timeout.as_millis() as u64,
+                }),
+            );
+            Err(format!(
+                "Sidecar request {} ({}) timed out after {}ms",
+                id,
+                method,
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
+/// Resolve every pending request with a JSON-RPC error so callers fail fast
+/// instead of waiting out their timeouts.
+fn fail_pending(state: &mut SidecarState, message: &str) {
+    for (id, sender) in state.pending.drain() {
+        let _ = sender.send(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": SIDECAR_GONE_CODE, "message": message },
+        }));
+    }
+}
+
+fn restart_delay(attempt: u32) -> Duration {
+    let factor = 1u64 << attempt.min(16);
+    Duration::from_millis(RESTART_BASE_DELAY_MS.saturating_mul(factor).min(RESTART_MAX_DELAY_MS))
+}
+
+/// Handle the sidecar exiting. Exits of a child that has since been replaced
+/// are only logged; otherwise pending requests fail and, within the configured
+/// attempt budget, the sidecar is restarted with exponential backoff.
+fn handle_terminated(app: &tauri::AppHandle, instance: &str, pid: u32, code: Option<i32>, signal: Option<i32>) {
+    log::error!("[Sidecar] {} exited: code={:?} signal={:?}", instance, code, signal);
+
+    let Ok(sidecar) = sidecar_state(app, instance) else {
+        return;
+    };
+    let restart = {
+        let Ok(mut state) = sidecar.lock() else {
+            return;
+        };
+        if state.child.as_ref().map(|child| child.pid()) != Some(pid) {
+            return;
+        }
+        state.child = None;
+        state.started = false;
+        fail_pending(&mut state, "Sidecar process exited");
+
+        if state.spawned_at.is_some_and(|at| at.elapsed() >= Duration::from_secs(RESTART_RESET_SECS)) {
+            state.restart_attempts = 0;
+        }
+        let max_attempts = settings::load().sidecar_max_restarts;
+        match state.name.clone() {
+            Some(name) if state.restart_attempts < max_attempts => {
+                let delay = restart_delay(state.restart_attempts);
+                state.restart_attempts += 1;
+                Some((name, state.restart_attempts, delay))
+            }
+            _ => None,
+        }
+    };
+
+    let _ = emit_event(
+        app,
+        instance,
+        "sidecar-exited",
+        serde_json::json!({
+            "code": code,
+            "signal": signal,
+            "restarting": restart.is_some(),
+            "attempt": restart.as_ref().map(|(_, attempt, _)| *attempt),
+        }),
+    );
+    let _ = emit_event(
+        app,
+        instance,
+        "sidecar-status",
+        serde_json::json!({ "status": "exited", "code": code, "signal": signal }),
+    );
+
+    if let Some((name, attempt, delay)) = restart {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let Ok(mut sidecar_state) = sidecar.lock() else {
+                return;
+            };
+            // A manual start or restart got there first, or the sidecar was
+            // stopped during the backoff.
+            if sidecar_state.started || sidecar_state.stopped {
+                return;
+            }
+            log::warn!("[Sidecar] restarting {} as {} (attempt {})", name, sidecar_state.instance, attempt);
+            if let Err(e) = spawn_sidecar(&app, &mut sidecar_state, &name) {
+                log::error!("[Sidecar] restart failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Relay one message read from the sidecar's stdout.
+fn relay_stdout(app: &tauri::AppHandle, instance: &str, bytes: &[u8]) {
+    let message = String::from_utf8_lossy(bytes);
+    let message = message.trim();
+    if message.is_empty() {
+        return;
+    }
+    if let Err(e) = relay_message(app, instance, message) {
+        log::error!("[Sidecar] {}: {}", instance, e);
+    }
+}
+
+/// Emit each stderr line as `sidecar-stderr` and log it, within `budget`.
+fn relay_stderr(app: &tauri::AppHandle, instance: &str, budget: &mut LogBudget, bytes: &[u8]) {
+    let text = String::from_utf8_lossy(bytes);
+    for line in text.lines().map(str::trim_end).filter(|line| !line.is_empty()) {
+        let _ = emit_event(app, instance, "sidecar-stderr", serde_json::json!({ "line": line, "done": false }));
+        let (admitted, dropped) = budget.admit();
+        if dropped > 0 {
+            log::error!("[Sidecar] Suppressed {} stderr lines in the last {}s", dropped, STDERR_LOG_WINDOW_SECS);
+        }
+        if admitted {
+            log::error!("[Sidecar] {} stderr: {}", instance, line);
+        }
+    }
+}
+
+/// Spawn the named sidecar, record it in `state` and start relaying its output.
+fn spawn_sidecar(
+    app: &tauri::AppHandle,
+    state: &mut SidecarState,
+    name: &str,
+) -> Result<(), String> {
+    if !ALLOWED_SIDECARS.contains(&name) {
+        return Err(format!("Unknown sidecar: {}", name));
+    }
+    // Bundled sidecars sit next to the executable under their file name.
+    let binary = Path::new(name)
+        .file_name()
+        .ok_or_else(|| format!("Invalid sidecar name: {}", name))?;
+    let framing = Framing::from_env();
+    // Length-prefixed frames can't be split on newlines, so read output raw.
+    let raw_out = framing == Framing::ContentLength;
+    let (mut events, child) = app
+        .shell()
+        .sidecar(binary)
+        .map_err(|e| format!("Failed to resolve sidecar {}: {}", name, e))?
+        .env(FRAMING_ENV, framing.as_str())
+        .set_raw_out(raw_out)
+        .spawn()
+        .map_err(|e| format!("Failed to spawn sidecar {}: {}", name, e))?;
+    let pid = child.pid();
+    let (exit_sender, exit_receiver) = oneshot::channel::<()>();
+    state.child = Some(child);
+    state.exited = Some(exit_receiver);
+    state.framing = framing;
+    state.started = true;
+    state.name = Some(name.to_string());
+    state.spawned_at = Some(Instant::now());
+
+    let app_handle = app.clone();
+    let instance = state.instance.clone();
+    tauri::async_runtime::spawn(async move {
+        // Dropped when the event stream ends, which resolves `exited`.
+        let _exit_sender = exit_sender;
+        let mut stderr_budget = LogBudget::new(STDERR_LOG_BURST, STDERR_LOG_WINDOW_SECS);
+        let mut stdout_frames = FrameBuffer::default();
+        let mut stderr_lines = FrameBuffer::default();
+        while let Some(event) = events.recv().await {
+            match event {
+                CommandEvent::Stdout(bytes) if raw_out => {
+                    stdout_frames.extend(&bytes);
+                    while let Some(frame) = stdout_frames.next_frame() {
+                        match frame {
+                            Ok(body) => relay_stdout(&app_handle, &instance, &body),
+                            Err(e) => log::error!("[Sidecar] {}: {}", instance, e),
+                        }
+                    }
+                }
+                CommandEvent::Stdout(bytes) => relay_stdout(&app_handle, &instance, &bytes),
+                CommandEvent::Stderr(bytes) if raw_out => {
+                    stderr_lines.extend(&bytes);
+                    while let Some(line) = stderr_lines.next_line() {
+                        relay_stderr(&app_handle, &instance, &mut stderr_budget, &line);
+                    }
+                }
+                CommandEvent::Stderr(bytes) => relay_stderr(&app_handle, &instance, &mut stderr_budget, &bytes),
+                CommandEvent::Error(e) => log::error!("[Sidecar] {}: {}", instance, e),
+                CommandEvent::Terminated(payload) => {
+                    let rest = stderr_lines.take_rest();
+                    if !rest.is_empty() {
+                        relay_stderr(&app_handle, &instance, &mut stderr_budget, &rest);
+                    }
+                    // Closes the stderr stream for listeners, with the exit status.
+                    let _ = emit_event(
+                        &app_handle,
+                        &instance,
+                        "sidecar-stderr",
+                        serde_json::json!({
+                            "line": null,
+                            "done": true,
+                            "exitCode": payload.code,
+                            "signal": payload.signal,
+                        }),
+                    );
+                    handle_terminated(&app_handle, &instance, pid, payload.code, payload.signal);
+                }
+                _ => {}
+            }
+        }
+    });
+
+    let _ = emit_event(app, &state.instance, "sidecar-status", serde_json::json!({ "status": "ready" }));
+    Ok(())
+}
+
+/// Ask the sidecar to exit with a `shutdown` notification and kill it if it
+/// is still running after `SHUTDOWN_GRACE_MS`. Pending requests fail and the
+/// exit doesn't trigger an auto-restart.
+async fn shutdown_sidecar(app: &tauri::AppHandle, instance: &str) -> Result<(), String> {
+    let (child, exited) = {
+        let state = sidecar_state(app, instance)?;
+        let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+        if sidecar_state.child.is_some() {
+            if let Err(e) = write_message(&mut sidecar_state, &build_notification("shutdown", None)) {
+                log::warn!("[Sidecar] {}", e);
+            }
+        }
+        sidecar_state.started = false;
+        sidecar_state.stopped = true;
+        fail_pending(&mut sidecar_state, "Sidecar stopped");
+        // Without a child, `handle_terminated` ignores the exit.
+        (sidecar_state.child.take(), sidecar_state.exited.take())
+    };
+    let Some(child) = child else {
+        return Ok(());
+    };
+
+    let grace = Duration::from_millis(SHUTDOWN_GRACE_MS);
+    let exited = match exited {
+        Some(exited) => tokio::time::timeout(grace, exited).await.is_ok(),
+        None => false,
+    };
+    if !exited {
+        log::warn!("[Sidecar] did not exit within {}ms, killing it", SHUTDOWN_GRACE_MS);
+        child.kill().map_err(|e| format!("Failed to kill sidecar: {}", e))?;
+    }
+    let _ = emit_event(app, instance, "sidecar-status", serde_json::json!({ "status": "stopped" }));
+    Ok(())
+}
+
+/// Shut down every sidecar instance, as `shutdown_sidecar` does for one.
+pub async fn shutdown_all_sidecars(app: &tauri::AppHandle) -> Result<(), String> {
+    let instances: Vec<String> = {
+        let registry = app.state::<Mutex<SidecarRegistry>>();
+        let registry = registry.lock().map_err(|e| e.to_string())?;
+        registry.sidecars.keys().cloned().collect()
+    };
+    let mut result = Ok(());
+    for instance in instances {
+        if let Err(e) = shutdown_sidecar(app, &instance).await {
+            result = Err(format!("{}: {}", instance, e));
+        }
+    }
+    result
 }
 
 /// Start the sidecar process using Tauri's shell plugin.
-/// The sidecar communicates via stdin/stdout JSON-RPC.
+/// The sidecar communicates via stdin/stdout JSON-RPC, one message per line
+/// unless `CLAW_SIDECAR_FRAMING=content-length` selects length-prefixed frames.
 /// Stdout lines are parsed and emitted as `sidecar-message` Tauri events.
-/// Stderr is logged for debugging.
+/// Stderr lines are emitted as `sidecar-stderr` and logged (rate limited);
+/// a final `sidecar-stderr` with `done: true` carries the exit code.
+///
+/// `name` picks the binary; `instance` keys the running process, so several
+/// can run side by side. Every command takes the same `instance`, and events
+/// of a non-default instance are suffixed with it (`sidecar-message:{instance}`).
 #[tauri::command]
 pub fn start_sidecar(
     app: tauri::AppHandle,
-    state: tauri::State<'_, Mutex<SidecarState>>,
+    name: Option<String>,
+    instance: Option<String>,
 ) -> Result<(), String> {
+    let state = sidecar_state(&app, &instance_name(instance)?)?;
     let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
     if sidecar_state.started {
         return Ok(());
     }
-    sidecar_state.started = true;
-    drop(sidecar_state);
+    let name = name.unwrap_or_else(|| DEFAULT_SIDECAR.to_string());
+    sidecar_state.restart_attempts = 0;
+    sidecar_state.stopped = false;
+    spawn_sidecar(&app, &mut sidecar_state, &name)
+}
 
-    // Use Tauri shell plugin to spawn the sidecar
-    // The frontend SidecarBridge will manage the actual process via @tauri-apps/plugin-shell
-    // This Rust module provides the relay commands for IPC
-    let _ = app.emit("sidecar-status", serde_json::json!({ "status": "ready" }));
+/// Stop the sidecar gracefully. A no-op when it isn't running.
+#[tauri::command]
+pub async fn stop_sidecar(app: tauri::AppHandle, instance: Option<String>) -> Result<(), String> {
+    shutdown_sidecar(&app, &instance_name(instance)?).await
+}
 
-    Ok(())
+/// Kill the running sidecar (if any) and start a fresh one, failing whatever
+/// requests were still in flight. Also recovers after auto-restart gave up.
+#[tauri::command]
+pub fn restart_sidecar(
+    app: tauri::AppHandle,
+    name: Option<String>,
+    instance: Option<String>,
+) -> Result<(), String> {
+    let state = sidecar_state(&app, &instance_name(instance)?)?;
+    let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+    let name = name
+        .or_else(|| sidecar_state.name.clone())
+        .unwrap_or_else(|| DEFAULT_SIDECAR.to_string());
+    if let Some(child) = sidecar_state.child.take() {
+        let _ = child.kill();
+    }
+    sidecar_state.started = false;
+    fail_pending(&mut sidecar_state, "Sidecar restarted");
+    sidecar_state.restart_attempts = 0;
+    sidecar_state.stopped = false;
+    spawn_sidecar(&app, &mut sidecar_state, &name)
 }
 
-/// Send a JSON-RPC message to the sidecar via the frontend relay.
-/// The frontend SidecarBridge manages the actual stdin/stdout communication.
-/// This command constructs a JSON-RPC request and emits it for the bridge.
+/// Send a JSON-RPC request to the sidecar by writing it to the child's stdin.
+/// Returns the request id; the response arrives as a `sidecar-message` event.
+/// If none arrives within `timeout_ms`, `sidecar-timeout` is emitted instead.
+/// Fails as busy while `sidecar_max_in_flight` requests are already pending.
 #[tauri::command]
 pub fn sidecar_send(
     app: tauri::AppHandle,
-    state: tauri::State<'_, Mutex<SidecarState>>,
     method: String,
     params: Value,
+    timeout_ms: Option<u64>,
+    instance: Option<String>,
 ) -> Result<u64, String> {
+    let instance = instance_name(instance)?;
+    let state = sidecar_state(&app, &instance)?;
     let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
-    let id = sidecar_state.next_request_id();
+    sidecar_state.ensure_capacity(1)?;
+    let (id, receiver) = dispatch_request(&mut sidecar_state, &method, params)?;
     drop(sidecar_state);
 
-    let request = serde_json::json!({
+    watch_response(&app, &instance, id, method, receiver, timeout_ms);
+    Ok(id)
+}
+
+/// Send a JSON-RPC notification to the sidecar. No id is allocated and no
+/// response is awaited, so use it for fire-and-forget events such as tab
+/// updates and log forwarding.
+#[tauri::command]
+pub fn sidecar_notify(
+    app: tauri::AppHandle,
+    method: String,
+    params: Option<Value>,
+    instance: Option<String>,
+) -> Result<(), String> {
+    let state = sidecar_state(&app, &instance_name(instance)?)?;
+    let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+    write_message(&mut sidecar_state, &build_notification(&method, params))
+}
+
+/// Cancel a pending request: the sidecar is sent a `$/cancelRequest`
+/// notification and the request fails now with a cancellation error, also
+/// emitted as its `sidecar-message`. Unknown or finished ids are a no-op.
+#[tauri::command]
+pub fn sidecar_cancel(
+    app: tauri::AppHandle,
+    id: u64,
+    instance: Option<String>,
+) -> Result<(), String> {
+    let instance = instance_name(instance)?;
+    let state = sidecar_state(&app, &instance)?;
+    let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+    let Some(sender) = sidecar_state.pending.remove(&id) else {
+        return Ok(());
+    };
+    let notification = build_notification("$/cancelRequest", Some(serde_json::json!({ "id": id })));
+    if let Err(e) = write_message(&mut sidecar_state, &notification) {
+        log::warn!("[Sidecar] {}", e);
+    }
+    drop(sidecar_state);
+
+    let response = serde_json::json!({
         "jsonrpc": "2.0",
-        "method": method,
-        "params": params,
         "id": id,
+        "error": { "code": REQUEST_CANCELLED_CODE, "message": "Request cancelled" },
     });
+    let _ = sender.send(response.clone());
+    emit_event(&app, &instance, "sidecar-message", &response)
+}
 
-    // Emit the request for the frontend SidecarBridge to write to stdin
-    app.emit("sidecar-request", request)
-        .map_err(|e| format!("Failed to emit sidecar request: {}", e))?;
+/// Send several requests as one JSON-RPC batch (a single JSON array line).
+/// Returns the ids in call order. Each response arrives as its own
+/// `sidecar-message` and each request times out independently.
+#[tauri::command]
+pub fn sidecar_send_batch(
+    app: tauri::AppHandle,
+    calls: Vec<(String, Value)>,
+    timeout_ms: Option<u64>,
+    instance: Option<String>,
+) -> Result<Vec<u64>, String> {
+    if calls.is_empty() {
+        return Err("Batch must contain at least one request".to_string());
+    }
 
-    Ok(id)
+    let instance = instance_name(instance)?;
+    let state = sidecar_state(&app, &instance)?;
+    let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+    sidecar_state.ensure_capacity(calls.len())?;
+    let mut batch = Vec::with_capacity(calls.len());
+    let mut methods = Vec::with_capacity(calls.len());
+    for (method, params) in calls {
+        let (id, request) = build_request(&mut sidecar_state, &method, params);
+        batch.push(request);
+        methods.push((id, method));
+    }
+    write_message(&mut sidecar_state, &Value::Array(batch))?;
+    let receivers: Vec<_> = methods
+        .into_iter()
+        .map(|(id, method)| {
+            let receiver = register_pending(&mut sidecar_state, id);
+            (id, method, receiver)
+        })
+        .collect();
+    drop(sidecar_state);
+
+    let mut ids = Vec::with_capacity(receivers.len());
+    for (id, method, receiver) in receivers {
+        watch_response(&app, &instance, id, method, receiver, timeout_ms);
+        ids.push(id);
+    }
+    Ok(ids)
 }
 
-/// Called by the frontend when it receives a line from sidecar stdout.
-/// Parses the JSON-RPC response/notification and emits appropriate events.
+/// Send a JSON-RPC request and wait for its result. JSON-RPC errors and
+/// timeouts are returned as `Err`.
+#[tauri::command]
+pub async fn sidecar_request(
+    app: tauri::AppHandle,
+    method: String,
+    params: Value,
+    timeout_ms: Option<u64>,
+    instance: Option<String>,
+) -> Result<Value, String> {
+    let instance = instance_name(instance)?;
+    let (id, receiver) = {
+        let state = sidecar_state(&app, &instance)?;
+        let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+        sidecar_state.ensure_capacity(1)?;
+        dispatch_request(&mut sidecar_state, &method, params)?
+    };
+
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS));
+    let mut response = await_response(&app, &instance, id, &method, receiver, timeout).await?;
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("Sidecar request failed");
+        return Err(message.to_string());
+    }
+    Ok(response.get_mut("result").map(Value::take).unwrap_or(Value::Null))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarLoad {
+    /// Requests awaiting a response, health pings included.
+    pub in_flight: usize,
+    pub max_in_flight: usize,
+}
+
+/// How many requests the sidecar has in flight, against its limit.
+#[tauri::command]
+pub fn sidecar_in_flight(app: tauri::AppHandle, instance: Option<String>) -> Result<SidecarLoad, String> {
+    let state = sidecar_state(&app, &instance_name(instance)?)?;
+    let sidecar_state = state.lock().map_err(|e| e.to_string())?;
+    Ok(SidecarLoad {
+        in_flight: sidecar_state.pending.len(),
+        max_in_flight: sidecar_state.max_in_flight,
+    })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SidecarHealth {
+    pub alive: bool,
+    /// Round trip of this ping; `None` when it went unanswered.
+    pub latency_ms: Option<u64>,
+    /// Unix time in milliseconds of the last answered ping, from any check.
+    pub last_pong_ms: Option<u64>,
+}
+
+/// Ping the sidecar and report whether it answered within `timeout_ms`
+/// (default `HEALTH_TIMEOUT_MS`). A sidecar that isn't running is reported
+/// as not alive rather than as an error, so the frontend can poll this.
+#[tauri::command]
+pub async fn sidecar_health(
+    app: tauri::AppHandle,
+    timeout_ms: Option<u64>,
+    instance: Option<String>,
+) -> Result<SidecarHealth, String> {
+    let started = Instant::now();
+    let state = sidecar_state(&app, &instance_name(instance)?)?;
+    let (id, receiver) = {
+        let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+        let unresponsive = SidecarHealth {
+            alive: false,
+            latency_ms: None,
+            last_pong_ms: sidecar_state.last_pong_ms,
+        };
+        if sidecar_state.child.is_none() {
+            return Ok(unresponsive);
+        }
+        match dispatch_request(&mut sidecar_state, "ping", serde_json::json!({})) {
+            Ok((id, receiver)) => {
+                sidecar_state.health_checks.insert(id);
+                (id, receiver)
+            }
+            Err(e) => {
+                log::warn!("[Sidecar] health check failed: {}", e);
+                return Ok(unresponsive);
+            }
+        }
+    };
+
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(HEALTH_TIMEOUT_MS));
+    let response = tokio::time::timeout(timeout, receiver).await;
+    let alive = matches!(
+        &response,
+        Ok(Ok(message)) if message.pointer("/result/pong").and_then(Value::as_bool) == Some(true)
+    );
+
+    let mut sidecar_state = state.lock().map_err(|e| e.to_string())?;
+    sidecar_state.pending.remove(&id);
+    sidecar_state.health_checks.remove(&id);
+    if alive {
+        sidecar_state.last_pong_ms = Some(tabs::now_ms());
+    }
+    Ok(SidecarHealth {
+        alive,
+        latency_ms: alive.then(|| started.elapsed().as_millis() as u64),
+        last_pong_ms: sidecar_state.last_pong_ms,
+    })
+}
+
+/// Turn the JSON-RPC trace on or off. While on, every message to and from
+/// each sidecar is appended to `sidecar.log` in the logs directory, apart
+/// from the system log. Returns the trace file's path.
+#[tauri::command]
+pub fn set_sidecar_trace(enabled: bool) -> Result<String, String> {
+    let dir = logger::logs_base_dir().ok_or("Logs directory unavailable")?;
+    let path = dir.join(TRACE_FILE_NAME);
+    let mut trace = TRACE_FILE.lock().map_err(|e| e.to_string())?;
+    if enabled && trace.is_none() {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let _ = writeln!(file, "[{}] trace started", trace_timestamp());
+        *trace = Some(file);
+        log::info!("[Sidecar] JSON-RPC trace on, writing to {}", path.display());
+    } else if !enabled {
+        if let Some(mut file) = trace.take() {
+            let _ = writeln!(file, "[{}] trace stopped", trace_timestamp());
+            log::info!("[Sidecar] JSON-RPC trace off");
+        }
+    }
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Relay a line of sidecar stdout read elsewhere. Kept for callers that still
+/// spawn the sidecar themselves; `start_sidecar` now reads stdout directly.
 #[tauri::command]
 pub fn sidecar_receive(
     app: tauri::AppHandle,
     message: String,
+    instance: Option<String>,
 ) -> Result<(), String> {
-    let parsed: Value = serde_json::from_str(&message)
-        .map_err(|e| format!("Invalid JSON from sidecar: {}", e))?;
-
-    // Emit as a sidecar-message event for any listeners
-    app.emit("sidecar-message", &parsed)
-        .map_err(|e| format!("Failed to emit sidecar message: {}", e))?;
-
-    Ok(())
+    relay_message(&app, &instance_name(instance)?, &message)
 }
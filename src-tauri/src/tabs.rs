@@ -1,10 +1,26 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
+use base64::Engine;
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use tauri::{
     webview::{NewWindowResponse, WebviewBuilder},
-    Emitter, Manager, PhysicalPosition, PhysicalSize, Webview, WebviewUrl, Window,
+    Emitter, Listener, Manager, PhysicalPosition, PhysicalSize, Webview, WebviewUrl, Window,
 };
+use tokio::sync::oneshot;
+use crate::capture;
 use crate::devtools;
+use crate::downloads;
+use crate::history;
+use crate::settings::{self, LinkInterceptConfig};
+use crate::workspace;
+
+/// Label of the window created from `tauri.conf.json`.
+pub const MAIN_WINDOW: &str = "main";
 
 /// Layout constants in logical pixels. Used as a fallback before UI reports its true bounds.
 const AGENT_PANEL_WIDTH: f64 = 320.0;
@@ -12,6 +28,77 @@ const TAB_LIST_WIDTH: f64 = 200.0;
 const NAV_BAR_HEIGHT: f64 = 56.0;
 const BLANK_PAGE_PATH: &str = "blank.html";
 
+/// How long an injected script has to report its result before the caller gives up.
+const EVAL_TIMEOUT_MS: u64 = 10_000;
+/// Default wait for `run_js_in_tab`, long enough for agent scripts that await
+/// page activity.
+const RUN_JS_TIMEOUT_MS: u64 = 30_000;
+/// How long back/forward waits for the URL to change before giving up on
+/// emitting `tab-navigated`.
+const HISTORY_SETTLE_POLL_MS: u64 = 100;
+const HISTORY_SETTLE_POLLS: u32 = 30;
+/// A page's permission prompt is denied if `respond_permission` doesn't
+/// answer it within this long.
+const PERMISSION_TIMEOUT_SECS: u64 = 30;
+/// Full-page screenshots are cut off at this many device pixels to bound memory.
+const MAX_FULL_PAGE_HEIGHT_PX: u32 = 16_384;
+/// Pause after each scroll so the page can repaint before it is captured.
+const SCROLL_SETTLE_MS: u64 = 150;
+/// How often idle tabs are checked for auto-hibernation.
+const HIBERNATION_CHECK_INTERVAL_MS: u64 = 60_000;
+/// How often `tab-resource-update` is emitted for every loaded tab.
+const RESOURCE_POLL_INTERVAL_MS: u64 = 30_000;
+/// `extract_readable` returns at most this many characters of article text.
+const MAX_READABLE_CHARS: usize = 100_000;
+/// Pages with less extracted text than this have no article to show in reader mode.
+const MIN_READER_MODE_CHARS: usize = 250;
+/// Default and upper bound for `wait_for_selector`.
+const WAIT_FOR_SELECTOR_TIMEOUT_MS: u64 = 10_000;
+const MAX_WAIT_FOR_SELECTOR_TIMEOUT_MS: u64 = 120_000;
+/// Extra time Rust gives the page to report its own timeout before giving up.
+const WAIT_FOR_SELECTOR_GRACE_MS: u64 = 1_000;
+/// Longest `set_scroll` waits for a smooth scroll to finish.
+const SMOOTH_SCROLL_WAIT_MS: u64 = 1_500;
+/// Default and upper bound for `wait_for_network_idle` and
+/// `wait_for_dom_content_loaded`.
+const WAIT_FOR_LOAD_TIMEOUT_MS: u64 = 30_000;
+const MAX_WAIT_FOR_LOAD_TIMEOUT_MS: u64 = 120_000;
+/// How long no request may be in flight before the network counts as idle.
+const NETWORK_IDLE_MS: u64 = 500;
+/// How long `request_close_tab` waits for the page to report unsaved state.
+const CLOSE_GUARD_TIMEOUT_MS: u64 = 1_000;
+/// Error raised by `RUN_JS_BODY` when the page's CSP forbids `eval`.
+const EVAL_BLOCKED_ERROR: &str = "__CLAW_EVAL_BLOCKED__";
+/// Supported range for the chrome UI zoom factor.
+const MIN_UI_ZOOM: f64 = 0.5;
+const MAX_UI_ZOOM: f64 = 2.0;
+/// Supported range and menu step for per-tab page zoom.
+const MIN_TAB_ZOOM: f64 = 0.5;
+const MAX_TAB_ZOOM: f64 = 3.0;
+const TAB_ZOOM_STEP: f64 = 0.1;
+/// Where parked background webviews are moved, on both axes.
+const OFFSCREEN_OFFSET: i32 = -10_000;
+/// How many closed tabs `reopen_closed_tab` can bring back.
+const MAX_CLOSED_TABS: usize = 25;
+/// Default for `CLAW_DEBUG_MAX_EVENTS_PER_SEC`, the per-tab debug event rate limit.
+const DEFAULT_DEBUG_MAX_EVENTS_PER_SEC: u32 = 50;
+/// Render snapshots of a tab arriving within this long are coalesced into the latest.
+const RENDER_COALESCE_MS: u64 = 250;
+/// Quiet period after the last resize event before content webviews are
+/// re-laid out. A live drag fires resize events every frame.
+const RESIZE_DEBOUNCE_MS: u64 = 24;
+/// Re-lay out at least this often during a continuous drag so the content
+/// doesn't lag visibly behind the window edge.
+const RESIZE_MAX_WAIT_MS: u64 = 100;
+/// `set_content_bounds` calls closer together than this (e.g. while the
+/// agent panel animates) collapse into one reposition with the last bounds.
+const CONTENT_BOUNDS_THROTTLE_MS: u64 = 16;
+/// Selected text reported with a context menu or `tab-selection` is cut off
+/// at this many characters.
+const MAX_SELECTION_CHARS: usize = 10_000;
+/// Average adult silent-reading speed used when the caller gives no WPM.
+const DEFAULT_READING_WPM: u32 = 200;
+
 #[cfg(target_os = "macos")]
 fn user_agent_override() -> Option<&'static str> {
     Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 14_2) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Safari/605.1.15")
@@ -32,23 +119,135 @@ fn user_agent_override() -> Option<&'static str> {
     None
 }
 
+/// User agents `set_user_agent` accepts by name.
+const USER_AGENT_PRESETS: &[(&str, &str)] = &[
+    (
+        "desktop",
+        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36",
+    ),
+    (
+        "iphone",
+        "Mozilla/5.0 (iPhone; CPU iPhone OS 17_2 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.2 Mobile/15E148 Safari/604.1",
+    ),
+    (
+        "android",
+        "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Mobile Safari/537.36",
+    ),
+];
+
+/// Change the user agent of a live webview; it applies to every later
+/// request and navigation. `None` restores the engine's own.
+#[cfg(target_os = "macos")]
+fn set_webview_user_agent(webview: &Webview, user_agent: Option<&str>) -> Result<(), String> {
+    use objc2_foundation::NSString;
+    use objc2_web_kit::WKWebView;
+
+    let user_agent = user_agent.map(str::to_string);
+    webview
+        .with_webview(move |platform| unsafe {
+            let view: &WKWebView = &*platform.inner().cast();
+            let user_agent = user_agent.as_deref().map(NSString::from_str);
+            view.setCustomUserAgent(user_agent.as_deref());
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+fn set_webview_user_agent(webview: &Webview, user_agent: Option<&str>) -> Result<(), String> {
+    use webkit2gtk::{SettingsExt, WebViewExt};
+
+    let user_agent = user_agent.map(str::to_string);
+    webview
+        .with_webview(move |platform| {
+            if let Some(settings) = WebViewExt::settings(&platform.inner()) {
+                settings.set_user_agent(user_agent.as_deref());
+            }
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn set_webview_user_agent(_webview: &Webview, _user_agent: Option<&str>) -> Result<(), String> {
+    Err("Changing the user agent of an open tab isn't supported on this platform".to_string())
+}
+
+/// Shared by the page scripts in place of their `__EMIT_PRELUDE__` line:
+/// `emitToApp(event, payload)` sends a Tauri event from the page and returns
+/// whether it went out. Failures are swallowed so a page without the event API
+/// (or a throwing one) never breaks the script that reports to the app.
+const EMIT_PRELUDE: &str = r#"
+  const emitToApp = (event, payload) => {
+    try {
+      const api = window.__TAURI__ && window.__TAURI__.event;
+      if (!api || typeof api.emit !== 'function') return false;
+      api.emit(event, payload);
+      return true;
+    } catch {
+      return false;
+    }
+  };
+"#;
+
 const DEBUG_INIT_SCRIPT: &str = r#"
 (() => {
   if (window.__CLAW_DEBUG_CAPTURE__) return;
   window.__CLAW_DEBUG_CAPTURE__ = true;
 
   const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  const CATEGORIES = new Set(__CATEGORIES__);
   const MAX_MESSAGE = 1200;
   const MAX_TEXT = 1600;
+  // Token bucket of MAX_EVENTS_PER_SEC, plus a cap on serialized bytes per
+  // second. Dropped events are summed up in a `dropped` event.
+  const MAX_EVENTS_PER_SEC = __MAX_EVENTS_PER_SEC__;
+  const MAX_BYTES_PER_SEC = 256 * 1024;
+  const DROP_SUMMARY_MS = 5000;
+  let tokens = MAX_EVENTS_PER_SEC;
+  let lastRefillAt = performance.now();
+  let bytesWindowStart = lastRefillAt;
+  let bytesInWindow = 0;
+  let dropped = 0;
+  let dropSummaryTimer = null;
+
+  const admit = (bytes) => {
+    const now = performance.now();
+    tokens = Math.min(MAX_EVENTS_PER_SEC, tokens + ((now - lastRefillAt) / 1000) * MAX_EVENTS_PER_SEC);
+    lastRefillAt = now;
+    if (now - bytesWindowStart >= 1000) {
+      bytesWindowStart = now;
+      bytesInWindow = 0;
+    }
+    if (tokens < 1 || bytesInWindow + bytes > MAX_BYTES_PER_SEC) return false;
+    tokens -= 1;
+    bytesInWindow += bytes;
+    return true;
+  };
+
+  const reportDropped = () => {
+    if (dropSummaryTimer) return;
+    dropSummaryTimer = setTimeout(() => {
+      dropSummaryTimer = null;
+      emitToApp('claw-debug', { type: 'dropped', tabId: TAB_ID, dropped, url: location.href });
+      dropped = 0;
+    }, DROP_SUMMARY_MS);
+  };
 
+  // Render snapshots go to Rust, which coalesces them before the frontend sees them.
   const emit = (type, payload) => {
+    const body = Object.assign({ type, tabId: TAB_ID }, payload);
+    let bytes;
     try {
-      const api = window.__TAURI__ && window.__TAURI__.event;
-      if (!api || typeof api.emit !== 'function') return;
-      api.emit('claw-debug', Object.assign({ type, tabId: TAB_ID }, payload));
+      bytes = JSON.stringify(body).length;
     } catch {
-      // Ignore emit failures.
+      return;
     }
+    if (!admit(bytes)) {
+      dropped += 1;
+      reportDropped();
+      return;
+    }
+    emitToApp(type === 'render' ? 'claw-debug-render' : 'claw-debug', body);
   };
 
   const normalizeWhitespace = (text) => String(text || '').replace(/\s+/g, ' ').trim();
@@ -94,29 +293,102 @@ const DEBUG_INIT_SCRIPT: &str = r#"
     };
   };
 
-  ['log', 'info', 'warn', 'error', 'debug'].forEach(wrapConsole);
+  if (CATEGORIES.has('console')) {
+    ['log', 'info', 'warn', 'error', 'debug'].forEach(wrapConsole);
+  }
 
-  window.addEventListener('error', (event) => {
-    emit('error', {
-      message: normalizeWhitespace(event.message || 'Script error'),
-      filename: event.filename,
-      lineno: event.lineno,
-      colno: event.colno,
-      stack: event.error && event.error.stack ? normalizeWhitespace(event.error.stack) : undefined,
-      url: location.href,
-      title: document.title,
+  const installErrorHooks = () => {
+    window.addEventListener('error', (event) => {
+      emit('error', {
+        message: normalizeWhitespace(event.message || 'Script error'),
+        filename: event.filename,
+        lineno: event.lineno,
+        colno: event.colno,
+        stack: event.error && event.error.stack ? normalizeWhitespace(event.error.stack) : undefined,
+        url: location.href,
+        title: document.title,
+      });
     });
-  });
 
-  window.addEventListener('unhandledrejection', (event) => {
-    emit('unhandledrejection', {
-      reason: truncate(normalizeWhitespace(safeStringify(event.reason)), MAX_MESSAGE),
+    window.addEventListener('unhandledrejection', (event) => {
+      emit('unhandledrejection', {
+        reason: truncate(normalizeWhitespace(safeStringify(event.reason)), MAX_MESSAGE),
+        url: location.href,
+        title: document.title,
+      });
+    });
+  };
+
+  if (CATEGORIES.has('errors')) {
+    installErrorHooks();
+  }
+
+  const emitNetwork = (method, requestUrl, status, startedAt, error) => {
+    emit('network', {
+      method: String(method || 'GET').toUpperCase(),
+      requestUrl: truncate(String(requestUrl), MAX_MESSAGE),
+      status,
+      durationMs: Math.round(performance.now() - startedAt),
+      error: error ? truncate(normalizeWhitespace(safeStringify(error)), MAX_MESSAGE) : undefined,
       url: location.href,
-      title: document.title,
     });
-  });
+  };
+
+  const installNetworkHooks = () => {
+    const originalFetch = window.fetch;
+    if (typeof originalFetch === 'function') {
+      window.fetch = function (input, init) {
+        const startedAt = performance.now();
+        const method = (init && init.method) || (input && input.method) || 'GET';
+        const requestUrl = input && input.url ? input.url : input;
+        return originalFetch.apply(this, arguments).then(
+          (response) => {
+            emitNetwork(method, requestUrl, response.status, startedAt);
+            return response;
+          },
+          (error) => {
+            emitNetwork(method, requestUrl, 0, startedAt, error);
+            throw error;
+          },
+        );
+      };
+    }
+
+    const originalOpen = XMLHttpRequest.prototype.open;
+    const originalSend = XMLHttpRequest.prototype.send;
+    XMLHttpRequest.prototype.open = function (method, requestUrl) {
+      this.__clawRequest = { method, requestUrl };
+      return originalOpen.apply(this, arguments);
+    };
+    XMLHttpRequest.prototype.send = function () {
+      const request = this.__clawRequest;
+      if (request) {
+        const startedAt = performance.now();
+        this.addEventListener('loadend', () => {
+          emitNetwork(request.method, request.requestUrl, this.status, startedAt);
+        }, { once: true });
+      }
+      return originalSend.apply(this, arguments);
+    };
+  };
+
+  if (CATEGORIES.has('network')) {
+    installNetworkHooks();
+  }
+
+  // Re-snapshot after DOM changes settle, but no more than a few times a second,
+  // and at least every RENDER_MAX_WAIT_MS while the page keeps changing.
+  const RENDER_DEBOUNCE_MS = 500;
+  const RENDER_MAX_WAIT_MS = 2000;
+  const MIN_RENDER_INTERVAL_MS = 250;
+  let snapshotSeq = 0;
+  let lastRenderAt = -Infinity;
+  let pendingSince = 0;
+  let renderTimer = null;
 
   const sendRender = () => {
+    snapshotSeq += 1;
+    lastRenderAt = performance.now();
     let textSample = '';
     try {
       if (document.body) {
@@ -132,13 +404,43 @@ const DEBUG_INIT_SCRIPT: &str = r#"
       viewport: { w: window.innerWidth, h: window.innerHeight, dpr: window.devicePixelRatio || 1 },
       scroll: { x: window.scrollX || 0, y: window.scrollY || 0 },
       textSample,
+      snapshotSeq,
     });
   };
 
-  if (document.readyState === 'complete' || document.readyState === 'interactive') {
+  const scheduleRender = () => {
+    const now = performance.now();
+    if (renderTimer) {
+      clearTimeout(renderTimer);
+    } else {
+      pendingSince = now;
+    }
+    const settle = Math.min(RENDER_DEBOUNCE_MS, pendingSince + RENDER_MAX_WAIT_MS - now);
+    const wait = Math.max(settle, lastRenderAt + MIN_RENDER_INTERVAL_MS - now, 0);
+    renderTimer = setTimeout(() => {
+      renderTimer = null;
+      sendRender();
+    }, wait);
+  };
+
+  const startRender = () => {
     setTimeout(sendRender, 0);
+    try {
+      new MutationObserver(scheduleRender).observe(document.documentElement, {
+        childList: true,
+        subtree: true,
+        characterData: true,
+      });
+    } catch {
+      // Ignore observer failures; the initial snapshot still goes out.
+    }
+  };
+
+  if (!CATEGORIES.has('render')) return;
+  if (document.readyState === 'complete' || document.readyState === 'interactive') {
+    startRender();
   } else {
-    document.addEventListener('DOMContentLoaded', () => setTimeout(sendRender, 0), { once: true });
+    document.addEventListener('DOMContentLoaded', startRender, { once: true });
   }
 })();
 "#;
@@ -149,17 +451,12 @@ const LINK_INTERCEPT_SCRIPT: &str = r#"
   window.__CLAW_LINK_INTERCEPT__ = true;
 
   const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  // Replaced in place by `set_link_intercept_config` for open tabs.
+  window.__CLAW_LINK_CONFIG__ = __CONFIG__;
+  const config = () => window.__CLAW_LINK_CONFIG__ || {};
 
-  const emit = (url, reason) => {
-    try {
-      const api = window.__TAURI__ && window.__TAURI__.event;
-      if (!api || typeof api.emit !== 'function') return false;
-      api.emit('tab-open-request', { tabId: TAB_ID, url, reason });
-      return true;
-    } catch {
-      return false;
-    }
-  };
+  const emit = (url, reason) => emitToApp('tab-open-request', { tabId: TAB_ID, url, reason });
 
   const resolveUrl = (href) => {
     try {
@@ -169,458 +466,5969 @@ const LINK_INTERCEPT_SCRIPT: &str = r#"
     }
   };
 
-  const isMac = (() => {
-    const platform = navigator.platform || '';
-    const userAgent = navigator.userAgent || '';
-    return /Mac|iPhone|iPad|iPod/.test(platform) || /Macintosh|Mac OS X/.test(userAgent);
-  })();
+  const linkUrl = (event) => {
+    const target = event.target;
+    if (!(target instanceof Element)) return null;
+    const link = target.closest('a[href]');
+    if (!link) return null;
+
+    const href = link.getAttribute('href');
+    if (!href) return null;
+
+    return resolveUrl(href);
+  };
 
   const handler = (event) => {
     if (!event) return;
-    const wantsNewTab = event.shiftKey || (isMac && event.metaKey);
-    if (!wantsNewTab) return;
+    const { enabled, useShift, useCtrl, useMeta } = config();
+    if (!enabled) return;
+    // The reason names the modifier that opened the link.
+    const reason = (useShift && event.shiftKey && 'shift-click')
+      || (useCtrl && event.ctrlKey && 'ctrl-click')
+      || (useMeta && event.metaKey && 'meta-click');
+    if (!reason) return;
     if (event.defaultPrevented) return;
     if (event.button !== 0) return;
 
-    const target = event.target;
-    if (!(target instanceof Element)) return;
-    const link = target.closest('a[href]');
-    if (!link) return;
+    const url = linkUrl(event);
+    if (!url) return;
 
-    const href = link.getAttribute('href');
-    if (!href) return;
+    if (!emit(url, reason)) return;
+
+    event.preventDefault();
+    event.stopPropagation();
+  };
+
+  // Middle-button press on a link would start autoscroll; suppress it only there.
+  const middleDown = (event) => {
+    if (!event || event.button !== 1 || event.defaultPrevented) return;
+    if (!config().enabled) return;
+    if (linkUrl(event)) event.preventDefault();
+  };
+
+  const middleClick = (event) => {
+    if (!event || event.button !== 1 || event.defaultPrevented) return;
+    if (!config().enabled) return;
 
-    const url = resolveUrl(href);
+    const url = linkUrl(event);
     if (!url) return;
 
-    if (!emit(url, 'shift-click')) return;
+    if (!emit(url, 'middle-click')) return;
 
     event.preventDefault();
     event.stopPropagation();
   };
 
   document.addEventListener('click', handler, true);
+  document.addEventListener('mousedown', middleDown, true);
+  document.addEventListener('auxclick', middleClick, true);
 })();
 "#;
 
-fn debug_capture_enabled() -> bool {
-    if cfg!(debug_assertions) {
-        return true;
-    }
-    match std::env::var("CLAW_DEBUG_CAPTURE") {
-        Ok(value) => {
-            let normalized = value.trim().to_lowercase();
-            normalized == "1" || normalized == "true" || normalized == "yes"
-        }
-        Err(_) => false,
-    }
-}
+/// Replaces the native context menu: a right-click emits `tab-context-menu`
+/// with what is under the pointer so the app can render its own menu. The
+/// native menu still shows when interception is off, when Tauri's event API
+/// is unavailable, on Shift+right-click, and when the page handled the event.
+const CONTEXT_MENU_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_CONTEXT_MENU__) return;
+  window.__CLAW_CONTEXT_MENU__ = true;
 
-fn debug_init_script(tab_id: &str) -> Option<String> {
-    if !debug_capture_enabled() {
-        return None;
+  const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  const MAX_SELECTION_CHARS = __MAX_SELECTION_CHARS__;
+  // Replaced in place by `set_context_menu_intercept` for open tabs.
+  window.__CLAW_CONTEXT_MENU_ENABLED__ = __ENABLED__;
+
+  const resolveUrl = (href) => {
+    if (!href) return null;
+    try {
+      return new URL(href, document.baseURI).toString();
+    } catch {
+      return null;
     }
-    let tab_id_literal = serde_json::to_string(tab_id).unwrap_or_else(|_| "\"unknown\"".to_string());
-    Some(DEBUG_INIT_SCRIPT.replace("__TAB_ID__", &tab_id_literal))
-}
+  };
 
-fn link_intercept_script(tab_id: &str) -> String {
-    let tab_id_literal = serde_json::to_string(tab_id).unwrap_or_else(|_| "\"unknown\"".to_string());
-    LINK_INTERCEPT_SCRIPT.replace("__TAB_ID__", &tab_id_literal)
-}
+  // Bubble phase on window, so a page with its own menu can claim the event first.
+  window.addEventListener('contextmenu', (event) => {
+    if (!window.__CLAW_CONTEXT_MENU_ENABLED__ || event.defaultPrevented || event.shiftKey) return;
+    const target = event.target instanceof Element ? event.target : null;
+    const link = target && target.closest('a[href]');
+    const image = target && target.closest('img');
+    const selection = String(window.getSelection() || '').trim();
+    const payload = {
+      tabId: TAB_ID,
+      x: event.clientX,
+      y: event.clientY,
+      linkUrl: link ? resolveUrl(link.getAttribute('href')) : null,
+      imageUrl: image ? resolveUrl(image.currentSrc || image.getAttribute('src')) : null,
+      selectionText: selection ? selection.slice(0, MAX_SELECTION_CHARS) : null,
+    };
+    if (!emitToApp('tab-context-menu', payload)) return;
+    event.preventDefault();
+  });
+})();
+"#;
 
-fn normalize_tab_url(url: &url::Url) -> String {
-    if url.path().ends_with(BLANK_PAGE_PATH) {
-        "about:blank".to_string()
-    } else {
-        url.to_string()
-    }
-}
+/// Counts the page's in-flight `fetch` and XHR requests for
+/// `wait_for_network_idle`, exposed as `window.__CLAW_NETWORK__`.
+const NETWORK_ACTIVITY_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_NETWORK__) return;
 
-/// JS reports bounds relative to the webview viewport. On macOS the
-/// window `inner_size()` includes the title bar region but the viewport
-/// only covers below the title bar. `chrome_y_offset` is the physical-pixel
-/// gap between the two coordinate systems, stored once the JS first reports.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct ContentBounds {
-    pub left: f64,
-    pub top: f64,
-    pub width: f64,
-    pub height: f64,
-}
+  let inFlight = 0;
+  let lastChange = performance.now();
+  const listeners = new Set();
+  const update = (delta) => {
+    inFlight = Math.max(0, inFlight + delta);
+    lastChange = performance.now();
+    listeners.forEach((listener) => listener());
+  };
 
-/// Compute physical bounds for child webviews.
-///
-/// Uses the stored JS layout (`left`, `top` from viewport) combined with the
-/// current `inner_size` so that width/height stay correct even when the Rust
-/// resize handler fires before the JS handler updates the stored bounds.
-fn content_bounds(window: &Window, state: &TabState) -> Result<(PhysicalPosition<i32>, PhysicalSize<u32>), String> {
-    let inner_size = window.inner_size().map_err(|e| e.to_string())?;
-    let scale = window.scale_factor().map_err(|e| e.to_string())?;
-    let y_off = state.chrome_y_offset;
+  window.__CLAW_NETWORK__ = {
+    inFlight: () => inFlight,
+    idleFor: () => (inFlight > 0 ? 0 : performance.now() - lastChange),
+    subscribe: (listener) => {
+      listeners.add(listener);
+      return () => listeners.delete(listener);
+    },
+  };
 
-    // Logical-pixel offsets for left edge and top edge (from viewport origin).
-    let (left_logical, top_viewport) = if let Some(b) = &state.content_bounds {
-        (b.left, b.top)
-    } else {
-        (AGENT_PANEL_WIDTH + TAB_LIST_WIDTH, NAV_BAR_HEIGHT)
+  const originalFetch = window.fetch;
+  if (typeof originalFetch === 'function') {
+    window.fetch = function () {
+      update(1);
+      let pending;
+      try {
+        pending = originalFetch.apply(this, arguments);
+      } catch (error) {
+        update(-1);
+        throw error;
+      }
+      pending.then(() => update(-1), () => update(-1));
+      return pending;
     };
+  }
 
-    // Convert to physical pixels, adding the title-bar offset to y.
-    let left_px  = (left_logical * scale).round().max(0.0) as i32;
-    let top_px   = ((top_viewport + y_off) * scale).round().max(0.0) as i32;
+  const originalSend = XMLHttpRequest.prototype.send;
+  XMLHttpRequest.prototype.send = function () {
+    update(1);
+    this.addEventListener('loadend', () => update(-1), { once: true });
+    try {
+      return originalSend.apply(this, arguments);
+    } catch (error) {
+      update(-1);
+      throw error;
+    }
+  };
+})();
+"#;
 
-    // Width and height are derived from the *current* inner_size so they stay
-    // correct even when stored JS bounds are stale during a resize.
-    let width_px  = inner_size.width.saturating_sub(left_px.max(0) as u32);
-    let height_px = inner_size.height.saturating_sub(top_px.max(0) as u32);
+/// Tracks what closing the tab would throw away for `request_close_tab`:
+/// `beforeunload` handlers and form fields edited since the last submit.
+/// `window.__CLAW_CLOSE_GUARD__.shouldConfirm()` runs the handlers against a
+/// stand-in event, so only ones that would make a browser prompt count.
+const CLOSE_GUARD_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_CLOSE_GUARD__) return;
 
-    Ok((
-        PhysicalPosition::new(left_px, top_px),
-        PhysicalSize::new(width_px, height_px),
-    ))
-}
+  const handlers = new Set();
+  let dirty = false;
 
-fn apply_bounds(window: &Window, webview: &Webview, state: &TabState) -> Result<(), String> {
-    let _ = webview.set_auto_resize(false);
-    let (position, size) = content_bounds(window, state)?;
-    let bounds = tauri::Rect {
-        position: position.into(),
-        size: size.into(),
+  const originalAdd = window.addEventListener;
+  const originalRemove = window.removeEventListener;
+  window.addEventListener = function (type, listener, options) {
+    if (this === window && type === 'beforeunload' && listener) handlers.add(listener);
+    return originalAdd.call(this, type, listener, options);
+  };
+  window.removeEventListener = function (type, listener, options) {
+    if (this === window && type === 'beforeunload') handlers.delete(listener);
+    return originalRemove.call(this, type, listener, options);
+  };
+
+  const isField = (el) => {
+    if (!el) return false;
+    if (el.isContentEditable) return true;
+    if (el.tagName === 'INPUT') return !['search', 'button', 'submit', 'reset'].includes(el.type);
+    return el.tagName === 'TEXTAREA' || el.tagName === 'SELECT';
+  };
+  document.addEventListener('input', (event) => {
+    if (isField(event.target)) dirty = true;
+  }, true);
+  document.addEventListener('submit', () => {
+    dirty = false;
+  }, true);
+
+  const wouldPrompt = () => {
+    const event = {
+      type: 'beforeunload',
+      target: window,
+      currentTarget: window,
+      cancelable: true,
+      defaultPrevented: false,
+      returnValue: '',
+      preventDefault() {
+        this.defaultPrevented = true;
+      },
+      stopPropagation() {},
+      stopImmediatePropagation() {},
     };
-    webview
-        .set_bounds(bounds)
-        .map_err(|e| format!("Failed to set webview bounds: {}", e))?;
-    Ok(())
-}
+    const run = (handler) => {
+      try {
+        return typeof handler === 'function' ? handler.call(window, event) : handler.handleEvent(event);
+      } catch {
+        return undefined;
+      }
+    };
+    handlers.forEach(run);
+    if (typeof window.onbeforeunload === 'function') {
+      const result = run(window.onbeforeunload);
+      if (typeof result === 'string' && result) event.returnValue = result;
+    }
+    return event.defaultPrevented || !!event.returnValue;
+  };
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TabInfo {
-    pub id: String,
-    pub url: String,
-    pub title: String,
-}
+  window.__CLAW_CLOSE_GUARD__ = {
+    shouldConfirm: () => dirty || wouldPrompt(),
+  };
+})();
+"#;
 
-pub struct TabState {
-    pub tabs: HashMap<String, TabInfo>,
-    pub active_tab: Option<String>,
-    pub content_bounds: Option<ContentBounds>,
-    /// Title-bar height in logical pixels. On macOS, `inner_size()` includes
-    /// the title bar but the JS viewport does not. Computed on first
-    /// `set_content_bounds` call and reused for all subsequent positioning.
-    pub chrome_y_offset: f64,
-}
+/// `eval_with_result` body that resolves to whether `CLOSE_GUARD_SCRIPT`
+/// wants the close confirmed.
+const CLOSE_GUARD_CHECK_SCRIPT: &str = r#"
+  const guard = window.__CLAW_CLOSE_GUARD__;
+  return !!(guard && guard.shouldConfirm());
+"#;
 
-impl TabState {
-    pub fn new() -> Self {
-        Self {
-            tabs: HashMap::new(),
-            active_tab: None,
-            content_bounds: None,
-            chrome_y_offset: 0.0,
-        }
-    }
-}
+/// Reports the user's text selection as `tab-selection` once it settles, and
+/// once more with empty text when it collapses. Text inside form fields and
+/// same-origin iframes is included; cross-origin frames are out of reach.
+/// `window.__CLAW_SELECTION__.read()` serves `get_selection`.
+const SELECTION_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_SELECTION__) return;
 
-/// Create a new content webview tab positioned below the chrome.
-pub fn create_tab(
-    app: &tauri::AppHandle,
-    state: &mut TabState,
-    url: &str,
-) -> Result<String, String> {
-    let id = uuid::Uuid::new_v4().to_string();
-    let label = format!("tab-{}", id);
+  const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  const MAX_CHARS = __MAX_SELECTION_CHARS__;
+  const DEBOUNCE_MS = 250;
+  let lastText = '';
+  let timer = null;
 
-    let window = app
-        .get_window("main")
-        .ok_or("Main window not found")?;
+  const toRect = (rect, offsetX, offsetY) => ({
+    x: rect.left + offsetX,
+    y: rect.top + offsetY,
+    width: rect.width,
+    height: rect.height,
+  });
 
-    let (position, size) = content_bounds(&window, state)?;
+  // The focused form field's selected text, which getSelection() doesn't expose.
+  const fieldSelection = (doc, offsetX, offsetY) => {
+    const field = doc.activeElement;
+    if (!field || !(field instanceof doc.defaultView.HTMLTextAreaElement || field instanceof doc.defaultView.HTMLInputElement)) {
+      return null;
+    }
+    let start;
+    let end;
+    try {
+      start = field.selectionStart;
+      end = field.selectionEnd;
+    } catch {
+      return null;
+    }
+    if (start == null || end == null || start === end) return null;
+    return { text: field.value.slice(start, end), rect: toRect(field.getBoundingClientRect(), offsetX, offsetY) };
+  };
 
-    let webview_url = if url == "about:blank" || url.is_empty() {
-        WebviewUrl::App(BLANK_PAGE_PATH.into())
-    } else {
-        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
-        WebviewUrl::External(parsed)
-    };
+  const documentSelection = (doc, offsetX, offsetY) => {
+    const field = fieldSelection(doc, offsetX, offsetY);
+    if (field) return field;
 
-    let mut builder = WebviewBuilder::new(&label, webview_url);
-    if let Some(user_agent) = user_agent_override() {
-        builder = builder.user_agent(user_agent);
+    const selection = doc.getSelection();
+    if (selection && !selection.isCollapsed && selection.rangeCount > 0) {
+      const rect = selection.getRangeAt(0).getBoundingClientRect();
+      return { text: String(selection), rect: toRect(rect, offsetX, offsetY) };
     }
-    if let Some(script) = debug_init_script(&id) {
-        builder = builder.initialization_script(script);
+
+    // Follow focus into same-origin iframes; cross-origin access throws.
+    const frame = doc.activeElement;
+    if (frame && frame.tagName === 'IFRAME') {
+      try {
+        const inner = frame.contentDocument;
+        if (inner) {
+          const frameRect = frame.getBoundingClientRect();
+          return documentSelection(inner, offsetX + frameRect.left + frame.clientLeft, offsetY + frameRect.top + frame.clientTop);
+        }
+      } catch {
+        // Cross-origin frame.
+      }
     }
-    builder = builder.initialization_script(link_intercept_script(&id));
+    return null;
+  };
 
-    let app_handle = app.clone();
-    let tab_id = id.clone();
+  const read = () => {
+    const found = documentSelection(document, 0, 0);
+    const text = found ? found.text.trim() : '';
+    if (!text) return { text: '', rect: null, truncated: false };
+    return { text: text.slice(0, MAX_CHARS), rect: found.rect, truncated: text.length > MAX_CHARS };
+  };
+
+  const report = () => {
+    timer = null;
+    const selection = read();
+    // A collapsed selection is reported once, to clear the previous one.
+    if (selection.text === lastText) return;
+    lastText = selection.text;
+    emitToApp('tab-selection', Object.assign({ tabId: TAB_ID }, selection));
+  };
+
+  const schedule = () => {
+    if (timer !== null) clearTimeout(timer);
+    timer = setTimeout(report, DEBOUNCE_MS);
+  };
+
+  window.__CLAW_SELECTION__ = { read };
+  document.addEventListener('selectionchange', schedule);
+  // Field selections don't always fire selectionchange on the document.
+  document.addEventListener('select', schedule, true);
+  document.addEventListener('mouseup', schedule, true);
+  document.addEventListener('keyup', schedule, true);
+})();
+"#;
+
+/// `eval_with_result` body for `get_selection`: the live selection, read the
+/// same way `SELECTION_SCRIPT` reports it.
+const GET_SELECTION_SCRIPT: &str = r#"
+  if (!window.__CLAW_SELECTION__) return { text: '', rect: null, truncated: false };
+  return window.__CLAW_SELECTION__.read();
+"#;
+
+/// `eval_with_result` body that copies `__TEXT__` to the clipboard from the
+/// page, trying the async Clipboard API before `execCommand('copy')`.
+const COPY_TEXT_SCRIPT: &str = r#"
+  const TEXT = __TEXT__;
+  try {
+    await navigator.clipboard.writeText(TEXT);
+    return true;
+  } catch {
+    const area = document.createElement('textarea');
+    area.value = TEXT;
+    area.setAttribute('readonly', '');
+    area.style.cssText = 'position: fixed; top: -1000px; opacity: 0;';
+    (document.body || document.documentElement).appendChild(area);
+    area.select();
+    const copied = document.execCommand('copy');
+    area.remove();
+    if (!copied) throw new Error('The page did not allow copying to the clipboard');
+    return true;
+  }
+"#;
+
+/// `eval_with_result` body that downloads the image at `__URL__` through the
+/// page, so it lands in the downloads folder like any other download. The
+/// image is fetched into a blob first, since `download` is ignored on
+/// cross-origin links.
+const SAVE_IMAGE_SCRIPT: &str = r#"
+  const URL_TO_SAVE = __URL__;
+  let response;
+  try {
+    response = await fetch(URL_TO_SAVE, { credentials: 'include' });
+  } catch {
+    throw new Error('The image could not be fetched from this page');
+  }
+  if (!response.ok) throw new Error(`Image request failed with status ${response.status}`);
+  const blob = await response.blob();
+  const name = decodeURIComponent(new URL(URL_TO_SAVE).pathname.split('/').pop() || '') || 'image';
+  const objectUrl = URL.createObjectURL(blob);
+  const link = document.createElement('a');
+  link.href = objectUrl;
+  link.download = name;
+  link.style.display = 'none';
+  (document.body || document.documentElement).appendChild(link);
+  link.click();
+  link.remove();
+  setTimeout(() => URL.revokeObjectURL(objectUrl), 60000);
+  return name;
+"#;
+
+/// Estimates load progress from `document.readyState` and how many of the
+/// document's subresources have finished, emitting a monotonic
+/// `tab-load-progress` in [0, 1].
+const LOAD_PROGRESS_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_LOAD_PROGRESS__) return;
+  window.__CLAW_LOAD_PROGRESS__ = true;
+
+  const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  const MIN_STEP = 0.05;
+  let lastProgress = 0;
+
+  const resourceFraction = () => {
+    const expected = document.querySelectorAll(
+      'img[src], script[src], link[rel~="stylesheet"][href], iframe[src], video[src], audio[src]'
+    ).length;
+    if (!expected) return 0;
+    const loaded = performance.getEntriesByType('resource').length;
+    return Math.min(1, loaded / expected);
+  };
+
+  const estimate = () => {
+    const fraction = resourceFraction();
+    switch (document.readyState) {
+      case 'complete':
+        return 1;
+      case 'interactive':
+        return 0.6 + 0.35 * fraction;
+      default:
+        return 0.1 + 0.4 * fraction;
+    }
+  };
+
+  const update = () => {
+    const progress = Math.round(estimate() * 100) / 100;
+    if (progress <= lastProgress) return;
+    if (progress < 1 && progress - lastProgress < MIN_STEP) return;
+    lastProgress = progress;
+    emitToApp('tab-load-progress', { tabId: TAB_ID, progress });
+  };
+
+  update();
+  document.addEventListener('readystatechange', update);
+  if (typeof PerformanceObserver === 'function') {
+    try {
+      const observer = new PerformanceObserver(() => {
+        update();
+        if (lastProgress >= 1) observer.disconnect();
+      });
+      observer.observe({ type: 'resource', buffered: true });
+    } catch {
+      // Older engines: readyState transitions alone drive the estimate.
+    }
+  }
+})();
+"#;
+
+/// Reports the page's favicon as `tab-favicon`, preferring declared icons over
+/// touch icons over the default `/favicon.ico`, and re-reports when the head changes.
+const FAVICON_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_FAVICON__) return;
+  window.__CLAW_FAVICON__ = true;
+
+  const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  let lastUrl;
+
+  const resolveUrl = (href) => {
+    try {
+      return new URL(href, document.baseURI).toString();
+    } catch {
+      return null;
+    }
+  };
+
+  const findFavicon = () => {
+    let touchIcon = null;
+    for (const link of document.querySelectorAll('link[rel][href]')) {
+      const rels = (link.getAttribute('rel') || '').toLowerCase().split(/\s+/);
+      if (rels.includes('icon')) {
+        const url = resolveUrl(link.getAttribute('href'));
+        if (url) return url;
+      }
+      if (!touchIcon && (rels.includes('apple-touch-icon') || rels.includes('apple-touch-icon-precomposed'))) {
+        touchIcon = resolveUrl(link.getAttribute('href'));
+      }
+    }
+    if (touchIcon) return touchIcon;
+    if (location.protocol === 'http:' || location.protocol === 'https:') {
+      return resolveUrl('/favicon.ico');
+    }
+    return null;
+  };
+
+  const report = () => {
+    const url = findFavicon();
+    if (url === lastUrl) return;
+    lastUrl = url;
+    emitToApp('tab-favicon', { tabId: TAB_ID, url });
+  };
+
+  const start = () => {
+    report();
+    const root = document.head || document.documentElement;
+    if (!root) return;
+    new MutationObserver(report).observe(root, {
+      childList: true,
+      subtree: true,
+      attributes: true,
+      attributeFilter: ['href', 'rel'],
+    });
+  };
+
+  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', start, { once: true });
+  } else {
+    start();
+  }
+})();
+"#;
+
+/// Reports `document.title` as `tab-title-changed` once the document is parsed
+/// and again whenever the `<title>` element or its text changes.
+const TITLE_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_TITLE__) return;
+  window.__CLAW_TITLE__ = true;
+
+  const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  let lastTitle;
+
+  const report = () => {
+    const title = (document.title || '').trim();
+    if (!title || title === lastTitle) return;
+    lastTitle = title;
+    emitToApp('tab-title-changed', { tabId: TAB_ID, title });
+  };
+
+  const start = () => {
+    report();
+    const root = document.head || document.documentElement;
+    if (!root) return;
+    new MutationObserver(report).observe(root, {
+      childList: true,
+      subtree: true,
+      characterData: true,
+    });
+  };
+
+  if (document.readyState === 'loading') {
+    document.addEventListener('DOMContentLoaded', start, { once: true });
+  } else {
+    start();
+  }
+})();
+"#;
+
+/// Mutes the page's `<audio>`/`<video>` elements while the tab is muted,
+/// including ones added later, and reports `tab-audio-state` whenever the
+/// page starts or stops playing audible media. Web Audio output isn't covered.
+/// Emits `tab-audio-ready` on each new document so Rust can reapply the mute.
+const AUDIO_SCRIPT: &str = r#"
+(() => {
+  if (window.__CLAW_AUDIO__) return;
+
+  const TAB_ID = __TAB_ID__;
+__EMIT_PRELUDE__
+  let muted = false;
+  let lastPlaying = false;
+
+  const mediaElements = () => document.querySelectorAll('audio, video');
+
+  const isPlaying = () => {
+    for (const el of mediaElements()) {
+      if (!el.paused && !el.ended && !el.__clawPageMuted && el.volume > 0) return true;
+    }
+    return false;
+  };
+
+  const report = () => {
+    const playing = isPlaying();
+    if (playing === lastPlaying) return;
+    lastPlaying = playing;
+    emitToApp('tab-audio-state', { tabId: TAB_ID, playing, muted });
+  };
+
+  // Remember the page's own muted flag so unmuting restores it.
+  const apply = (el) => {
+    if (muted) {
+      if (el.__clawPageMuted === undefined) el.__clawPageMuted = el.muted;
+      el.muted = true;
+    } else if (el.__clawPageMuted !== undefined) {
+      el.muted = el.__clawPageMuted;
+      el.__clawPageMuted = undefined;
+    }
+  };
+
+  const setMuted = (value) => {
+    muted = !!value;
+    mediaElements().forEach(apply);
+    lastPlaying = !isPlaying();
+    report();
+  };
+
+  window.__CLAW_AUDIO__ = { setMuted };
+
+  for (const type of ['play', 'playing', 'pause', 'ended', 'emptied', 'volumechange']) {
+    document.addEventListener(type, (event) => {
+      const el = event.target;
+      if (!(el instanceof HTMLMediaElement)) return;
+      if (type === 'volumechange' && muted && el.__clawPageMuted !== undefined && !el.muted) {
+        // The page unmuted its element; keep it silent but track its intent.
+        el.__clawPageMuted = false;
+        el.muted = true;
+        return;
+      }
+      if (type === 'play' && muted) apply(el);
+      report();
+    }, true);
+  }
+
+  const start = () => {
+    const root = document.documentElement;
+    if (!root) return;
+    new MutationObserver((mutations) => {
+      if (!muted) return;
+      for (const mutation of mutations) {
+        for (const node of mutation.addedNodes) {
+          if (node instanceof HTMLMediaElement) apply(node);
+          else if (node.querySelectorAll) node.querySelectorAll('audio, video').forEach(apply);
+        }
+      }
+    }).observe(root, { childList: true, subtree: true });
+  };
+
+  if (document.documentElement) {
+    start();
+  } else {
+    document.addEventListener('DOMContentLoaded', start, { once: true });
+  }
+  emitToApp('tab-audio-ready', { tabId: TAB_ID });
+})();
+"#;
+
+/// Adds or updates the `<style>` element of a user style.
+const USER_STYLE_SCRIPT: &str = r#"
+(function (id, css) {
+  const elementId = 'claw-user-style-' + id;
+  let style = document.getElementById(elementId);
+  if (!style) {
+    style = document.createElement('style');
+    style.id = elementId;
+    (document.head || document.documentElement).appendChild(style);
+  }
+  style.textContent = css;
+})(__ID__, __CSS__);
+"#;
+
+/// Wraps a function body so its (awaited) return value is emitted back to Rust
+/// as a `claw-eval-result` event correlated by request id. A throw is reported
+/// with its name, message and stack; `undefined` is told apart from `null`.
+const EVAL_RESULT_SCRIPT: &str = r#"
+(() => {
+  const REQUEST_ID = __REQUEST_ID__;
+__EMIT_PRELUDE__
+
+  // If the emit fails the caller times out instead.
+  const emit = (payload) => emitToApp('claw-eval-result', Object.assign({ requestId: REQUEST_ID }, payload));
+
+  const settle = (value) => {
+    if (value === undefined) {
+      emit({ ok: true, value: null, undefined: true });
+      return;
+    }
+    let json;
+    try {
+      json = JSON.stringify(value);
+    } catch (err) {
+      emit({ ok: false, error: `Result is not JSON-serializable: ${err && err.message ? err.message : err}` });
+      return;
+    }
+    if (json === undefined) {
+      emit({ ok: false, error: `Result is not JSON-serializable: ${typeof value}` });
+      return;
+    }
+    emit({ ok: true, value });
+  };
+
+  const fail = (err) => {
+    const isError = err instanceof Error;
+    const message = isError ? String(err.message) : String(err);
+    emit({
+      ok: false,
+      error: message,
+      exception: {
+        name: isError ? String(err.name) : typeof err,
+        message,
+        stack: isError && err.stack ? String(err.stack) : null,
+      },
+    });
+  };
+
+  Promise.resolve()
+    .then(async () => {
+__BODY__
+    })
+    .then(settle, fail);
+})();
+"#;
+
+/// Back/forward availability from the Navigation API, for platforms without a
+/// native back-forward list query. Without the API only "back" can be guessed.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+const HISTORY_AVAILABILITY_SCRIPT: &str = r#"
+  const nav = window.navigation;
+  if (nav && typeof nav.canGoBack === 'boolean') {
+    return [nav.canGoBack, nav.canGoForward];
+  }
+  return [history.length > 1, false];
+"#;
+
+/// Current scroll position and the extent a full-page capture has to cover.
+const SCROLL_METRICS_SCRIPT: &str = r#"
+  const root = document.scrollingElement || document.documentElement;
+  return {
+    x: window.scrollX,
+    y: window.scrollY,
+    viewportHeight: window.innerHeight,
+    scrollHeight: Math.max(root.scrollHeight, window.innerHeight),
+  };
+"#;
+
+/// `eval_with_result` body reporting the scroll offset, in the render debug
+/// event's `{ x, y }` shape, and how far the document can scroll.
+const GET_SCROLL_SCRIPT: &str = r#"
+  const root = document.scrollingElement || document.documentElement;
+  return {
+    x: window.scrollX || 0,
+    y: window.scrollY || 0,
+    maxX: Math.max(0, root.scrollWidth - root.clientWidth),
+    maxY: Math.max(0, root.scrollHeight - root.clientHeight),
+  };
+"#;
+
+/// `eval_with_result` body that scrolls the document to (`__X__`, `__Y__`),
+/// clamped to its bounds. With `__SMOOTH__` it animates and waits for the
+/// scroll to end (at most `__SMOOTH_WAIT_MS__`). Resolves to the position
+/// reached, shaped like `GET_SCROLL_SCRIPT`'s.
+const SET_SCROLL_SCRIPT: &str = r#"
+  const SMOOTH = __SMOOTH__;
+  const SMOOTH_WAIT_MS = __SMOOTH_WAIT_MS__;
+  const root = document.scrollingElement || document.documentElement;
+  const position = () => ({
+    x: window.scrollX || 0,
+    y: window.scrollY || 0,
+    maxX: Math.max(0, root.scrollWidth - root.clientWidth),
+    maxY: Math.max(0, root.scrollHeight - root.clientHeight),
+  });
+
+  const { x, y, maxX, maxY } = position();
+  const left = Math.min(Math.max(0, __X__), maxX);
+  const top = Math.min(Math.max(0, __Y__), maxY);
+  if (left === x && top === y) return position();
+
+  if (SMOOTH) {
+    const ended = new Promise((resolve) => {
+      const timer = setTimeout(resolve, SMOOTH_WAIT_MS);
+      window.addEventListener('scrollend', () => {
+        clearTimeout(timer);
+        resolve();
+      }, { once: true });
+    });
+    window.scrollTo({ left, top, behavior: 'smooth' });
+    await ended;
+  } else {
+    window.scrollTo({ left, top, behavior: 'instant' });
+  }
+  return position();
+"#;
+
+/// `eval_with_result` body that scrolls the element matching `__SELECTOR__`
+/// to the middle of the viewport and, when `__CLICK__` is true, clicks its
+/// center with the pointer/mouse event sequence a real click produces.
+/// Resolves to the element's viewport rect after scrolling.
+const ELEMENT_ACTION_SCRIPT: &str = r#"
+  const SELECTOR = __SELECTOR__;
+  const el = document.querySelector(SELECTOR);
+  if (!el) throw new Error(`No element matches selector ${SELECTOR}`);
+  el.scrollIntoView({ behavior: 'instant', block: 'center', inline: 'nearest' });
+  const rect = el.getBoundingClientRect();
+
+  if (__CLICK__) {
+    const init = {
+      bubbles: true,
+      cancelable: true,
+      composed: true,
+      view: window,
+      button: 0,
+      clientX: rect.left + rect.width / 2,
+      clientY: rect.top + rect.height / 2,
+    };
+    const pointer = (type, buttons) => {
+      if (typeof PointerEvent === 'function') {
+        el.dispatchEvent(new PointerEvent(type, { ...init, buttons, pointerId: 1, pointerType: 'mouse', isPrimary: true }));
+      }
+    };
+    pointer('pointerdown', 1);
+    el.dispatchEvent(new MouseEvent('mousedown', { ...init, buttons: 1 }));
+    if (typeof el.focus === 'function') el.focus({ preventScroll: true });
+    pointer('pointerup', 0);
+    el.dispatchEvent(new MouseEvent('mouseup', { ...init, buttons: 0 }));
+    el.dispatchEvent(new MouseEvent('click', { ...init, buttons: 0, detail: 1 }));
+  }
+
+  return { x: rect.x, y: rect.y, width: rect.width, height: rect.height };
+"#;
+
+/// `eval_with_result` body that fills the element matching `__SELECTOR__` with
+/// `__TEXT__` the way frameworks expect: the value goes through the native
+/// setter (so React's value tracking sees it) and `input`/`change` fire.
+/// With `__SIMULATE_TYPING__` each character also gets keydown/keypress/keyup.
+/// `<select>` picks the option whose value or label matches; contenteditable
+/// elements get the text inserted as if typed. Resolves to the new value.
+const FILL_INPUT_SCRIPT: &str = r#"
+  const SELECTOR = __SELECTOR__;
+  const TEXT = __TEXT__;
+  const SIMULATE_TYPING = __SIMULATE_TYPING__;
+  const el = document.querySelector(SELECTOR);
+  if (!el) throw new Error(`No element matches selector ${SELECTOR}`);
+  el.scrollIntoView({ behavior: 'instant', block: 'center', inline: 'nearest' });
+  if (typeof el.focus === 'function') el.focus({ preventScroll: true });
+
+  const fire = (type) => el.dispatchEvent(new Event(type, { bubbles: true }));
+  const key = (type, ch) => el.dispatchEvent(
+    new KeyboardEvent(type, { key: ch, bubbles: true, cancelable: true, composed: true })
+  );
+  const inputEvent = (data) => el.dispatchEvent(
+    new InputEvent('input', { inputType: 'insertText', data, bubbles: true, composed: true })
+  );
+
+  if (el instanceof HTMLSelectElement) {
+    const option = Array.from(el.options).find(
+      (o) => o.value === TEXT || o.label === TEXT || o.text.trim() === TEXT
+    );
+    if (!option) throw new Error(`No option matching ${JSON.stringify(TEXT)} in ${SELECTOR}`);
+    el.value = option.value;
+    fire('input');
+    fire('change');
+    return el.value;
+  }
+
+  if (el.isContentEditable) {
+    const range = document.createRange();
+    range.selectNodeContents(el);
+    const selection = window.getSelection();
+    selection.removeAllRanges();
+    selection.addRange(range);
+    const chunks = SIMULATE_TYPING ? Array.from(TEXT) : [TEXT];
+    if (!chunks.length) document.execCommand('delete');
+    let written = '';
+    for (const chunk of chunks) {
+      if (SIMULATE_TYPING) {
+        key('keydown', chunk);
+        key('keypress', chunk);
+      }
+      // execCommand fires its own input event; fall back to a plain write.
+      if (!document.execCommand('insertText', false, chunk)) {
+        written += chunk;
+        el.textContent = written;
+        inputEvent(chunk);
+      }
+      if (SIMULATE_TYPING) key('keyup', chunk);
+    }
+    return el.innerText;
+  }
+
+  if (!(el instanceof HTMLInputElement || el instanceof HTMLTextAreaElement)) {
+    throw new Error(`Element ${SELECTOR} is not a fillable input`);
+  }
+  const proto = el instanceof HTMLTextAreaElement ? HTMLTextAreaElement.prototype : HTMLInputElement.prototype;
+  const setValue = Object.getOwnPropertyDescriptor(proto, 'value').set;
+
+  if (SIMULATE_TYPING) {
+    setValue.call(el, '');
+    fire('input');
+    for (const ch of Array.from(TEXT)) {
+      key('keydown', ch);
+      key('keypress', ch);
+      setValue.call(el, el.value + ch);
+      inputEvent(ch);
+      key('keyup', ch);
+    }
+  } else {
+    setValue.call(el, TEXT);
+    inputEvent(TEXT);
+  }
+  fire('change');
+  return el.value;
+"#;
+
+/// `eval_with_result` body that waits until `__SELECTOR__` matches an element,
+/// re-checking on DOM mutations and on a short poll (visibility can change
+/// through layout alone). With `__VISIBLE__` the element must also be
+/// rendered with a non-empty box. Resolves to its rect, or rejects after
+/// `__TIMEOUT_MS__`.
+const WAIT_FOR_SELECTOR_SCRIPT: &str = r#"
+  const SELECTOR = __SELECTOR__;
+  const VISIBLE = __VISIBLE__;
+  const TIMEOUT_MS = __TIMEOUT_MS__;
+
+  const isVisible = (el) => {
+    const style = getComputedStyle(el);
+    if (style.visibility === 'hidden' || style.display === 'none') return false;
+    if (el.offsetParent === null && style.position !== 'fixed' && el !== document.body) return false;
+    const rect = el.getBoundingClientRect();
+    return rect.width > 0 && rect.height > 0;
+  };
+  const find = () => {
+    const el = document.querySelector(SELECTOR);
+    return el && (!VISIBLE || isVisible(el)) ? el : null;
+  };
+
+  const el = await new Promise((resolve, reject) => {
+    let observer = null;
+    let poll = null;
+    let timer = null;
+    const done = () => {
+      if (observer) observer.disconnect();
+      clearInterval(poll);
+      clearTimeout(timer);
+    };
+    const check = () => {
+      const found = find();
+      if (!found) return;
+      done();
+      resolve(found);
+    };
+    timer = setTimeout(() => {
+      done();
+      reject(new Error(`Timed out after ${TIMEOUT_MS}ms waiting for selector ${SELECTOR}`));
+    }, TIMEOUT_MS);
+    observer = new MutationObserver(check);
+    observer.observe(document.documentElement, { childList: true, subtree: true, attributes: true });
+    poll = setInterval(check, 100);
+    check();
+  });
+
+  const rect = el.getBoundingClientRect();
+  return { x: rect.x, y: rect.y, width: rect.width, height: rect.height };
+"#;
+
+/// `eval_with_result` body that resolves once no request tracked by
+/// `NETWORK_ACTIVITY_SCRIPT` has been in flight for `__IDLE_MS__`, to the
+/// milliseconds waited, or rejects after `__TIMEOUT_MS__`.
+const WAIT_FOR_NETWORK_IDLE_SCRIPT: &str = r#"
+  const IDLE_MS = __IDLE_MS__;
+  const TIMEOUT_MS = __TIMEOUT_MS__;
+  const tracker = window.__CLAW_NETWORK__;
+  if (!tracker) throw new Error('Network tracking is not installed in this page');
+
+  const started = performance.now();
+  return await new Promise((resolve, reject) => {
+    let timer = null;
+    let unsubscribe = null;
+    const done = () => {
+      clearTimeout(timer);
+      clearTimeout(deadline);
+      if (unsubscribe) unsubscribe();
+    };
+    const deadline = setTimeout(() => {
+      done();
+      reject(new Error(`Timed out after ${TIMEOUT_MS}ms waiting for network idle (${tracker.inFlight()} requests in flight)`));
+    }, TIMEOUT_MS);
+    const check = () => {
+      clearTimeout(timer);
+      if (tracker.inFlight() > 0) return;
+      const remaining = IDLE_MS - tracker.idleFor();
+      if (remaining > 0) {
+        timer = setTimeout(check, remaining);
+        return;
+      }
+      done();
+      resolve(Math.round(performance.now() - started));
+    };
+    unsubscribe = tracker.subscribe(check);
+    check();
+  });
+"#;
+
+/// `eval_with_result` body that resolves once the document has been parsed
+/// (`DOMContentLoaded`), or rejects after `__TIMEOUT_MS__`.
+const WAIT_FOR_DOM_CONTENT_LOADED_SCRIPT: &str = r#"
+  const TIMEOUT_MS = __TIMEOUT_MS__;
+  if (document.readyState === 'loading') {
+    await new Promise((resolve, reject) => {
+      const timer = setTimeout(() => {
+        reject(new Error(`Timed out after ${TIMEOUT_MS}ms waiting for DOMContentLoaded`));
+      }, TIMEOUT_MS);
+      document.addEventListener('DOMContentLoaded', () => {
+        clearTimeout(timer);
+        resolve();
+      }, { once: true });
+    });
+  }
+  return null;
+"#;
+
+/// `eval_with_result` body for arbitrary agent code. Indirect eval runs the code
+/// as a global script, so both expressions (`document.title`) and statement
+/// lists yield their completion value; promises are awaited by the wrapper.
+const RUN_JS_BODY: &str = r#"
+  const indirectEval = eval;
+  try {
+    indirectEval('void 0');
+  } catch {
+    throw new Error(__BLOCKED__);
+  }
+  return indirectEval(__CODE__);
+"#;
+
+/// Readability-style extraction: scores block containers by the paragraph
+/// text they hold (commas, length, class/id hints, link density), takes the
+/// best one, and returns its text with scripts, styles and page chrome
+/// stripped. Falls back to `document.body.innerText` when no container
+/// stands out. `textContent` is cut to `__MAX_CHARS__`; `length` and
+/// `wordCount` describe the full text. Shared by reader mode and reading stats
+/// so both agree on what the main content is.
+const READABLE_SCRIPT: &str = r#"
+  const MAX_CHARS = __MAX_CHARS__;
+  const MIN_ARTICLE_CHARS = 250;
+  const STRIP_SELECTOR = 'script, style, noscript, template, link, meta, nav, aside, footer, form, iframe, svg, canvas, button, input, select, textarea, [aria-hidden="true"], [hidden], [role="navigation"], [role="complementary"]';
+  const POSITIVE = /article|body|content|entry|main|page|post|text|blog|story/i;
+  const NEGATIVE = /comment|meta|footer|footnote|sidebar|sponsor|ad-|advert|share|social|related|promo|nav|menu|banner|cookie|popup|subscribe/i;
+  const BLOCKS = 'p, h1, h2, h3, h4, h5, h6, li, pre, blockquote, div, section, article, tr, br';
+
+  const meta = (...selectors) => {
+    for (const selector of selectors) {
+      const el = document.querySelector(selector);
+      const value = el && (el.getAttribute('content') || el.textContent || '').trim();
+      if (value) return value;
+    }
+    return null;
+  };
+  const collapse = (text) => text.replace(/[ \t\u00a0]+/g, ' ').replace(/ *\n */g, '\n').replace(/\n{3,}/g, '\n\n').trim();
+  const textOf = (el) => (el.textContent || '').trim();
+
+  const classWeight = (el) => {
+    const hint = `${el.className && typeof el.className === 'string' ? el.className : ''} ${el.id || ''}`;
+    let weight = 0;
+    if (POSITIVE.test(hint)) weight += 25;
+    if (NEGATIVE.test(hint)) weight -= 25;
+    return weight;
+  };
+  const linkDensity = (el) => {
+    const total = textOf(el).length;
+    if (!total) return 0;
+    let linked = 0;
+    el.querySelectorAll('a').forEach((a) => { linked += textOf(a).length; });
+    return linked / total;
+  };
+
+  const pickArticle = () => {
+    const scores = new Map();
+    const addScore = (el, score) => {
+      if (!el || el === document.documentElement) return;
+      if (!scores.has(el)) scores.set(el, classWeight(el));
+      scores.set(el, scores.get(el) + score);
+    };
+    document.querySelectorAll('p, pre, td').forEach((node) => {
+      const text = textOf(node);
+      if (text.length < 25) return;
+      const score = 1 + text.split(',').length + Math.min(Math.floor(text.length / 100), 3);
+      addScore(node.parentElement, score);
+      if (node.parentElement) addScore(node.parentElement.parentElement, score / 2);
+    });
+    let best = null;
+    let bestScore = 0;
+    for (const [el, score] of scores) {
+      const adjusted = score * (1 - linkDensity(el));
+      if (adjusted > bestScore) {
+        best = el;
+        bestScore = adjusted;
+      }
+    }
+    return best;
+  };
+
+  const readableText = (root) => {
+    const clone = root.cloneNode(true);
+    clone.querySelectorAll(STRIP_SELECTOR).forEach((el) => el.remove());
+    clone.querySelectorAll(BLOCKS).forEach((el) => el.after(document.createTextNode('\n')));
+    return collapse(clone.textContent || '');
+  };
+
+  const title = meta('meta[property="og:title"]', 'meta[name="twitter:title"]') || document.title || '';
+  const byline = meta(
+    'meta[name="author"]',
+    'meta[property="article:author"]',
+    '[itemprop="author"] [itemprop="name"]',
+    '[itemprop="author"]',
+    '[rel="author"]',
+    '.byline',
+    '.author'
+  );
+
+  let text = '';
+  const article = document.body ? pickArticle() : null;
+  if (article) text = readableText(article);
+  if (text.length < MIN_ARTICLE_CHARS && document.body) {
+    text = collapse(document.body.innerText || '');
+  }
+
+  const firstParagraph = text.split('\n').find((line) => line.length > 80) || text;
+  const excerpt = meta('meta[name="description"]', 'meta[property="og:description"]')
+    || firstParagraph.slice(0, 300);
+
+  return {
+    title,
+    byline: byline && byline.length <= 200 ? byline : null,
+    textContent: text.slice(0, MAX_CHARS),
+    excerpt,
+    length: text.length,
+    wordCount: text.split(/\s+/).filter(Boolean).length,
+    truncated: text.length > MAX_CHARS,
+  };
+"#;
+
+/// Swaps the page body for a typographic rendering of `__CONTENT__` (a
+/// `ReadableContent`) and back. The original body is kept detached so it is
+/// restored intact, and the reading position is carried over as a fraction
+/// of the page height.
+const READER_MODE_SCRIPT: &str = r#"
+  const enable = __ENABLE__;
+  const saved = window.__CLAW_READER__;
+  const scrollFraction = () => {
+    const max = document.documentElement.scrollHeight - window.innerHeight;
+    return max > 0 ? window.scrollY / max : 0;
+  };
+  const scrollToFraction = (fraction) => {
+    const max = document.documentElement.scrollHeight - window.innerHeight;
+    window.scrollTo({ left: 0, top: Math.max(0, max) * fraction, behavior: 'instant' });
+  };
+
+  if (!enable) {
+    if (!saved) return { readerMode: false };
+    const fraction = scrollFraction();
+    document.body.replaceWith(saved.body);
+    saved.style.remove();
+    window.__CLAW_READER__ = null;
+    scrollToFraction(fraction);
+    return { readerMode: false };
+  }
+  if (saved) return { readerMode: true };
+  if (!document.body) return { readerMode: false };
+
+  const content = __CONTENT__;
+  const fraction = scrollFraction();
+  const style = document.createElement('style');
+  style.textContent = `
+    body.claw-reader { margin: 0; background: #fbfaf7; color: #222; }
+    .claw-reader article { max-width: 40em; margin: 0 auto; padding: 48px 24px 96px;
+      font: 19px/1.65 Georgia, 'Iowan Old Style', serif; }
+    .claw-reader h1 { font-size: 1.8em; line-height: 1.25; margin: 0 0 0.4em; }
+    .claw-reader .claw-reader-byline { color: #666; font-style: italic; margin: 0 0 2em; }
+    .claw-reader p { margin: 0 0 1em; }
+    @media (prefers-color-scheme: dark) {
+      body.claw-reader { background: #1c1c1e; color: #ddd; }
+      .claw-reader .claw-reader-byline { color: #999; }
+    }`;
+  const body = document.createElement('body');
+  body.className = 'claw-reader';
+  const article = document.createElement('article');
+  const heading = document.createElement('h1');
+  heading.textContent = content.title || document.title;
+  article.appendChild(heading);
+  if (content.byline) {
+    const byline = document.createElement('p');
+    byline.className = 'claw-reader-byline';
+    byline.textContent = content.byline;
+    article.appendChild(byline);
+  }
+  for (const line of content.textContent.split('\n')) {
+    if (!line.trim()) continue;
+    const paragraph = document.createElement('p');
+    paragraph.textContent = line;
+    article.appendChild(paragraph);
+  }
+  body.appendChild(article);
+
+  const original = document.body;
+  (document.head || document.documentElement).appendChild(style);
+  original.replaceWith(body);
+  window.__CLAW_READER__ = { body: original, style };
+  scrollToFraction(fraction);
+  return { readerMode: true };
+"#;
+
+/// Reports the JS heap (`performance.memory`, Chromium-based engines only)
+/// and how many elements the document holds.
+const RESOURCE_USAGE_SCRIPT: &str = r#"
+  const memory = performance.memory;
+  return {
+    jsHeapUsedBytes: memory ? memory.usedJSHeapSize : null,
+    jsHeapTotalBytes: memory ? memory.totalJSHeapSize : null,
+    jsHeapLimitBytes: memory ? memory.jsHeapSizeLimit : null,
+    domNodes: document.getElementsByTagName('*').length,
+  };
+"#;
+
+/// Highlights every match of `QUERY` (or clears highlights when it is `null`)
+/// and marks the match at `INDEX` as current. Returns `{ total }`.
+const FIND_SCRIPT: &str = r#"
+  const QUERY = __QUERY__;
+  const INDEX = __INDEX__;
+  const MATCH_CASE = __MATCH_CASE__;
+  const MAX_MATCHES = 1000;
+  const SKIP_TAGS = new Set(['SCRIPT', 'STYLE', 'NOSCRIPT', 'TEXTAREA', 'INPUT', 'SELECT']);
+  const BASE_STYLE = 'background: #ffe066; color: inherit; padding: 0;';
+  const ACTIVE_STYLE = 'background: #ff9632; color: inherit; padding: 0;';
+
+  const clear = () => {
+    document.querySelectorAll('mark[data-claw-find]').forEach((mark) => {
+      const parent = mark.parentNode;
+      if (!parent) return;
+      parent.replaceChild(document.createTextNode(mark.textContent || ''), mark);
+      parent.normalize();
+    });
+    window.__CLAW_FIND__ = null;
+  };
+
+  const fold = (text) => (MATCH_CASE ? text : text.toLowerCase());
+
+  const highlight = (query) => {
+    const needle = fold(query);
+    const walker = document.createTreeWalker(document.body, NodeFilter.SHOW_TEXT, {
+      acceptNode: (node) => {
+        const parent = node.parentElement;
+        if (!parent || SKIP_TAGS.has(parent.tagName)) return NodeFilter.FILTER_REJECT;
+        return fold(node.nodeValue || '').includes(needle)
+          ? NodeFilter.FILTER_ACCEPT
+          : NodeFilter.FILTER_REJECT;
+      },
+    });
+    const nodes = [];
+    while (walker.nextNode()) nodes.push(walker.currentNode);
+
+    const marks = [];
+    for (const node of nodes) {
+      let rest = node;
+      let offset = fold(rest.nodeValue).indexOf(needle);
+      while (offset !== -1 && marks.length < MAX_MATCHES) {
+        const match = rest.splitText(offset);
+        rest = match.splitText(query.length);
+        const mark = document.createElement('mark');
+        mark.setAttribute('data-claw-find', '');
+        match.parentNode.replaceChild(mark, match);
+        mark.appendChild(match);
+        marks.push(mark);
+        offset = fold(rest.nodeValue).indexOf(needle);
+      }
+      if (marks.length >= MAX_MATCHES) break;
+    }
+    return marks;
+  };
+
+  if (QUERY === null || !document.body) {
+    clear();
+    return { total: 0 };
+  }
+
+  let state = window.__CLAW_FIND__;
+  const stale = !state
+    || state.query !== QUERY
+    || state.matchCase !== MATCH_CASE
+    || state.marks.some((mark) => !mark.isConnected);
+  if (stale) {
+    clear();
+    state = { query: QUERY, matchCase: MATCH_CASE, marks: QUERY ? highlight(QUERY) : [] };
+    window.__CLAW_FIND__ = state;
+  }
+
+  const total = state.marks.length;
+  if (total === 0) return { total: 0 };
+  const index = ((INDEX % total) + total) % total;
+  state.marks.forEach((mark, i) => {
+    mark.style.cssText = i === index ? ACTIVE_STYLE : BASE_STYLE;
+  });
+  state.marks[index].scrollIntoView({ block: 'center', inline: 'nearest' });
+  return { total };
+"#;
+
+fn env_flag_enabled(name: &str) -> bool {
+    match std::env::var(name) {
+        Ok(value) => {
+            let normalized = value.trim().to_lowercase();
+            normalized == "1" || normalized == "true" || normalized == "yes"
+        }
+        Err(_) => false,
+    }
+}
+
+/// Categories of page activity the debug script can capture.
+const DEBUG_CATEGORIES: &[&str] = &["console", "errors", "render", "network"];
+
+/// Which debug capture categories are on. `CLAW_DEBUG_CAPTURE` takes either
+/// `1`/`true`/`yes` for all of them or a comma-separated list such as
+/// `console,errors`. Otherwise debug builds capture everything and release
+/// builds nothing.
+fn debug_capture_categories() -> Vec<&'static str> {
+    let raw = std::env::var("CLAW_DEBUG_CAPTURE").unwrap_or_default();
+    let normalized = raw.trim().to_lowercase();
+    if matches!(normalized.as_str(), "1" | "true" | "yes") {
+        return DEBUG_CATEGORIES.to_vec();
+    }
+    let selected: Vec<&'static str> = DEBUG_CATEGORIES
+        .iter()
+        .copied()
+        .filter(|category| normalized.split(',').any(|item| item.trim() == *category))
+        .collect();
+    if !selected.is_empty() {
+        return selected;
+    }
+    if cfg!(debug_assertions) {
+        DEBUG_CATEGORIES.to_vec()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Opt-in via `CLAW_WARM_BACKGROUND_TABS`. Off by default because some
+/// platforms let hidden webviews keep intercepting pointer events unless
+/// they are also moved off-screen.
+fn warm_background_tabs_enabled() -> bool {
+    env_flag_enabled("CLAW_WARM_BACKGROUND_TABS")
+}
+
+/// Fill in the placeholders every page script shares: `__TAB_ID__` as a JS
+/// string literal and `__EMIT_PRELUDE__` as `EMIT_PRELUDE`. Script-specific
+/// placeholders are replaced by the caller afterwards.
+fn tab_script(template: &str, tab_id: &str) -> String {
+    let tab_id_literal = serde_json::to_string(tab_id).unwrap_or_else(|_| "\"unknown\"".to_string());
+    template
+        .replace("__EMIT_PRELUDE__", EMIT_PRELUDE)
+        .replace("__TAB_ID__", &tab_id_literal)
+}
+
+fn debug_init_script(tab_id: &str) -> Option<String> {
+    let categories = debug_capture_categories();
+    if categories.is_empty() {
+        return None;
+    }
+    let categories_literal = serde_json::to_string(&categories).unwrap_or_else(|_| "[]".to_string());
+    Some(
+        tab_script(DEBUG_INIT_SCRIPT, tab_id)
+            .replace("__CATEGORIES__", &categories_literal)
+            .replace("__MAX_EVENTS_PER_SEC__", &debug_max_events_per_sec().to_string()),
+    )
+}
+
+/// Per-tab debug event rate, from `CLAW_DEBUG_MAX_EVENTS_PER_SEC` when set.
+fn debug_max_events_per_sec() -> u32 {
+    std::env::var("CLAW_DEBUG_MAX_EVENTS_PER_SEC")
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u32>().ok())
+        .filter(|rate| *rate > 0)
+        .unwrap_or(DEFAULT_DEBUG_MAX_EVENTS_PER_SEC)
+}
+
+fn link_intercept_script(tab_id: &str, config: &LinkInterceptConfig) -> String {
+    let config_literal = serde_json::to_string(config).unwrap_or_else(|_| "{}".to_string());
+    tab_script(LINK_INTERCEPT_SCRIPT, tab_id)
+        .replace("__CONFIG__", &config_literal)
+}
+
+fn context_menu_script(tab_id: &str, enabled: bool) -> String {
+    tab_script(CONTEXT_MENU_SCRIPT, tab_id)
+        .replace("__MAX_SELECTION_CHARS__", &MAX_SELECTION_CHARS.to_string())
+        .replace("__ENABLED__", if enabled { "true" } else { "false" })
+}
+
+fn selection_script(tab_id: &str) -> String {
+    tab_script(SELECTION_SCRIPT, tab_id)
+        .replace("__MAX_SELECTION_CHARS__", &MAX_SELECTION_CHARS.to_string())
+}
+
+fn load_progress_script(tab_id: &str) -> String {
+    tab_script(LOAD_PROGRESS_SCRIPT, tab_id)
+}
+
+fn favicon_script(tab_id: &str) -> String {
+    tab_script(FAVICON_SCRIPT, tab_id)
+}
+
+fn title_script(tab_id: &str) -> String {
+    tab_script(TITLE_SCRIPT, tab_id)
+}
+
+fn audio_script(tab_id: &str) -> String {
+    tab_script(AUDIO_SCRIPT, tab_id)
+}
+
+fn set_muted_script(muted: bool) -> String {
+    format!("window.__CLAW_AUDIO__ && window.__CLAW_AUDIO__.setMuted({});", muted)
+}
+
+fn user_style_script(style: &UserStyle) -> String {
+    let id_literal = serde_json::to_string(&style.id).unwrap_or_else(|_| "\"\"".to_string());
+    let css_literal = serde_json::to_string(&style.css).unwrap_or_else(|_| "\"\"".to_string());
+    USER_STYLE_SCRIPT
+        .replace("__ID__", &id_literal)
+        .replace("__CSS__", &css_literal)
+}
+
+fn remove_user_style_script(id: &str) -> String {
+    let id_literal = serde_json::to_string(id).unwrap_or_else(|_| "\"\"".to_string());
+    format!("document.getElementById('claw-user-style-' + {})?.remove();", id_literal)
+}
+
+/// A user script guarded so that a throw doesn't surface as a page error.
+fn user_script_source(script: &UserScript) -> String {
+    format!(
+        "try {{\n{}\n}} catch (e) {{ console.error('[ClawBrowser] user script {} failed:', e); }}",
+        script.js, script.id
+    )
+}
+
+pub fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The app page new and blank tabs load: the `new_tab_page` setting, or
+/// `BLANK_PAGE_PATH` when that is unset or isn't a plain relative path.
+fn new_tab_page() -> &'static str {
+    static PAGE: OnceLock<String> = OnceLock::new();
+    PAGE.get_or_init(|| {
+        let configured = settings::load().new_tab_page;
+        let page = configured.trim().trim_start_matches('/');
+        if page.is_empty() {
+            return BLANK_PAGE_PATH.to_string();
+        }
+        let valid = !page.contains([':', '\\', '?', '#'])
+            && !page.split('/').any(|segment| segment.is_empty() || segment == "..");
+        if !valid {
+            log::warn!("[Tabs] Ignoring invalid newTabPage {:?}; using {}", configured, BLANK_PAGE_PATH);
+            return BLANK_PAGE_PATH.to_string();
+        }
+        page.to_string()
+    })
+}
+
+/// Whether `url` is served by the app itself rather than a website: the
+/// `tauri://localhost` protocol, `tauri.localhost` on Windows, or the dev
+/// server in development.
+fn is_app_url(url: &url::Url) -> bool {
+    if url.scheme() == "tauri" {
+        return true;
+    }
+    match url.host_str() {
+        Some("tauri.localhost") => true,
+        Some("localhost") | Some("127.0.0.1") => tauri::is_dev(),
+        _ => false,
+    }
+}
+
+/// Reports the new-tab page (configured or `BLANK_PAGE_PATH`) as
+/// `about:blank`; websites with a page of the same name are left alone.
+fn normalize_tab_url(url: &url::Url) -> String {
+    let path = url.path().trim_start_matches('/');
+    if is_app_url(url) && (path == new_tab_page() || path == BLANK_PAGE_PATH) {
+        "about:blank".to_string()
+    } else {
+        url.to_string()
+    }
+}
+
+/// JS reports bounds relative to the webview viewport. On macOS the
+/// window `inner_size()` includes the title bar region but the viewport
+/// only covers below the title bar. `chrome_y_offset` is the physical-pixel
+/// gap between the two coordinate systems, stored once the JS first reports.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ContentBounds {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// The parts of a window's `TabState` that decide where content webviews go.
+#[derive(Debug, Clone, Copy)]
+struct ContentLayout {
+    content_bounds: Option<ContentBounds>,
+    chrome_y_offset: f64,
+    focus_mode: bool,
+    ui_zoom: f64,
+}
+
+/// Everything a reposition needs, copied out so the webview calls can run
+/// after the `TabState` lock is released. Resizing a webview can wait on the
+/// event loop, which may itself be waiting on that lock.
+struct LayoutSnapshot {
+    window_label: String,
+    active_tab: Option<String>,
+    background_tabs: Vec<String>,
+    warm_background_tabs: bool,
+    layout: ContentLayout,
+}
+
+/// Compute physical bounds for child webviews.
+///
+/// Uses the stored JS layout (`left`, `top` from viewport) combined with the
+/// current `inner_size` so that width/height stay correct even when the Rust
+/// resize handler fires before the JS handler updates the stored bounds.
+fn content_bounds(window: &Window, layout: &ContentLayout) -> Result<(PhysicalPosition<i32>, PhysicalSize<u32>), String> {
+    let inner_size = window.inner_size().map_err(|e| e.to_string())?;
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    let y_off = layout.chrome_y_offset;
+
+    // Offsets for left edge and top edge (from viewport origin) in CSS pixels
+    // of the chrome. Focus mode hides the chrome, so content starts at the
+    // viewport origin.
+    let (left_css, top_css) = if layout.focus_mode {
+        (0.0, 0.0)
+    } else if let Some(b) = &layout.content_bounds {
+        (b.left, b.top)
+    } else {
+        (AGENT_PANEL_WIDTH + TAB_LIST_WIDTH, NAV_BAR_HEIGHT)
+    };
+
+    // The chrome's CSS pixels are scaled by the UI zoom.
+    let left_logical = left_css * layout.ui_zoom;
+    let top_viewport = top_css * layout.ui_zoom;
+
+    // Convert to physical pixels, adding the title-bar offset to y.
+    let left_px  = (left_logical * scale).round().max(0.0) as i32;
+    let top_px   = ((top_viewport + y_off) * scale).round().max(0.0) as i32;
+
+    // Width and height are derived from the *current* inner_size so they stay
+    // correct even when stored JS bounds are stale during a resize.
+    let width_px  = inner_size.width.saturating_sub(left_px.max(0) as u32);
+    let height_px = inner_size.height.saturating_sub(top_px.max(0) as u32);
+
+    Ok((
+        PhysicalPosition::new(left_px, top_px),
+        PhysicalSize::new(width_px, height_px),
+    ))
+}
+
+/// Far off-screen, but at the content area's size so a page that keeps
+/// loading while parked lays out (media queries, `innerWidth`) as it will
+/// when shown. Zero-sized when the window can't be measured.
+fn offscreen_rect(window: Option<&Window>, layout: &ContentLayout) -> tauri::Rect {
+    let size = window
+        .and_then(|window| content_bounds(window, layout).ok())
+        .map(|(_, size)| size)
+        .unwrap_or(PhysicalSize::new(0, 0));
+    tauri::Rect {
+        position: PhysicalPosition::new(OFFSCREEN_OFFSET, OFFSCREEN_OFFSET).into(),
+        size: size.into(),
+    }
+}
+
+/// Take a background webview out of view. By default it is parked far
+/// off-screen at the content size; with warm background tabs it keeps its
+/// real content bounds and is only hidden, so switching back needs no
+/// relayout.
+fn park_webview(window: Option<&Window>, webview: &Webview, state: &TabState) {
+    // Hide before moving: a warm tab is resized in place, and doing that
+    // while it is still visible briefly stacks it over the active tab.
+    let _ = webview.hide();
+    match window {
+        Some(window) if state.warm_background_tabs => {
+            let _ = apply_bounds(window, webview, state);
+        }
+        _ => {
+            let _ = webview.set_bounds(offscreen_rect(window, &state.content_layout()));
+        }
+    }
+}
+
+fn apply_bounds(window: &Window, webview: &Webview, state: &TabState) -> Result<(), String> {
+    let _ = webview.set_auto_resize(false);
+    let (position, size) = content_bounds(window, &state.content_layout())?;
+    let bounds = tauri::Rect {
+        position: position.into(),
+        size: size.into(),
+    };
+    webview
+        .set_bounds(bounds)
+        .map_err(|e| format!("Failed to set webview bounds: {}", e))?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabInfo {
+    pub id: String,
+    /// URL of the current document after redirects, as reported by page loads.
+    pub url: String,
+    /// URL last requested through `create_tab` or `navigate_tab`.
+    pub requested_url: String,
+    pub title: String,
+    pub favicon: Option<String>,
+    /// Page zoom factor, reapplied whenever the tab is shown.
+    pub zoom: f64,
+    /// Uses its own non-persistent data store; never saved with the session.
+    pub incognito: bool,
+    /// Media elements are muted; reapplied on every document the tab loads.
+    pub muted: bool,
+    /// The webview has been torn down to save memory; `url` and `scroll`
+    /// are used to re-create it when the tab is woken.
+    pub hibernated: bool,
+    /// Unix time in milliseconds when the tab was last switched to or away from.
+    pub last_active_ms: u64,
+    /// Scroll offset recorded at hibernation, restored once the tab reloads.
+    pub scroll: Option<ScrollPosition>,
+    /// Styles and scripts added to this tab only.
+    pub user_content: UserContent,
+    /// The page is shown as a reader-mode article; reapplied to each
+    /// document the tab loads.
+    pub reader_mode: bool,
+    /// Overrides the per-OS user agent for this tab, including when its
+    /// webview is re-created.
+    pub user_agent: Option<String>,
+    /// Kept ahead of unpinned tabs in the tab strip and spared by bulk closes
+    /// and Cmd/Ctrl+W.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// When a user script runs in each document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScriptRunAt {
+    /// Before any page script, as a native user script.
+    DocumentStart,
+    /// Once the document has finished loading.
+    #[default]
+    DocumentEnd,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserStyle {
+    pub id: String,
+    pub css: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserScript {
+    pub id: String,
+    pub js: String,
+    pub run_at: ScriptRunAt,
+}
+
+/// User styles and scripts, reapplied to every document they cover.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserContent {
+    pub styles: Vec<UserStyle>,
+    pub scripts: Vec<UserScript>,
+}
+
+/// A tab's scroll offset and the furthest it can scroll, in CSS pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TabScroll {
+    pub x: f64,
+    pub y: f64,
+    pub max_x: f64,
+    pub max_y: f64,
+}
+
+/// A document scroll offset in CSS pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScrollPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+pub struct TabState {
+    /// Label of the top-level window these tabs live in.
+    pub window_label: String,
+    /// Open tabs in tab-strip order. `list_tabs` returns them in this order.
+    pub tabs: IndexMap<String, TabInfo>,
+    pub active_tab: Option<String>,
+    pub content_bounds: Option<ContentBounds>,
+    /// Title-bar height in logical pixels. On macOS, `inner_size()` includes
+    /// the title bar but the JS viewport does not. Computed on first
+    /// `set_content_bounds` call and reused for all subsequent positioning.
+    pub chrome_y_offset: f64,
+    /// Active find-in-page session per tab. Kept here rather than in the page
+    /// so next/prev keep cycling correctly across tab switches.
+    pub find: HashMap<String, FindState>,
+    /// When set, the chrome is collapsed and content fills the whole window.
+    pub focus_mode: bool,
+    /// Keep background tabs at their real bounds (hidden) instead of
+    /// off-screen. See `warm_background_tabs_enabled`.
+    pub warm_background_tabs: bool,
+    /// Zoom factor of the chrome UI. JS-reported bounds are in chrome CSS
+    /// pixels, so they are multiplied by this before positioning webviews.
+    pub ui_zoom: f64,
+    /// Tabs whose current document has started but not finished loading.
+    pub loading: HashSet<String>,
+    /// Tabs that have finished loading at least one document.
+    pub loaded: HashSet<String>,
+    /// New-tab click gestures, baked into each tab's link-intercept script.
+    pub link_intercept: LinkInterceptConfig,
+    /// Right-clicks emit `tab-context-menu` instead of showing the native menu.
+    pub context_menu_intercept: bool,
+    /// Lowercased host patterns (`*` wildcards) that content tabs may not
+    /// navigate to. Shared by every window and with each tab's
+    /// `on_navigation` hook, which must not take the `TabState` lock.
+    pub navigation_blocklist: Arc<RwLock<Vec<String>>>,
+    /// Extra request headers per tab, sent with `navigate_tab` loads.
+    pub headers: HashMap<String, HashMap<String, String>>,
+    /// Recently closed tabs, most recent last. Incognito tabs are not recorded.
+    pub closed_tabs: VecDeque<ClosedTab>,
+    /// Styles and scripts applied to every tab. Shared by every window, like
+    /// `navigation_blocklist`.
+    pub global_user_content: Arc<RwLock<UserContent>>,
+    /// Remembered permission decisions keyed by (origin, permission). Matching
+    /// prompts from any tab in the window are answered without asking.
+    pub permission_policy: HashMap<(String, String), bool>,
+    /// Tabs whose close is waiting on `confirm_close_tab`.
+    pub close_pending: HashSet<String>,
+    /// Coalesces bursts of `set_content_bounds` calls.
+    bounds_throttle: BoundsThrottle,
+}
+
+/// `generation` identifies the latest `set_content_bounds` call;
+/// `applied_at` is when bounds were last pushed to the webviews.
+#[derive(Default)]
+struct BoundsThrottle {
+    generation: u64,
+    applied_at: Option<Instant>,
+}
+
+/// What `reopen_closed_tab` needs to bring a closed tab back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedTab {
+    pub url: String,
+    pub title: String,
+    /// Reopened as a pinned tab.
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+impl TabState {
+    pub fn new(
+        window_label: &str,
+        navigation_blocklist: Arc<RwLock<Vec<String>>>,
+        global_user_content: Arc<RwLock<UserContent>>,
+    ) -> Self {
+        let settings = settings::load();
+        Self {
+            window_label: window_label.to_string(),
+            tabs: IndexMap::new(),
+            active_tab: None,
+            content_bounds: None,
+            chrome_y_offset: 0.0,
+            find: HashMap::new(),
+            focus_mode: false,
+            warm_background_tabs: warm_background_tabs_enabled(),
+            ui_zoom: settings.ui_zoom.clamp(MIN_UI_ZOOM, MAX_UI_ZOOM),
+            loading: HashSet::new(),
+            loaded: HashSet::new(),
+            link_intercept: settings.link_intercept,
+            context_menu_intercept: settings.context_menu_intercept,
+            navigation_blocklist,
+            headers: HashMap::new(),
+            closed_tabs: VecDeque::new(),
+            global_user_content,
+            permission_policy: HashMap::new(),
+            close_pending: HashSet::new(),
+            bounds_throttle: BoundsThrottle::default(),
+        }
+    }
+
+    fn content_layout(&self) -> ContentLayout {
+        ContentLayout {
+            content_bounds: self.content_bounds,
+            chrome_y_offset: self.chrome_y_offset,
+            focus_mode: self.focus_mode,
+            ui_zoom: self.ui_zoom,
+        }
+    }
+
+    fn layout_snapshot(&self) -> LayoutSnapshot {
+        LayoutSnapshot {
+            window_label: self.window_label.clone(),
+            active_tab: self.active_tab.clone(),
+            background_tabs: self
+                .tabs
+                .keys()
+                .filter(|id| Some(*id) != self.active_tab.as_ref())
+                .cloned()
+                .collect(),
+            warm_background_tabs: self.warm_background_tabs,
+            layout: self.content_layout(),
+        }
+    }
+}
+
+/// A tab's native webview next to what `TabState` expects of it, from
+/// `debug_list_webviews`.
+#[cfg(any(debug_assertions, feature = "devtools"))]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebviewDebugInfo {
+    pub tab_id: String,
+    pub label: String,
+    pub active: bool,
+    pub hibernated: bool,
+    /// Whether a native webview exists for the tab.
+    pub exists: bool,
+    /// Physical position and size as the platform reports them.
+    pub position: Option<PhysicalPosition<i32>>,
+    pub size: Option<PhysicalSize<u32>>,
+    /// Whether the platform view is shown; `None` where it can't be asked.
+    pub visible: Option<bool>,
+    /// Where `TabState` puts the webview: the content area for the active
+    /// tab and warm background tabs, off-screen for other background tabs.
+    pub expected_position: Option<PhysicalPosition<i32>>,
+    pub expected_size: Option<PhysicalSize<u32>>,
+    pub expected_visible: bool,
+    /// The native state differs from what `TabState` expects.
+    pub mismatch: bool,
+}
+
+/// Tab state of every top-level browser window, keyed by window label.
+/// Each window's state has its own lock so windows don't contend.
+pub struct WindowTabs {
+    windows: HashMap<String, Arc<Mutex<TabState>>>,
+    navigation_blocklist: Arc<RwLock<Vec<String>>>,
+    global_user_content: Arc<RwLock<UserContent>>,
+    next_window: u32,
+}
+
+impl WindowTabs {
+    pub fn new() -> Self {
+        let navigation_blocklist = Arc::new(RwLock::new(Vec::new()));
+        let global_user_content = Arc::new(RwLock::new(UserContent::default()));
+        let main = TabState::new(
+            MAIN_WINDOW,
+            Arc::clone(&navigation_blocklist),
+            Arc::clone(&global_user_content),
+        );
+        Self {
+            windows: HashMap::from([(MAIN_WINDOW.to_string(), Arc::new(Mutex::new(main)))]),
+            navigation_blocklist,
+            global_user_content,
+            next_window: 2,
+        }
+    }
+}
+
+/// The tab state of window `label`.
+pub fn window_state(app: &tauri::AppHandle, label: &str) -> Result<Arc<Mutex<TabState>>, String> {
+    let windows = app.state::<Mutex<WindowTabs>>();
+    let windows = windows.lock().map_err(|e| e.to_string())?;
+    windows
+        .windows
+        .get(label)
+        .cloned()
+        .ok_or_else(|| format!("Window {} not found", label))
+}
+
+/// The tab state of the window hosting `tab_id`.
+pub fn tab_window_state(app: &tauri::AppHandle, tab_id: &str) -> Result<Arc<Mutex<TabState>>, String> {
+    let webview = tab_webview(app, tab_id)?;
+    window_state(app, webview.window().label())
+}
+
+/// Tab states of all open browser windows.
+fn all_window_states(app: &tauri::AppHandle) -> Result<Vec<Arc<Mutex<TabState>>>, String> {
+    let windows = app.state::<Mutex<WindowTabs>>();
+    let windows = windows.lock().map_err(|e| e.to_string())?;
+    Ok(windows.windows.values().cloned().collect())
+}
+
+/// The focused browser window's label, falling back to the main window.
+/// Menu shortcuts and the screenshot heartbeat act on this window.
+pub fn focused_window_label(app: &tauri::AppHandle) -> String {
+    let windows = app.state::<Mutex<WindowTabs>>();
+    let Ok(windows) = windows.lock() else {
+        return MAIN_WINDOW.to_string();
+    };
+    app.windows()
+        .into_iter()
+        .find(|(label, window)| {
+            windows.windows.contains_key(label) && window.is_focused().unwrap_or(false)
+        })
+        .map(|(label, _)| label)
+        .unwrap_or_else(|| MAIN_WINDOW.to_string())
+}
+
+/// Open another top-level browser window with its own, initially empty, set
+/// of tabs. It is built from the main window's config so it loads the same
+/// chrome. Returns the new window's label.
+pub fn new_window(app: &tauri::AppHandle) -> Result<String, String> {
+    let mut config = app
+        .config()
+        .app
+        .windows
+        .first()
+        .cloned()
+        .ok_or("No window config found")?;
+    let (label, state) = {
+        let windows = app.state::<Mutex<WindowTabs>>();
+        let mut windows = windows.lock().map_err(|e| e.to_string())?;
+        let label = format!("window-{}", windows.next_window);
+        windows.next_window += 1;
+        let state = TabState::new(
+            &label,
+            Arc::clone(&windows.navigation_blocklist),
+            Arc::clone(&windows.global_user_content),
+        );
+        let state = Arc::new(Mutex::new(state));
+        windows.windows.insert(label.clone(), Arc::clone(&state));
+        (label, state)
+    };
+
+    config.label = label.clone();
+    let built = tauri::WebviewWindowBuilder::from_config(app, &config).and_then(|builder| builder.build());
+    let window = match built {
+        Ok(window) => window,
+        Err(e) => {
+            if let Ok(mut windows) = app.state::<Mutex<WindowTabs>>().lock() {
+                windows.windows.remove(&label);
+            }
+            return Err(format!("Failed to create window: {}", e));
+        }
+    };
+    devtools::watch_webview_devtools(app.clone(), label.clone());
+    watch_window(app, &window.as_ref().window());
+    if let Ok(state) = state.lock() {
+        let _ = apply_ui_zoom(app, &state);
+    }
+    log::info!("[Tabs] opened window {}", label);
+    Ok(label)
+}
+
+/// Coalesces a window's resize events. `generation` identifies the latest
+/// event; `pending_since` is when the current burst started.
+#[derive(Default)]
+struct ResizeDebounce {
+    generation: u64,
+    pending_since: Option<Instant>,
+}
+
+fn reposition_window(app: &tauri::AppHandle, label: &str) {
+    let Ok(state) = window_state(app, label) else {
+        return;
+    };
+    let snapshot = match state.lock() {
+        Ok(state) => state.layout_snapshot(),
+        Err(_) => return,
+    };
+    let _ = reposition_snapshot(app, &snapshot, true);
+}
+
+/// Debounced resize handling: reposition once the events go quiet for
+/// `RESIZE_DEBOUNCE_MS`, and at most `RESIZE_MAX_WAIT_MS` apart while they
+/// keep coming. The trailing reposition always runs, so the final bounds
+/// match the window's final size.
+fn handle_resize(app: &tauri::AppHandle, label: &str, debounce: &Arc<Mutex<ResizeDebounce>>) {
+    let now = Instant::now();
+    let (generation, overdue) = {
+        let Ok(mut debounce) = debounce.lock() else {
+            return;
+        };
+        debounce.generation += 1;
+        let since = *debounce.pending_since.get_or_insert(now);
+        let overdue = now.duration_since(since) >= Duration::from_millis(RESIZE_MAX_WAIT_MS);
+        if overdue {
+            debounce.pending_since = None;
+        }
+        (debounce.generation, overdue)
+    };
+    if overdue {
+        reposition_window(app, label);
+        return;
+    }
+
+    let app = app.clone();
+    let label = label.to_string();
+    let debounce = Arc::clone(debounce);
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(RESIZE_DEBOUNCE_MS)).await;
+        {
+            let Ok(mut debounce) = debounce.lock() else {
+                return;
+            };
+            if debounce.generation != generation {
+                return;
+            }
+            debounce.pending_since = None;
+        }
+        reposition_window(&app, &label);
+    });
+}
+
+/// The window moved to a display with a different scale factor. Recompute
+/// `chrome_y_offset` for the new inner size, since the cached logical offset
+/// no longer matches, and ask the chrome to report fresh bounds.
+fn handle_scale_change(app: &tauri::AppHandle, label: &str, scale: f64, inner_size: PhysicalSize<u32>) {
+    let Ok(state) = window_state(app, label) else {
+        return;
+    };
+    let snapshot = {
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        if let Some(bounds) = state.content_bounds {
+            let inner_h_logical = inner_size.height as f64 / scale;
+            state.chrome_y_offset = chrome_y_offset(inner_h_logical, &bounds, state.ui_zoom);
+        }
+        state.layout_snapshot()
+    };
+    let _ = reposition_snapshot(app, &snapshot, true);
+    let _ = app.emit_to(label, "content-bounds-requested", serde_json::json!({ "scaleFactor": scale }));
+}
+
+/// Keep a browser window's active content webview fitted to it as it
+/// resizes, and drop its tab state once it is destroyed.
+pub fn watch_window(app: &tauri::AppHandle, window: &Window) {
+    let app_handle = app.clone();
+    let label = window.label().to_string();
+    let debounce = Arc::new(Mutex::new(ResizeDebounce::default()));
+    window.on_window_event(move |event| match event {
+        tauri::WindowEvent::Resized(_) => handle_resize(&app_handle, &label, &debounce),
+        tauri::WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size, .. } => {
+            handle_scale_change(&app_handle, &label, *scale_factor, *new_inner_size)
+        }
+        tauri::WindowEvent::Destroyed => {
+            // The window's content webviews are destroyed along with it.
+            if let Ok(mut windows) = app_handle.state::<Mutex<WindowTabs>>().lock() {
+                windows.windows.remove(&label);
+            }
+        }
+        _ => {}
+    });
+}
+
+/// Size of a tab's main content and how long it takes to read.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadingStats {
+    pub word_count: usize,
+    pub estimated_minutes: u32,
+}
+
+/// Approximate resource usage of a tab. Fields the platform can't report are
+/// `None`: the JS heap is only exposed by WebView2, and no platform webview
+/// exposes the content process through Tauri, so `process_rss_bytes` is
+/// currently always `None`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TabResourceUsage {
+    pub js_heap_used_bytes: Option<u64>,
+    pub js_heap_total_bytes: Option<u64>,
+    pub js_heap_limit_bytes: Option<u64>,
+    pub dom_nodes: Option<u64>,
+    pub process_rss_bytes: Option<u64>,
+}
+
+/// A page's main article as plain text, for summarization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadableContent {
+    pub title: String,
+    pub byline: Option<String>,
+    /// Article text with paragraph breaks, capped at `MAX_READABLE_CHARS`.
+    pub text_content: String,
+    pub excerpt: String,
+    /// Length of the full article text in characters, before capping.
+    pub length: usize,
+    /// Words in the full article text, before capping.
+    pub word_count: usize,
+    pub truncated: bool,
+}
+
+/// An element's bounding box in CSS pixels, relative to the viewport.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElementRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScrollMetrics {
+    x: f64,
+    y: f64,
+    viewport_height: f64,
+    scroll_height: f64,
+}
+
+/// Find-in-page progress for a tab. `active_match` is 1-based so the UI can
+/// render "3 of 17" directly; it is 0 when there are no matches.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FindState {
+    pub query: String,
+    pub match_case: bool,
+    pub active_match: usize,
+    pub total_matches: usize,
+}
+
+/// Result-returning evaluations waiting on a `claw-eval-result` event from a tab.
+pub struct EvalState {
+    pending: HashMap<String, oneshot::Sender<EvalResultPayload>>,
+}
+
+impl EvalState {
+    pub fn new() -> Self {
+        Self {
+            pending: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EvalResultPayload {
+    request_id: String,
+    ok: bool,
+    #[serde(default)]
+    value: Value,
+    /// The script returned `undefined` (`value` is then `null`).
+    #[serde(default)]
+    undefined: bool,
+    #[serde(default)]
+    error: Option<String>,
+    /// Set when the script threw, as opposed to returning an unusable value.
+    #[serde(default)]
+    exception: Option<JsError>,
+}
+
+impl EvalResultPayload {
+    fn into_result(self) -> Result<Value, String> {
+        if self.ok {
+            Ok(self.value)
+        } else {
+            Err(self.error.unwrap_or_else(|| "Script failed".to_string()))
+        }
+    }
+}
+
+/// An exception thrown by page script.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsError {
+    pub name: String,
+    pub message: String,
+    pub stack: Option<String>,
+}
+
+/// Outcome of `run_js_in_tab_json`: exactly one of `value`, `undefined` or
+/// `error` is present.
+#[derive(Debug, Serialize)]
+pub struct JsEvalOutcome {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub undefined: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsError>,
+}
+
+/// Where `create_tab` puts a new tab and whether it takes over the window.
+#[derive(Debug, Clone, Copy)]
+pub struct OpenTabOptions {
+    /// Tab-strip position; `None` or past the end appends.
+    pub index: Option<usize>,
+    /// Make the new tab the active one.
+    pub activate: bool,
+    /// Open parked behind the active tab without taking focus, as for a
+    /// middle-clicked link. Implies `activate: false`.
+    pub background: bool,
+}
+
+impl Default for OpenTabOptions {
+    fn default() -> Self {
+        Self {
+            index: None,
+            activate: true,
+            background: false,
+        }
+    }
+}
+
+impl OpenTabOptions {
+    /// Whether the new tab is shown and made active. A window with no active
+    /// tab always shows the new one, so it is never left empty.
+    fn foreground(&self, state: &TabState) -> bool {
+        (self.activate && !self.background) || state.active_tab.is_none()
+    }
+}
+
+/// Create a new content webview tab positioned below the chrome.
+pub fn create_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    url: &str,
+    options: OpenTabOptions,
+) -> Result<String, String> {
+    open_tab(app, state, url, false, options)
+}
+
+/// Create a tab with an isolated, non-persistent data store, so cookies and
+/// storage are not shared with other tabs and vanish when it closes.
+/// On Windows, InPrivate tabs share one store with each other.
+pub fn create_incognito_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    url: &str,
+) -> Result<String, String> {
+    open_tab(app, state, url, true, OpenTabOptions::default())
+}
+
+fn open_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    url: &str,
+    incognito: bool,
+    options: OpenTabOptions,
+) -> Result<String, String> {
+    let id = uuid::Uuid::new_v4().to_string();
+
+    let window = app
+        .get_window(&state.window_label)
+        .ok_or_else(|| format!("Window {} not found", state.window_label))?;
+
+    // Build first: if the webview can't be created, the current tab stays
+    // visible and active instead of every tab being parked.
+    let webview = build_tab_webview(app, state, &window, &id, url, incognito)?;
+
+    let foreground = options.foreground(state);
+    if foreground {
+        // The new webview is stacked on top, so parking the others now
+        // doesn't flash them over it.
+        for existing_id in state.tabs.keys() {
+            let existing_label = format!("tab-{}", existing_id);
+            if let Some(webview) = app.get_webview(&existing_label) {
+                park_webview(Some(&window), &webview, state);
+            }
+        }
+        let _ = webview.set_focus();
+    } else {
+        // The active tab stays in front and keeps focus.
+        park_webview(Some(&window), &webview, state);
+    }
+
+    state.tabs.insert(
+        id.clone(),
+        TabInfo {
+            id: id.clone(),
+            url: url.to_string(),
+            requested_url: url.to_string(),
+            title: String::from("New Tab"),
+            favicon: None,
+            zoom: 1.0,
+            incognito,
+            muted: false,
+            hibernated: false,
+            last_active_ms: now_ms(),
+            scroll: None,
+            user_content: UserContent::default(),
+            reader_mode: false,
+            user_agent: None,
+            pinned: false,
+        },
+    );
+    apply_site_zoom(app, state, &id, url);
+    if let Some(index) = options.index {
+        let last = state.tabs.len() - 1;
+        state.tabs.move_index(last, index.clamp(pinned_count(state), last));
+    }
+    if foreground {
+        state.active_tab = Some(id.clone());
+    }
+
+    Ok(id)
+}
+
+/// Create the content webview for tab `id` at the content bounds, with all of
+/// the tab init scripts and event hooks. Shared by new tabs and woken ones.
+fn build_tab_webview(
+    app: &tauri::AppHandle,
+    state: &TabState,
+    window: &Window,
+    id: &str,
+    url: &str,
+    incognito: bool,
+) -> Result<Webview, String> {
+    let id = id.to_string();
+    let label = format!("tab-{}", id);
+    let (position, size) = content_bounds(window, &state.content_layout())?;
+
+    let webview_url = if url == "about:blank" || url.is_empty() {
+        WebviewUrl::App(new_tab_page().into())
+    } else {
+        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+        WebviewUrl::External(parsed)
+    };
+    let initial_url = match &webview_url {
+        WebviewUrl::External(parsed) => Some(parsed.to_string()),
+        _ => None,
+    };
+
+    let mut builder = WebviewBuilder::new(&label, webview_url).incognito(incognito);
+    let tab_user_agent = state.tabs.get(&id).and_then(|tab| tab.user_agent.as_deref());
+    if let Some(user_agent) = tab_user_agent.or(user_agent_override()) {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(script) = debug_init_script(&id) {
+        builder = builder.initialization_script(script);
+    }
+    builder = builder.initialization_script(link_intercept_script(&id, &state.link_intercept));
+    builder = builder.initialization_script(context_menu_script(&id, state.context_menu_intercept));
+    builder = builder.initialization_script(selection_script(&id));
+    builder = builder.initialization_script(NETWORK_ACTIVITY_SCRIPT);
+    builder = builder.initialization_script(CLOSE_GUARD_SCRIPT);
+    builder = builder.initialization_script(favicon_script(&id));
+    builder = builder.initialization_script(title_script(&id));
+    builder = builder.initialization_script(audio_script(&id));
+    builder = builder.initialization_script(load_progress_script(&id));
+    for script in document_start_scripts(state, &id) {
+        builder = builder.initialization_script(user_script_source(&script));
+    }
+
+    let app_handle = app.clone();
+    let tab_id = id.clone();
     let builder = builder.on_page_load(move |_webview, payload| {
-            if payload.event() == tauri::webview::PageLoadEvent::Finished {
-                let url_str = normalize_tab_url(payload.url());
+            let finished = payload.event() == tauri::webview::PageLoadEvent::Finished;
+            let url_str = normalize_tab_url(payload.url());
+            record_page_load(&app_handle, &tab_id, !finished, url_str.clone());
+            if finished {
+                // The page-side estimate can stall short of 1 (e.g. lazy
+                // resources), so Finished always completes the progress bar.
+                let _ = app_handle.emit(
+                    "tab-load-progress",
+                    serde_json::json!({
+                        "tabId": tab_id,
+                        "progress": 1.0,
+                    }),
+                );
                 let _ = app_handle.emit(
                     "tab-loaded",
                     serde_json::json!({
                         "tabId": tab_id,
-                        "url": url_str,
+                        "url": url_str,
+                    }),
+                );
+            } else {
+                let _ = app_handle.emit(
+                    "tab-load-started",
+                    serde_json::json!({
+                        "tabId": tab_id,
+                        "url": url_str,
+                    }),
+                );
+            }
+        });
+
+    let app_handle2 = app.clone();
+    let tab_id2 = id.clone();
+    let blocklist = Arc::clone(&state.navigation_blocklist);
+    let initial_seen = AtomicBool::new(false);
+    let builder = builder.on_navigation(move |nav_url| {
+        // The URL the tab was opened with is exempt from the blocklist once.
+        let is_initial = initial_url.as_deref() == Some(nav_url.as_str())
+            && !initial_seen.swap(true, Ordering::Relaxed);
+        if !is_initial {
+            if let Some(pattern) = blocked_by(&blocklist, nav_url) {
+                log::info!("[Tabs] Blocked navigation: tabId={} url={} pattern={}", tab_id2, nav_url, pattern);
+                let _ = app_handle2.emit(
+                    "tab-navigation-blocked",
+                    serde_json::json!({
+                        "tabId": tab_id2,
+                        "url": nav_url.as_str(),
+                        "pattern": pattern,
+                    }),
+                );
+                return false;
+            }
+        }
+
+        // Not written back to `TabInfo.url`: this also fires for subframes
+        // and for each redirect hop. `on_page_load` records the resolved URL.
+        let url_str = normalize_tab_url(nav_url);
+        let _ = app_handle2.emit(
+            "tab-navigated",
+            serde_json::json!({
+                "tabId": tab_id2,
+                "url": url_str,
+            }),
+        );
+        reset_find_state(&app_handle2, &tab_id2);
+        true
+    });
+
+    let app_handle3 = app.clone();
+    let tab_id3 = id.clone();
+    let builder = builder.on_new_window(move |url, _features| {
+        let url_str = normalize_tab_url(&url);
+        let _ = app_handle3.emit(
+            "tab-open-request",
+            serde_json::json!({
+                "tabId": tab_id3,
+                "url": url_str,
+                "reason": "new-window",
+            }),
+        );
+        NewWindowResponse::Deny
+    });
+
+    let app_handle4 = app.clone();
+    let tab_id4 = id.clone();
+    let builder = builder.on_download(move |_webview, event| {
+        downloads::handle_download_event(&app_handle4, &tab_id4, event)
+    });
+
+    // Add the new webview as a child of the window
+    let webview = window
+        .add_child(builder, position, size)
+        .map_err(|e| format!("Failed to create webview: {}", e))?;
+
+    // Immediately disable auto-resize before anything else can override our position
+    let _ = webview.set_auto_resize(false);
+    watch_certificate_errors(app, &webview, &id);
+    watch_permission_requests(app, &webview, &id);
+
+    // Check bounds right after add_child
+    apply_bounds(window, &webview, state)?;
+    devtools::watch_webview_devtools(app.clone(), label.clone());
+
+    Ok(webview)
+}
+
+/// Tear down a background tab's webview to reclaim its memory, keeping its
+/// entry (URL, title, scroll offset) so it still shows in `list_tabs` and can
+/// be woken later. The active tab and incognito tabs, whose data store would
+/// be lost, can't be hibernated. Emits `tab-hibernated`.
+pub async fn hibernate_tab(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    {
+        let state = tab_window_state(app, tab_id)?;
+        let state = state.lock().map_err(|e| e.to_string())?;
+        check_hibernatable(&state, tab_id)?;
+    }
+
+    // Best effort: a page that doesn't answer is restored from the top.
+    let scroll = eval_with_result(app, tab_id, SCROLL_METRICS_SCRIPT, Duration::from_millis(EVAL_TIMEOUT_MS))
+        .await
+        .ok()
+        .and_then(|value| serde_json::from_value::<ScrollMetrics>(value).ok())
+        .map(|metrics| ScrollPosition {
+            x: metrics.x,
+            y: metrics.y,
+        });
+
+    let state = tab_window_state(app, tab_id)?;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    // The tab may have been activated while its scroll offset was read.
+    check_hibernatable(&state, tab_id)?;
+    hibernate_webview(app, &mut state, tab_id, scroll);
+    Ok(())
+}
+
+fn check_hibernatable(state: &TabState, tab_id: &str) -> Result<(), String> {
+    let tab = state
+        .tabs
+        .get(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    if tab.hibernated {
+        return Err(format!("Tab {} is already hibernated", tab_id));
+    }
+    if tab.incognito {
+        return Err("Incognito tabs can't be hibernated".to_string());
+    }
+    if state.active_tab.as_deref() == Some(tab_id) {
+        return Err("The active tab can't be hibernated".to_string());
+    }
+    Ok(())
+}
+
+fn hibernate_webview(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    scroll: Option<ScrollPosition>,
+) {
+    if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+        let _ = webview.close();
+    }
+    forget_rejected_certificate(app, tab_id);
+    deny_tab_permissions(app, tab_id);
+    if let Some(tab) = state.tabs.get_mut(tab_id) {
+        tab.hibernated = true;
+        tab.scroll = scroll;
+    }
+    state.find.remove(tab_id);
+    state.loading.remove(tab_id);
+    state.loaded.remove(tab_id);
+    log::info!("[Tabs] hibernated tab {}", tab_id);
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tab-hibernated",
+        serde_json::json!({ "tabId": tab_id }),
+    );
+}
+
+/// Re-create a hibernated tab's webview at its last URL. The scroll offset is
+/// restored once the page finishes loading. The tab stays in the background
+/// unless it is the active one; `switch_tab` wakes tabs on its own.
+/// Emits `tab-woken`.
+pub fn wake_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+) -> Result<(), String> {
+    let tab = state
+        .tabs
+        .get(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    if !tab.hibernated {
+        return Ok(());
+    }
+    let (url, zoom) = (tab.url.clone(), tab.zoom);
+    let window = app
+        .get_window(&state.window_label)
+        .ok_or_else(|| format!("Window {} not found", state.window_label))?;
+
+    let webview = build_tab_webview(app, state, &window, tab_id, &url, false)?;
+    if state.active_tab.as_deref() != Some(tab_id) {
+        park_webview(Some(&window), &webview, state);
+    }
+    let _ = webview.set_zoom(zoom);
+    if let Some(tab) = state.tabs.get_mut(tab_id) {
+        tab.hibernated = false;
+    }
+    log::info!("[Tabs] woke tab {}", tab_id);
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tab-woken",
+        serde_json::json!({ "tabId": tab_id }),
+    );
+    Ok(())
+}
+
+/// Every minute, hibernate background tabs that haven't been active for
+/// `BrowserSettings::auto_hibernate_minutes` (0 disables this).
+pub fn start_hibernation_monitor(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_millis(HIBERNATION_CHECK_INTERVAL_MS)).await;
+            let minutes = settings::load().auto_hibernate_minutes;
+            if minutes == 0 {
+                continue;
+            }
+            let cutoff = now_ms().saturating_sub(u64::from(minutes) * 60_000);
+            let mut idle = Vec::new();
+            for state in all_window_states(&app).unwrap_or_default() {
+                let Ok(state) = state.lock() else {
+                    continue;
+                };
+                idle.extend(
+                    state
+                        .tabs
+                        .values()
+                        .filter(|tab| tab.last_active_ms < cutoff)
+                        .filter(|tab| check_hibernatable(&state, &tab.id).is_ok())
+                        .map(|tab| tab.id.clone()),
+                );
+            }
+            for tab_id in idle {
+                if let Err(e) = hibernate_tab(&app, &tab_id).await {
+                    log::warn!("[Tabs] Failed to auto-hibernate tab {}: {}", tab_id, e);
+                }
+            }
+        }
+    });
+}
+
+/// Close a content webview tab.
+/// Returns the new active tab ID (if any) so the frontend can stay in sync.
+pub fn close_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+) -> Result<Option<String>, String> {
+    let label = format!("tab-{}", tab_id);
+    if let Some(webview) = app.get_webview(&label) {
+        // Incognito stores are per-tab except on Windows, where all InPrivate
+        // tabs share one; only wipe that when its last tab closes.
+        let incognito = state.tabs.get(tab_id).is_some_and(|tab| tab.incognito);
+        let shared_store_in_use = cfg!(target_os = "windows")
+            && state.tabs.values().any(|tab| tab.incognito && tab.id != tab_id);
+        if incognito && !shared_store_in_use {
+            if let Err(e) = webview.clear_all_browsing_data() {
+                log::warn!("[Tabs] Failed to clear incognito tab {} data: {}", tab_id, e);
+            }
+        }
+        let _ = webview.close();
+    }
+    forget_rejected_certificate(app, tab_id);
+    deny_tab_permissions(app, tab_id);
+
+    if let Some(tab) = state.tabs.shift_remove(tab_id) {
+        if !tab.incognito {
+            if state.closed_tabs.len() == MAX_CLOSED_TABS {
+                state.closed_tabs.pop_front();
+            }
+            state.closed_tabs.push_back(ClosedTab {
+                url: tab.url,
+                title: tab.title,
+                pinned: tab.pinned,
+            });
+        }
+    }
+    state.find.remove(tab_id);
+    state.headers.remove(tab_id);
+    state.loading.remove(tab_id);
+    state.loaded.remove(tab_id);
+    state.close_pending.remove(tab_id);
+
+    if state.active_tab.as_deref() == Some(tab_id) {
+        // Activate the next available tab, waking it if it was hibernated.
+        state.active_tab = None;
+        if let Some(next) = state.tabs.keys().next().cloned() {
+            if let Err(e) = switch_tab(app, state, &next) {
+                log::warn!("[Tabs] Failed to activate tab {} after closing {}: {}", next, tab_id, e);
+            }
+        }
+    }
+
+    Ok(state.active_tab.clone())
+}
+
+/// Close every unpinned tab except `keep_tab_id`, which is made active. Returns the
+/// closed ids; each can be brought back with `reopen_closed_tab`.
+/// Emits a single `tabs-changed`.
+pub fn close_other_tabs(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    keep_tab_id: &str,
+) -> Result<Vec<String>, String> {
+    let closing: Vec<String> = state
+        .tabs
+        .values()
+        .filter(|tab| tab.id != keep_tab_id && !tab.pinned)
+        .map(|tab| tab.id.clone())
+        .collect();
+    close_tabs(app, state, keep_tab_id, closing)
+}
+
+/// Close the unpinned tabs after `tab_id` in the tab strip. If the active tab was
+/// among them, `tab_id` becomes active. Returns the closed ids; each can be
+/// brought back with `reopen_closed_tab`. Emits a single `tabs-changed`.
+pub fn close_tabs_to_right(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+) -> Result<Vec<String>, String> {
+    let index = state
+        .tabs
+        .get_index_of(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    let closing: Vec<String> = state
+        .tabs
+        .values()
+        .skip(index + 1)
+        .filter(|tab| !tab.pinned)
+        .map(|tab| tab.id.clone())
+        .collect();
+    close_tabs(app, state, tab_id, closing)
+}
+
+/// Close `closing` in one pass under the caller's lock. `keep_tab_id` is
+/// switched to first when the active tab is about to close, so the window
+/// always has a tab in front and `close_tab` never picks a replacement.
+fn close_tabs(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    keep_tab_id: &str,
+    closing: Vec<String>,
+) -> Result<Vec<String>, String> {
+    if !state.tabs.contains_key(keep_tab_id) {
+        return Err(format!("Tab {} not found", keep_tab_id));
+    }
+    let active_closing = state
+        .active_tab
+        .as_ref()
+        .is_none_or(|active| closing.contains(active));
+    if active_closing {
+        switch_tab(app, state, keep_tab_id)?;
+    }
+    for tab_id in &closing {
+        close_tab(app, state, tab_id)?;
+    }
+
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tabs-changed",
+        serde_json::json!({
+            "order": state.tabs.keys().collect::<Vec<_>>(),
+            "activeTabId": state.active_tab,
+            "closedTabIds": closing,
+        }),
+    );
+    Ok(closing)
+}
+
+/// Move a tab to `new_index` in the tab strip, clamped to the last position
+/// and to its side of the pinned/unpinned boundary.
+/// Emits `tabs-reordered` with the full order.
+pub fn reorder_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    new_index: usize,
+) -> Result<(), String> {
+    let from = state
+        .tabs
+        .get_index_of(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    let pinned = pinned_count(state);
+    let to = if state.tabs[from].pinned {
+        new_index.min(pinned - 1)
+    } else {
+        new_index.clamp(pinned, state.tabs.len() - 1)
+    };
+    state.tabs.move_index(from, to);
+    emit_tab_order(app, state);
+    Ok(())
+}
+
+fn emit_tab_order(app: &tauri::AppHandle, state: &TabState) {
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tabs-reordered",
+        serde_json::json!({ "order": state.tabs.keys().collect::<Vec<_>>() }),
+    );
+}
+
+/// Number of pinned tabs, which always lead the tab strip.
+fn pinned_count(state: &TabState) -> usize {
+    state.tabs.values().take_while(|tab| tab.pinned).count()
+}
+
+/// Pin or unpin a tab. A newly pinned tab joins the end of the pinned
+/// group; an unpinned one becomes the first unpinned tab.
+/// Emits `tab-pinned` and `tabs-reordered`.
+pub fn set_tab_pinned(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    pinned: bool,
+) -> Result<(), String> {
+    let from = state
+        .tabs
+        .get_index_of(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    if state.tabs[from].pinned == pinned {
+        return Ok(());
+    }
+    let count = pinned_count(state);
+    let to = if pinned { count } else { count - 1 };
+    state.tabs[from].pinned = pinned;
+    state.tabs.move_index(from, to);
+
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tab-pinned",
+        serde_json::json!({ "tabId": tab_id, "pinned": pinned }),
+    );
+    emit_tab_order(app, state);
+    Ok(())
+}
+
+/// Re-create the most recently closed tab and emit `tab-reopened`.
+/// Returns the new tab's id, or `None` when nothing is left to reopen.
+pub fn reopen_closed_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+) -> Result<Option<String>, String> {
+    let Some(closed) = state.closed_tabs.pop_back() else {
+        return Ok(None);
+    };
+    let tab_id = match create_tab(app, state, &closed.url, OpenTabOptions::default()) {
+        Ok(tab_id) => tab_id,
+        Err(e) => {
+            state.closed_tabs.push_back(closed);
+            return Err(e);
+        }
+    };
+    if closed.pinned {
+        set_tab_pinned(app, state, &tab_id, true)?;
+    }
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tab-reopened",
+        serde_json::json!({
+            "tabId": tab_id,
+            "url": closed.url,
+            "title": closed.title,
+        }),
+    );
+    Ok(Some(tab_id))
+}
+
+/// Ask the window's frontend to open a blank tab, which it does through
+/// `create_tab` so it tracks the tab like any other. With `beside_active`,
+/// the event names the active tab so the new one is placed to its right.
+/// Emits `open-new-tab`.
+pub fn request_new_tab(app: &tauri::AppHandle, state: &TabState, beside_active: bool) -> Result<(), String> {
+    let after_tab_id = state.active_tab.clone().filter(|_| beside_active);
+    app.emit_to(
+        state.window_label.as_str(),
+        "open-new-tab",
+        serde_json::json!({ "afterTabId": after_tab_id }),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Give keyboard focus back to the window's chrome, which shares the
+/// window's label, so a shortcut handled there works even when a content
+/// webview had focus.
+pub fn focus_chrome(app: &tauri::AppHandle, state: &TabState) -> Result<(), String> {
+    let chrome = app
+        .get_webview(&state.window_label)
+        .ok_or_else(|| format!("Chrome webview for window {} not found", state.window_label))?;
+    chrome
+        .set_focus()
+        .map_err(|e| format!("Failed to focus chrome: {}", e))
+}
+
+/// Give keyboard focus to the content webview of `tab_id`, which must be the
+/// active tab; focusing a parked webview would swallow keystrokes.
+pub fn focus_content_tab(app: &tauri::AppHandle, state: &TabState, tab_id: &str) -> Result<(), String> {
+    if !state.tabs.contains_key(tab_id) {
+        return Err(format!("Tab {} not found", tab_id));
+    }
+    if state.active_tab.as_deref() != Some(tab_id) {
+        return Err(format!("Tab {} is not the active tab", tab_id));
+    }
+    tab_webview(app, tab_id)?
+        .set_focus()
+        .map_err(|e| format!("Failed to focus tab {}: {}", tab_id, e))
+}
+
+/// Ask the window's chrome to focus its address bar (Cmd/Ctrl+L, Alt+D).
+/// Emits `focus-address-bar`.
+pub fn focus_address_bar(app: &tauri::AppHandle, state: &TabState) -> Result<(), String> {
+    let _ = focus_chrome(app, state);
+    app.emit_to(state.window_label.as_str(), "focus-address-bar", ())
+        .map_err(|e| e.to_string())
+}
+
+/// Ask the window's chrome to open find-in-page for the active tab
+/// (Cmd/Ctrl+F). Emits `open-find`.
+pub fn request_find(app: &tauri::AppHandle, state: &TabState) -> Result<(), String> {
+    let _ = focus_chrome(app, state);
+    app.emit_to(
+        state.window_label.as_str(),
+        "open-find",
+        serde_json::json!({ "tabId": state.active_tab }),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Dump each tab's native webview state in window `window_label` alongside
+/// what `TabState` believes, flagging tabs where the two disagree.
+#[cfg(any(debug_assertions, feature = "devtools"))]
+pub async fn debug_webviews(
+    app: &tauri::AppHandle,
+    window_label: &str,
+) -> Result<Vec<WebviewDebugInfo>, String> {
+    let (tabs, snapshot) = {
+        let state = window_state(app, window_label)?;
+        let state = state.lock().map_err(|e| e.to_string())?;
+        let tabs: Vec<(String, bool)> = state
+            .tabs
+            .values()
+            .map(|tab| (tab.id.clone(), tab.hibernated))
+            .collect();
+        (tabs, state.layout_snapshot())
+    };
+    let content = app
+        .get_window(&snapshot.window_label)
+        .and_then(|window| content_bounds(&window, &snapshot.layout).ok());
+
+    let mut infos = Vec::with_capacity(tabs.len());
+    for (tab_id, hibernated) in tabs {
+        let label = format!("tab-{}", tab_id);
+        let active = snapshot.active_tab.as_deref() == Some(tab_id.as_str());
+        let webview = app.get_webview(&label);
+        let position = webview.as_ref().and_then(|webview| webview.position().ok());
+        let size = webview.as_ref().and_then(|webview| webview.size().ok());
+        let visible = match &webview {
+            Some(webview) => devtools::native_visibility(webview).await,
+            None => None,
+        };
+        let expected_position = content.map(|(position, _)| {
+            if active || snapshot.warm_background_tabs {
+                position
+            } else {
+                PhysicalPosition::new(OFFSCREEN_OFFSET, OFFSCREEN_OFFSET)
+            }
+        });
+        let expected_size = content.map(|(_, size)| size);
+        let mismatch = webview.is_some() == hibernated
+            || (webview.is_some()
+                && (expected_position.is_some_and(|expected| position != Some(expected))
+                    || expected_size.is_some_and(|expected| size != Some(expected))
+                    || visible.is_some_and(|visible| visible != active)));
+        infos.push(WebviewDebugInfo {
+            tab_id,
+            label,
+            active,
+            hibernated,
+            exists: webview.is_some(),
+            position,
+            size,
+            visible,
+            expected_position,
+            expected_size,
+            expected_visible: active,
+            mismatch,
+        });
+    }
+    Ok(infos)
+}
+
+/// Close whichever tab is currently in front, unless it is pinned.
+/// Returns the id of the closed tab, or `None` if nothing was closed. Emits
+/// `tab-closed` so the frontend can drop the tab without a round-trip.
+pub fn close_active_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+) -> Result<Option<String>, String> {
+    let tab_id = match state.active_tab.clone() {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    if state.tabs.get(&tab_id).is_some_and(|tab| tab.pinned) {
+        return Ok(None);
+    }
+    close_tab_and_notify(app, state, &tab_id)?;
+    Ok(Some(tab_id))
+}
+
+/// `close_tab`, then emit `tab-closed` for a close the frontend didn't ask for.
+fn close_tab_and_notify(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+) -> Result<(), String> {
+    let new_active = close_tab(app, state, tab_id)?;
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tab-closed",
+        serde_json::json!({
+            "tabId": tab_id,
+            "activeTabId": new_active,
+        }),
+    );
+    Ok(())
+}
+
+/// Whether closing `tab_id` would lose unsaved state, per `CLOSE_GUARD_SCRIPT`.
+/// Pages that can't answer in time, or at all, don't hold up the close.
+async fn close_needs_confirm(app: &tauri::AppHandle, tab_id: &str) -> bool {
+    let timeout = Duration::from_millis(CLOSE_GUARD_TIMEOUT_MS);
+    match eval_with_result(app, tab_id, CLOSE_GUARD_CHECK_SCRIPT, timeout).await {
+        Ok(value) => value.as_bool().unwrap_or(false),
+        Err(e) => {
+            log::debug!("[Tabs] Close check for tab {} failed: {}", tab_id, e);
+            false
+        }
+    }
+}
+
+/// Close a tab the way a user would: if the page has a `beforeunload` handler
+/// that objects or edited form fields, emit `tab-close-confirm` { tabId } and
+/// hold the close until `confirm_close_tab`. Returns whether the tab closed;
+/// a tab that closes emits `tab-closed`. `close_tab` skips this check.
+pub async fn request_close_tab(
+    app: &tauri::AppHandle,
+    window_label: &str,
+    tab_id: &str,
+) -> Result<bool, String> {
+    let state = window_state(app, window_label)?;
+    {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        if !state.tabs.contains_key(tab_id) {
+            return Err(format!("Tab {} not found", tab_id));
+        }
+        if state.close_pending.contains(tab_id) {
+            return Ok(false);
+        }
+    }
+
+    let confirm = close_needs_confirm(app, tab_id).await;
+    let mut state = state.lock().map_err(|e| e.to_string())?;
+    if !state.tabs.contains_key(tab_id) {
+        return Ok(true);
+    }
+    if confirm {
+        if state.close_pending.insert(tab_id.to_string()) {
+            let _ = app.emit_to(
+                state.window_label.as_str(),
+                "tab-close-confirm",
+                serde_json::json!({ "tabId": tab_id }),
+            );
+        }
+        return Ok(false);
+    }
+    close_tab_and_notify(app, &mut state, tab_id)?;
+    Ok(true)
+}
+
+/// `request_close_tab` for the active tab, unless it is pinned. Used by the
+/// Close Tab menu item, which can't wait on the page.
+pub fn request_close_active_tab(app: &tauri::AppHandle, state: &TabState) {
+    let Some(tab_id) = state.active_tab.clone() else {
+        return;
+    };
+    if state.tabs.get(&tab_id).is_some_and(|tab| tab.pinned) {
+        return;
+    }
+    let app = app.clone();
+    let window_label = state.window_label.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = request_close_tab(&app, &window_label, &tab_id).await {
+            log::warn!("[Tabs] Failed to close tab {}: {}", tab_id, e);
+        }
+    });
+}
+
+/// Answer a `tab-close-confirm`: close the tab when `proceed` is set, or keep
+/// it open. Emits `tab-closed` if the tab closes.
+pub fn confirm_close_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    proceed: bool,
+) -> Result<(), String> {
+    if !state.close_pending.remove(tab_id) {
+        return Err(format!("Tab {} has no close waiting for confirmation", tab_id));
+    }
+    if proceed {
+        close_tab_and_notify(app, state, tab_id)?;
+    }
+    Ok(())
+}
+
+/// Switch to the tab at `index` in tab-strip order, or to the last tab when
+/// `index` is `None`. Does nothing if there is no tab at `index`. Emits
+/// `tab-activated` and returns the tab switched to.
+pub fn switch_to_tab_index(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    index: Option<usize>,
+) -> Result<Option<String>, String> {
+    let target = match index {
+        Some(index) => state.tabs.get_index(index),
+        None => state.tabs.last(),
+    };
+    let Some(tab_id) = target.map(|(id, _)| id.clone()) else {
+        return Ok(None);
+    };
+    activate_tab(app, state, &tab_id)?;
+    Ok(Some(tab_id))
+}
+
+/// Switch to the tab `step` places after the active one in tab-strip order,
+/// wrapping around at either end (negative steps go backwards). Emits
+/// `tab-activated` and returns the tab switched to.
+pub fn cycle_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    step: isize,
+) -> Result<Option<String>, String> {
+    let count = state.tabs.len() as isize;
+    let Some(current) = state
+        .active_tab
+        .as_ref()
+        .and_then(|id| state.tabs.get_index_of(id))
+    else {
+        return Ok(None);
+    };
+    let index = (current as isize + step).rem_euclid(count) as usize;
+    switch_to_tab_index(app, state, Some(index))
+}
+
+/// Switch to a tab on the backend's initiative and tell the frontend, which
+/// otherwise only learns of switches it requested.
+fn activate_tab(app: &tauri::AppHandle, state: &mut TabState, tab_id: &str) -> Result<(), String> {
+    if state.active_tab.as_deref() == Some(tab_id) {
+        return Ok(());
+    }
+    switch_tab(app, state, tab_id)?;
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "tab-activated",
+        serde_json::json!({ "tabId": tab_id }),
+    );
+    Ok(())
+}
+
+/// Switch to a tab -- show the target webview, hide all others.
+pub fn switch_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+) -> Result<(), String> {
+    if !state.tabs.contains_key(tab_id) {
+        return Err(format!("Tab {} not found", tab_id));
+    }
+
+    let started = std::time::Instant::now();
+    let window = app.get_window(&state.window_label);
+    if state.tabs.get(tab_id).is_some_and(|tab| tab.hibernated) {
+        wake_tab(app, state, tab_id)?;
+    }
+
+    // Hide the outgoing tab first, then any stragglers, and only then show
+    // the target, so two tabs are never visible at once.
+    let previous = state.active_tab.clone().filter(|id| id != tab_id);
+    let others = state.tabs.keys().filter(|id| *id != tab_id && Some(*id) != previous.as_ref());
+    for existing_id in previous.iter().chain(others) {
+        let label = format!("tab-{}", existing_id);
+        if let Some(webview) = app.get_webview(&label) {
+            park_webview(window.as_ref(), &webview, state);
+        }
+    }
+
+    // Show the target webview
+    let label = format!("tab-{}", tab_id);
+    if let Some(webview) = app.get_webview(&label) {
+        if let Some(window) = &window {
+            let _ = apply_bounds(window, &webview, state);
+        }
+        let _ = webview.show();
+        let _ = webview.set_focus();
+        if let Some(tab) = state.tabs.get(tab_id) {
+            let _ = webview.set_zoom(tab.zoom);
+        }
+    }
+
+    let now = now_ms();
+    for id in state.active_tab.iter().chain(std::iter::once(&tab_id.to_string())) {
+        if let Some(tab) = state.tabs.get_mut(id) {
+            tab.last_active_ms = now;
+        }
+    }
+    state.active_tab = Some(tab_id.to_string());
+    log::info!(
+        "[Tabs] switch_tab: tabId={} warm={} durationMs={}",
+        tab_id,
+        state.warm_background_tabs,
+        started.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+/// Hide all content webviews without changing the active tab state.
+/// Moves each webview far off-screen so it cannot intercept pointer events
+/// even if the native layer remains in the window hierarchy (unless warm
+/// background tabs are enabled, in which case they are only hidden).
+/// Keyboard focus moves to the chrome so keystrokes don't go to a hidden tab.
+/// Returns the active tab, which stays active, so callers can show it again.
+pub fn hide_all_tabs(
+    app: &tauri::AppHandle,
+    state: &TabState,
+) -> Result<Option<String>, String> {
+    let window = app.get_window(&state.window_label);
+    for existing_id in state.tabs.keys() {
+        let label = format!("tab-{}", existing_id);
+        if let Some(webview) = app.get_webview(&label) {
+            park_webview(window.as_ref(), &webview, state);
+        }
+    }
+    if let Err(e) = focus_chrome(app, state) {
+        log::warn!("[Tabs] hide_all_tabs: {}", e);
+    }
+    Ok(state.active_tab.clone())
+}
+
+/// Schemes that are navigated as typed, without a `//` authority.
+/// `javascript:` is left out on purpose: typed or pasted into the address bar
+/// it would run in the current page, so it is searched instead.
+const OPAQUE_SCHEMES: &[&str] = &["about:", "data:", "file:", "blob:", "mailto:", "view-source:"];
+
+/// Turn address-bar input into a URL: URLs with a scheme are kept, host-like
+/// input gets a scheme, and anything else becomes a search with
+/// `search_template` (whose `{query}` is replaced by the encoded input).
+/// `localhost` and IP addresses (dotted-quad IPv4, bracketed IPv6) are always
+/// hosts; single-word intranet hosts count when they carry a port or path
+/// (`intranet/`, `wiki:8080`). Numbers that aren't a full IPv4 address
+/// (`42`, `3.14`) are searched.
+fn resolve_address_input(input: &str, search_template: &str) -> String {
+    let input = input.trim();
+    let lowercase = input.to_ascii_lowercase();
+    if input.contains("://") || OPAQUE_SCHEMES.iter().any(|scheme| lowercase.starts_with(scheme)) {
+        return input.to_string();
+    }
+
+    if !input.is_empty() && !input.chars().any(char::is_whitespace) {
+        let authority = input.split(['/', '?', '#']).next().unwrap_or_default();
+        let (host, port) = match authority.find(']') {
+            // Bracketed IPv6 literal, optionally followed by a port.
+            Some(end) => {
+                let (host, rest) = authority.split_at(end + 1);
+                (host, rest.strip_prefix(':'))
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let valid_port = port.is_none_or(|port| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()));
+        // The URL parser reads dotted numbers such as `3.14` as IPv4
+        // shorthand, so only a full dotted quad is taken as an address.
+        let numeric = host.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+        let local = host.eq_ignore_ascii_case("localhost")
+            || host.parse::<std::net::Ipv4Addr>().is_ok()
+            || host.starts_with('[');
+        let domain = host.contains('.') && !numeric;
+        let intranet = !numeric && (port.is_some() || authority.len() < input.len());
+        let scheme = if local || !host.contains('.') { "http" } else { "https" };
+        if valid_port && !host.is_empty() && (local || domain || intranet) {
+            let candidate = format!("{}://{}", scheme, input);
+            if url::Url::parse(&candidate).is_ok() {
+                return candidate;
+            }
+        }
+    }
+
+    let query: String = url::form_urlencoded::byte_serialize(input.as_bytes()).collect();
+    search_template.replace("{query}", &query)
+}
+
+/// Load `url` in a tab. Input that isn't a URL is resolved as an address-bar
+/// entry (see `resolve_address_input`). Returns the URL that was loaded.
+pub fn navigate_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    url: &str,
+) -> Result<String, String> {
+    let resolved = resolve_address_input(url, &settings::load().search_engine_template);
+    let url = resolved.as_str();
+    let origin_changed = if let Some(tab) = state.tabs.get_mut(tab_id) {
+        let origin_changed = zoom_origin(&tab.url) != zoom_origin(url);
+        tab.url = url.to_string();
+        tab.requested_url = url.to_string();
+        // A hibernated tab loads the new URL when woken, from the top.
+        tab.scroll = None;
+        origin_changed
+    } else {
+        return Err(format!("Tab {} not found", tab_id));
+    };
+    if origin_changed {
+        apply_site_zoom(app, state, tab_id, url);
+    }
+
+    let label = format!("tab-{}", tab_id);
+    if let Some(webview) = app.get_webview(&label) {
+        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+        match state.headers.get(tab_id).filter(|headers| !headers.is_empty()) {
+            Some(headers) => load_with_headers(&webview, parsed, headers.clone())?,
+            None => webview
+                .navigate(parsed)
+                .map_err(|e| format!("Navigation failed: {}", e))?,
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Header names must be RFC 7230 tokens; values may not contain control
+/// characters (other than tab), which rules out CRLF injection.
+fn validate_header(name: &str, value: &str) -> Result<(), String> {
+    const TOKEN_SYMBOLS: &[u8] = b"!#$%&'*+-.^_`|~";
+    let valid_name = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || TOKEN_SYMBOLS.contains(&b));
+    if !valid_name {
+        return Err(format!("Invalid header name: {:?}", name));
+    }
+    if value.chars().any(|c| c.is_control() && c != '\t') {
+        return Err(format!("Invalid value for header {}", name));
+    }
+    Ok(())
+}
+
+/// Replace the extra headers sent when `navigate_tab` loads a page in this
+/// tab. An empty map clears them.
+pub fn set_tab_headers(
+    state: &mut TabState,
+    tab_id: &str,
+    headers: HashMap<String, String>,
+) -> Result<(), String> {
+    if !state.tabs.contains_key(tab_id) {
+        return Err(format!("Tab {} not found", tab_id));
+    }
+    for (name, value) in &headers {
+        validate_header(name, value)?;
+    }
+    if headers.is_empty() {
+        state.headers.remove(tab_id);
+    } else {
+        state.headers.insert(tab_id.to_string(), headers);
+    }
+    Ok(())
+}
+
+/// Load `url` as a main-frame request carrying `headers`.
+#[cfg(target_os = "macos")]
+fn load_with_headers(
+    webview: &Webview,
+    url: url::Url,
+    headers: HashMap<String, String>,
+) -> Result<(), String> {
+    use objc2_foundation::{NSMutableURLRequest, NSString, NSURL};
+
+    webview
+        .with_webview(move |platform| unsafe {
+            let view: &objc2_web_kit::WKWebView = &*platform.inner().cast();
+            let Some(ns_url) = NSURL::URLWithString(&NSString::from_str(url.as_str())) else {
+                log::error!("[Tabs] Cannot load {} with headers: invalid URL", url);
+                return;
+            };
+            let request = NSMutableURLRequest::requestWithURL(&ns_url);
+            for (name, value) in &headers {
+                request.setValue_forHTTPHeaderField(
+                    Some(&NSString::from_str(value)),
+                    &NSString::from_str(name),
+                );
+            }
+            let _ = view.loadRequest(&request);
+        })
+        .map_err(|e| format!("Navigation failed: {}", e))
+}
+
+/// Load `url` as a main-frame request carrying `headers`.
+#[cfg(target_os = "linux")]
+fn load_with_headers(
+    webview: &Webview,
+    url: url::Url,
+    headers: HashMap<String, String>,
+) -> Result<(), String> {
+    use webkit2gtk::{URIRequestExt, WebViewExt};
+
+    webview
+        .with_webview(move |platform| {
+            let request = webkit2gtk::URIRequest::new(url.as_str());
+            if let Some(request_headers) = request.http_headers() {
+                for (name, value) in &headers {
+                    request_headers.replace(name, value);
+                }
+            }
+            platform.inner().load_request(&request);
+        })
+        .map_err(|e| format!("Navigation failed: {}", e))
+}
+
+/// Without a native request API the headers can't be attached, so this
+/// falls back to a plain navigation.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn load_with_headers(
+    webview: &Webview,
+    url: url::Url,
+    _headers: HashMap<String, String>,
+) -> Result<(), String> {
+    log::warn!("[Tabs] Custom request headers are not supported on this platform; loading {} without them", url);
+    webview
+        .navigate(url)
+        .map_err(|e| format!("Navigation failed: {}", e))
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    /// The last certificate each tab rejected, with the URL that failed. GTK
+    /// objects stay on the main thread, where `proceed_anyway` reads them.
+    static REJECTED_CERTIFICATES: std::cell::RefCell<HashMap<String, (String, webkit2gtk::gio::TlsCertificate)>> =
+        std::cell::RefCell::new(HashMap::new());
+}
+
+#[cfg(target_os = "linux")]
+fn certificate_error_kind(errors: webkit2gtk::gio::TlsCertificateFlags) -> &'static str {
+    use webkit2gtk::gio::TlsCertificateFlags;
+    [
+        (TlsCertificateFlags::UNKNOWN_CA, "unknown-ca"),
+        (TlsCertificateFlags::BAD_IDENTITY, "bad-identity"),
+        (TlsCertificateFlags::NOT_ACTIVATED, "not-yet-valid"),
+        (TlsCertificateFlags::EXPIRED, "expired"),
+        (TlsCertificateFlags::REVOKED, "revoked"),
+        (TlsCertificateFlags::INSECURE, "insecure"),
+    ]
+    .into_iter()
+    .find(|(flag, _)| errors.contains(*flag))
+    .map_or("generic", |(_, kind)| kind)
+}
+
+/// Emit `tab-certificate-error` when a load fails TLS validation. WebKit still
+/// shows its own error page.
+#[cfg(target_os = "linux")]
+fn watch_certificate_errors(app: &tauri::AppHandle, webview: &Webview, tab_id: &str) {
+    use webkit2gtk::WebViewExt;
+
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    let _ = webview.with_webview(move |platform| {
+        platform
+            .inner()
+            .connect_load_failed_with_tls_errors(move |_view, uri, certificate, errors| {
+                let error_kind = certificate_error_kind(errors);
+                log::warn!("[Tabs] Certificate error: tabId={} url={} kind={}", tab_id, uri, error_kind);
+                REJECTED_CERTIFICATES.with(|rejected| {
+                    rejected
+                        .borrow_mut()
+                        .insert(tab_id.clone(), (uri.to_string(), certificate.clone()));
+                });
+                let _ = app.emit(
+                    "tab-certificate-error",
+                    serde_json::json!({
+                        "tabId": tab_id,
+                        "url": uri,
+                        "errorKind": error_kind,
+                    }),
+                );
+                false
+            });
+    });
+}
+
+/// WKWebView reports certificate challenges to its navigation delegate, which
+/// wry owns, and WebView2's `ServerCertificateErrorDetected` isn't reachable
+/// without the WebView2 COM bindings, so failures there surface only as the
+/// platform's own error page.
+#[cfg(not(target_os = "linux"))]
+fn watch_certificate_errors(_app: &tauri::AppHandle, _webview: &Webview, _tab_id: &str) {}
+
+/// Forget the certificate a tab rejected once its webview is gone.
+#[cfg(target_os = "linux")]
+fn forget_rejected_certificate(app: &tauri::AppHandle, tab_id: &str) {
+    let tab_id = tab_id.to_string();
+    let _ = app.run_on_main_thread(move || {
+        REJECTED_CERTIFICATES.with(|rejected| rejected.borrow_mut().remove(&tab_id));
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+fn forget_rejected_certificate(_app: &tauri::AppHandle, _tab_id: &str) {}
+
+/// Trust the certificate the tab last rejected for its host and reload the
+/// failed URL. Only for the tab's own data store, and only when
+/// `allowCertificateBypass` is set in the browser settings.
+pub async fn proceed_anyway(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    if !settings::load().allow_certificate_bypass {
+        return Err("Certificate bypass is disabled (allowCertificateBypass in browser settings)".to_string());
+    }
+    let webview = tab_webview(app, tab_id)?;
+    proceed_past_certificate(&webview, tab_id).await
+}
+
+#[cfg(target_os = "linux")]
+async fn proceed_past_certificate(webview: &Webview, tab_id: &str) -> Result<(), String> {
+    use webkit2gtk::{WebContextExt, WebViewExt};
+
+    let tab_id = tab_id.to_string();
+    with_native_webview(webview, move |platform| {
+        let (uri, certificate) = REJECTED_CERTIFICATES
+            .with(|rejected| rejected.borrow_mut().remove(&tab_id))
+            .ok_or_else(|| format!("Tab {} has no certificate error to bypass", tab_id))?;
+        let host = url::Url::parse(&uri)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .ok_or_else(|| format!("No host in {}", uri))?;
+        let view = platform.inner();
+        let context = view.context().ok_or("Webview has no web context")?;
+        context.allow_tls_certificate_for_host(&certificate, &host);
+        log::warn!("[Tabs] Bypassing certificate error: tabId={} host={}", tab_id, host);
+        view.load_uri(&uri);
+        Ok(())
+    })
+    .await?
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn proceed_past_certificate(_webview: &Webview, _tab_id: &str) -> Result<(), String> {
+    Err("Certificate bypass is not supported on this platform".to_string())
+}
+
+/// A permission prompt waiting for `respond_permission`.
+#[cfg(target_os = "linux")]
+struct PendingPermission {
+    request: webkit2gtk::PermissionRequest,
+    tab_id: String,
+    origin: String,
+    permission: &'static str,
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    /// Unanswered permission prompts by request id, on the GTK main thread.
+    static PENDING_PERMISSIONS: std::cell::RefCell<HashMap<String, PendingPermission>> =
+        std::cell::RefCell::new(HashMap::new());
+}
+
+/// The permission a WebKit request asks for, if it is one the app decides.
+#[cfg(target_os = "linux")]
+fn permission_kind(request: &webkit2gtk::PermissionRequest) -> Option<&'static str> {
+    use webkit2gtk::glib::{Cast, ObjectExt};
+    use webkit2gtk::UserMediaPermissionRequestExt;
+
+    if let Some(media) = request.downcast_ref::<webkit2gtk::UserMediaPermissionRequest>() {
+        return match (media.is_for_video_device(), media.is_for_audio_device()) {
+            (true, true) => Some("camera-microphone"),
+            (true, false) => Some("camera"),
+            (false, true) => Some("microphone"),
+            (false, false) => None,
+        };
+    }
+    if request.is::<webkit2gtk::GeolocationPermissionRequest>() {
+        return Some("geolocation");
+    }
+    if request.is::<webkit2gtk::NotificationPermissionRequest>() {
+        return Some("notifications");
+    }
+    None
+}
+
+/// A remembered decision for `permission` on `origin` in the tab's window.
+#[cfg(target_os = "linux")]
+fn remembered_permission(app: &tauri::AppHandle, tab_id: &str, origin: &str, permission: &str) -> Option<bool> {
+    let state = tab_window_state(app, tab_id).ok()?;
+    let state = state.lock().ok()?;
+    state
+        .permission_policy
+        .get(&(origin.to_string(), permission.to_string()))
+        .copied()
+}
+
+/// Hold camera, microphone, geolocation and notification prompts and emit
+/// `tab-permission-request` so the UI or agent can answer with
+/// `respond_permission`. Remembered decisions are applied directly.
+#[cfg(target_os = "linux")]
+fn watch_permission_requests(app: &tauri::AppHandle, webview: &Webview, tab_id: &str) {
+    use webkit2gtk::{PermissionRequestExt, WebViewExt};
+
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    let _ = webview.with_webview(move |platform| {
+        platform.inner().connect_permission_request(move |view, request| {
+            let Some(permission) = permission_kind(request) else {
+                return false;
+            };
+            let origin = view
+                .uri()
+                .and_then(|uri| url::Url::parse(&uri).ok())
+                .map(|url| url.origin().ascii_serialization())
+                .unwrap_or_default();
+            if let Some(allow) = remembered_permission(&app, &tab_id, &origin, permission) {
+                if allow {
+                    request.allow();
+                } else {
+                    request.deny();
+                }
+                return true;
+            }
+
+            let request_id = uuid::Uuid::new_v4().to_string();
+            PENDING_PERMISSIONS.with(|pending| {
+                pending.borrow_mut().insert(
+                    request_id.clone(),
+                    PendingPermission {
+                        request: request.clone(),
+                        tab_id: tab_id.clone(),
+                        origin: origin.clone(),
+                        permission,
+                    },
+                );
+            });
+            let _ = app.emit(
+                "tab-permission-request",
+                serde_json::json!({
+                    "requestId": request_id,
+                    "tabId": tab_id,
+                    "permission": permission,
+                    "origin": origin,
+                }),
+            );
+
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(PERMISSION_TIMEOUT_SECS)).await;
+                // Already answered unless this succeeds.
+                if respond_permission(&app, &request_id, false, false).await.is_ok() {
+                    log::info!("[Tabs] Permission request {} timed out and was denied", request_id);
+                }
+            });
+            true
+        });
+    });
+}
+
+/// WKWebView and WebView2 route permission prompts through delegates and
+/// COM handlers the webview library owns, so they keep the platform default.
+#[cfg(not(target_os = "linux"))]
+fn watch_permission_requests(_app: &tauri::AppHandle, _webview: &Webview, _tab_id: &str) {}
+
+/// Deny and drop a tab's unanswered permission prompts once its webview is
+/// gone, emitting `tab-permission-resolved` for each.
+#[cfg(target_os = "linux")]
+fn deny_tab_permissions(app: &tauri::AppHandle, tab_id: &str) {
+    use webkit2gtk::PermissionRequestExt;
+
+    let app_handle = app.clone();
+    let tab_id = tab_id.to_string();
+    let _ = app.run_on_main_thread(move || {
+        let dropped: Vec<(String, PendingPermission)> = PENDING_PERMISSIONS.with(|pending| {
+            let mut pending = pending.borrow_mut();
+            let ids: Vec<String> = pending
+                .iter()
+                .filter(|(_, request)| request.tab_id == tab_id)
+                .map(|(id, _)| id.clone())
+                .collect();
+            ids.into_iter()
+                .filter_map(|id| pending.remove(&id).map(|request| (id, request)))
+                .collect()
+        });
+        for (request_id, pending) in dropped {
+            pending.request.deny();
+            let _ = app_handle.emit(
+                "tab-permission-resolved",
+                serde_json::json!({
+                    "requestId": request_id,
+                    "tabId": pending.tab_id,
+                    "permission": pending.permission,
+                    "origin": pending.origin,
+                    "allowed": false,
+                }),
+            );
+        }
+    });
+}
+
+#[cfg(not(target_os = "linux"))]
+fn deny_tab_permissions(_app: &tauri::AppHandle, _tab_id: &str) {}
+
+/// Allow or deny a held prompt on the main thread. Returns its tab, origin and
+/// permission, or `None` if it was already answered.
+#[cfg(target_os = "linux")]
+async fn answer_pending_permission(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    allow: bool,
+) -> Result<Option<(String, String, String)>, String> {
+    use webkit2gtk::PermissionRequestExt;
+
+    let (sender, receiver) = oneshot::channel();
+    let request_id = request_id.to_string();
+    app.run_on_main_thread(move || {
+        let pending = PENDING_PERMISSIONS.with(|pending| pending.borrow_mut().remove(&request_id));
+        let answered = pending.map(|pending| {
+            if allow {
+                pending.request.allow();
+            } else {
+                pending.request.deny();
+            }
+            (pending.tab_id, pending.origin, pending.permission.to_string())
+        });
+        let _ = sender.send(answered);
+    })
+    .map_err(|e| format!("Failed to answer permission request: {}", e))?;
+    receiver
+        .await
+        .map_err(|_| "Permission request was dropped".to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn answer_pending_permission(
+    _app: &tauri::AppHandle,
+    _request_id: &str,
+    _allow: bool,
+) -> Result<Option<(String, String, String)>, String> {
+    Ok(None)
+}
+
+/// Answer a `tab-permission-request`. With `remember`, the decision is reused
+/// for the same origin and permission across the window's tabs. Emits
+/// `tab-permission-resolved`.
+pub async fn respond_permission(
+    app: &tauri::AppHandle,
+    request_id: &str,
+    allow: bool,
+    remember: bool,
+) -> Result<(), String> {
+    let (tab_id, origin, permission) = answer_pending_permission(app, request_id, allow)
+        .await?
+        .ok_or_else(|| format!("Permission request {} not found", request_id))?;
+    if remember {
+        let state = tab_window_state(app, &tab_id)?;
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        state
+            .permission_policy
+            .insert((origin.clone(), permission.clone()), allow);
+    }
+    let _ = app.emit(
+        "tab-permission-resolved",
+        serde_json::json!({
+            "requestId": request_id,
+            "tabId": tab_id,
+            "permission": permission,
+            "origin": origin,
+            "allowed": allow,
+        }),
+    );
+    Ok(())
+}
+
+fn tab_webview(app: &tauri::AppHandle, tab_id: &str) -> Result<Webview, String> {
+    app.get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Tab {} not found", tab_id))
+}
+
+/// Run `f` against the platform webview on the main thread and return its result.
+async fn with_native_webview<R, F>(webview: &Webview, f: F) -> Result<R, String>
+where
+    R: Send + 'static,
+    F: FnOnce(tauri::webview::PlatformWebview) -> R + Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    webview
+        .with_webview(move |platform| {
+            let _ = sender.send(f(platform));
+        })
+        .map_err(|e| format!("Failed to access webview: {}", e))?;
+    receiver
+        .await
+        .map_err(|_| "Webview closed before responding".to_string())
+}
+
+/// Whether the tab can go (back, forward), read from the native back-forward list.
+#[cfg(target_os = "macos")]
+async fn history_availability(
+    _app: &tauri::AppHandle,
+    _tab_id: &str,
+    webview: &Webview,
+) -> Result<(bool, bool), String> {
+    with_native_webview(webview, |platform| unsafe {
+        let view: &objc2_web_kit::WKWebView = &*platform.inner().cast();
+        (view.canGoBack(), view.canGoForward())
+    })
+    .await
+}
+
+/// Whether the tab can go (back, forward), read from the native back-forward list.
+#[cfg(target_os = "linux")]
+async fn history_availability(
+    _app: &tauri::AppHandle,
+    _tab_id: &str,
+    webview: &Webview,
+) -> Result<(bool, bool), String> {
+    use webkit2gtk::WebViewExt;
+    with_native_webview(webview, |platform| {
+        let view = platform.inner();
+        (view.can_go_back(), view.can_go_forward())
+    })
+    .await
+}
+
+/// Whether the tab can go (back, forward), via the page's Navigation API.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+async fn history_availability(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    _webview: &Webview,
+) -> Result<(bool, bool), String> {
+    let value = eval_with_result(
+        app,
+        tab_id,
+        HISTORY_AVAILABILITY_SCRIPT,
+        Duration::from_millis(EVAL_TIMEOUT_MS),
+    )
+    .await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected history state: {}", e))
+}
+
+/// Emit `tab-navigated` once a history traversal has changed the URL.
+/// Same-document traversals never reach `on_navigation`, so the URL is polled.
+fn emit_when_navigated(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    webview: Webview,
+    before: Option<url::Url>,
+) {
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        for _ in 0..HISTORY_SETTLE_POLLS {
+            tokio::time::sleep(Duration::from_millis(HISTORY_SETTLE_POLL_MS)).await;
+            let Ok(url) = webview.url() else {
+                return;
+            };
+            if before.as_ref() != Some(&url) {
+                let url = normalize_tab_url(&url);
+                set_tab_url(&app, &tab_id, url.clone());
+                let _ = app.emit(
+                    "tab-navigated",
+                    serde_json::json!({
+                        "tabId": tab_id,
+                        "url": url,
                     }),
                 );
+                return;
             }
+        }
+    });
+}
+
+/// Move `delta` entries through a tab's session history. A no-op when there is
+/// nothing in that direction.
+async fn traverse_history(app: &tauri::AppHandle, tab_id: &str, delta: i32) -> Result<(), String> {
+    let webview = tab_webview(app, tab_id)?;
+    let (can_go_back, can_go_forward) = history_availability(app, tab_id, &webview).await?;
+    if (delta < 0 && !can_go_back) || (delta > 0 && !can_go_forward) {
+        return Ok(());
+    }
+    let before = webview.url().ok();
+    webview
+        .eval(format!("history.go({})", delta))
+        .map_err(|e| format!("History navigation failed: {}", e))?;
+    emit_when_navigated(app, tab_id, webview, before);
+    Ok(())
+}
+
+pub async fn tab_back(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    traverse_history(app, tab_id, -1).await
+}
+
+pub async fn tab_forward(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    traverse_history(app, tab_id, 1).await
+}
+
+pub async fn can_go_back(app: &tauri::AppHandle, tab_id: &str) -> Result<bool, String> {
+    let webview = tab_webview(app, tab_id)?;
+    Ok(history_availability(app, tab_id, &webview).await?.0)
+}
+
+pub async fn can_go_forward(app: &tauri::AppHandle, tab_id: &str) -> Result<bool, String> {
+    let webview = tab_webview(app, tab_id)?;
+    Ok(history_availability(app, tab_id, &webview).await?.1)
+}
+
+/// Reload a tab. `bypass_cache` revalidates every resource with the server.
+#[cfg(target_os = "macos")]
+pub async fn reload_tab(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    bypass_cache: bool,
+) -> Result<(), String> {
+    let webview = tab_webview(app, tab_id)?;
+    with_native_webview(&webview, move |platform| unsafe {
+        let view: &objc2_web_kit::WKWebView = &*platform.inner().cast();
+        if bypass_cache {
+            view.reloadFromOrigin();
+        } else {
+            view.reload();
+        }
+    })
+    .await
+}
+
+/// Reload a tab. `bypass_cache` revalidates every resource with the server.
+#[cfg(target_os = "linux")]
+pub async fn reload_tab(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    bypass_cache: bool,
+) -> Result<(), String> {
+    use webkit2gtk::WebViewExt;
+    let webview = tab_webview(app, tab_id)?;
+    with_native_webview(&webview, move |platform| {
+        let view = platform.inner();
+        if bypass_cache {
+            view.reload_bypass_cache();
+        } else {
+            view.reload();
+        }
+    })
+    .await
+}
+
+/// Reload a tab. Without a native hard-reload primitive, `bypass_cache`
+/// re-navigates with a cache-busting query parameter.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub async fn reload_tab(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    bypass_cache: bool,
+) -> Result<(), String> {
+    let webview = tab_webview(app, tab_id)?;
+    let url = webview.url().map_err(|e| e.to_string())?;
+    if !bypass_cache || !matches!(url.scheme(), "http" | "https") {
+        return webview.reload().map_err(|e| format!("Reload failed: {}", e));
+    }
+    let mut busted = url.clone();
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    busted
+        .query_pairs_mut()
+        .append_pair("_clawReload", &stamp.to_string());
+    webview
+        .navigate(busted)
+        .map_err(|e| format!("Reload failed: {}", e))
+}
+
+/// Halt an in-flight navigation and any pending resource loads in a tab.
+#[cfg(target_os = "macos")]
+pub async fn stop_tab_loading(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    let webview = tab_webview(app, tab_id)?;
+    with_native_webview(&webview, |platform| unsafe {
+        let view: &objc2_web_kit::WKWebView = &*platform.inner().cast();
+        view.stopLoading();
+    })
+    .await
+}
+
+/// Halt an in-flight navigation and any pending resource loads in a tab.
+#[cfg(target_os = "linux")]
+pub async fn stop_tab_loading(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    use webkit2gtk::WebViewExt;
+    let webview = tab_webview(app, tab_id)?;
+    with_native_webview(&webview, |platform| platform.inner().stop_loading()).await
+}
+
+/// Halt an in-flight navigation and any pending resource loads in a tab.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub async fn stop_tab_loading(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    tab_webview(app, tab_id)?
+        .eval("window.stop()")
+        .map_err(|e| format!("Stop failed: {}", e))
+}
+
+/// Execute JavaScript in a content webview (agent DOM access) and return the
+/// JSON value of its result, awaiting promises, within `timeout_ms`
+/// (default `RUN_JS_TIMEOUT_MS`).
+/// Uses the standard Tauri webview JS execution API -- the intended mechanism
+/// for trusted agent code to interact with page content (form filling, extraction, etc.).
+/// Only callable from the trusted sidecar process, never from untrusted user input.
+///
+/// Pages whose CSP forbids `eval` cannot report a value; the code still runs
+/// and the result is `null`.
+pub async fn run_js_in_tab(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    code: &str,
+    timeout_ms: Option<u64>,
+) -> Result<Value, String> {
+    let code_literal = serde_json::to_string(code).map_err(|e| e.to_string())?;
+    let blocked_literal = serde_json::to_string(EVAL_BLOCKED_ERROR).map_err(|e| e.to_string())?;
+    let body = RUN_JS_BODY
+        .replace("__BLOCKED__", &blocked_literal)
+        .replace("__CODE__", &code_literal);
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(RUN_JS_TIMEOUT_MS));
+
+    match eval_with_result(app, tab_id, &body, timeout).await {
+        Err(e) if e == EVAL_BLOCKED_ERROR => {
+            let label = format!("tab-{}", tab_id);
+            let webview = app
+                .get_webview(&label)
+                .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+            webview
+                .eval(code)
+                .map_err(|e| format!("JS execution failed: {}", e))?;
+            Ok(Value::Null)
+        }
+        result => result,
+    }
+}
+
+/// Like `run_js_in_tab`, but reports how the code finished instead of
+/// collapsing everything into a value: its JSON result, `undefined`, or the
+/// exception it threw as `{ name, message, stack }`. A result that can't be
+/// serialized to JSON is an `Err`, as is a page whose CSP forbids `eval`.
+pub async fn run_js_in_tab_json(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    code: &str,
+    timeout_ms: Option<u64>,
+) -> Result<JsEvalOutcome, String> {
+    let code_literal = serde_json::to_string(code).map_err(|e| e.to_string())?;
+    let blocked_literal = serde_json::to_string(EVAL_BLOCKED_ERROR).map_err(|e| e.to_string())?;
+    let body = RUN_JS_BODY
+        .replace("__BLOCKED__", &blocked_literal)
+        .replace("__CODE__", &code_literal);
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(RUN_JS_TIMEOUT_MS));
+
+    let payload = eval_payload(app, tab_id, &body, timeout).await?;
+    if payload.ok {
+        return Ok(JsEvalOutcome {
+            value: (!payload.undefined).then_some(payload.value),
+            undefined: payload.undefined,
+            error: None,
         });
+    }
+    match payload.exception {
+        Some(exception) if exception.message == EVAL_BLOCKED_ERROR => {
+            Err("The page's Content Security Policy forbids evaluating code".to_string())
+        }
+        Some(exception) => Ok(JsEvalOutcome {
+            value: None,
+            undefined: false,
+            error: Some(exception),
+        }),
+        None => Err(payload.error.unwrap_or_else(|| "Script failed".to_string())),
+    }
+}
+
+/// Reposition the active content webview after a layout change. Non-active
+/// webviews stay off-screen to avoid intercepting pointer events; warm
+/// background tabs are resized along with the active one, and with
+/// `include_background` the rest are resized in place off-screen so they
+/// don't keep rendering at a stale size.
+pub fn reposition_webviews(
+    app: &tauri::AppHandle,
+    state: &TabState,
+    include_background: bool,
+) -> Result<(), String> {
+    reposition_snapshot(app, &state.layout_snapshot(), include_background)
+}
+
+/// `reposition_webviews` from a snapshot, for callers that have already
+/// released the `TabState` lock.
+fn reposition_snapshot(
+    app: &tauri::AppHandle,
+    state: &LayoutSnapshot,
+    include_background: bool,
+) -> Result<(), String> {
+    let active_id = match &state.active_tab {
+        Some(id) => id.clone(),
+        None => return Ok(()),
+    };
+
+    let window = app
+        .get_window(&state.window_label)
+        .ok_or_else(|| format!("Window {} not found", state.window_label))?;
+
+    let (position, size) = content_bounds(&window, &state.layout)?;
+    let bounds = tauri::Rect {
+        position: position.into(),
+        size: size.into(),
+    };
+
+    let label = format!("tab-{}", active_id);
+    if let Some(webview) = app.get_webview(&label) {
+        let _ = webview.set_auto_resize(false);
+        webview
+            .set_bounds(bounds)
+            .map_err(|e| format!("Failed to set webview bounds: {}", e))?;
+    }
+
+    // Warm background tabs track the content area too, so they are already
+    // the right size when shown.
+    if state.warm_background_tabs || include_background {
+        let background = if state.warm_background_tabs {
+            bounds
+        } else {
+            offscreen_rect(Some(&window), &state.layout)
+        };
+        for tab_id in &state.background_tabs {
+            if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+                let _ = webview.hide();
+                let _ = webview.set_bounds(background);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Toggle focus mode: the frontend collapses the tab list and nav bar on
+/// `focus-mode-changed` while the active webview expands to the full window.
+/// Returns the new state.
+pub fn toggle_focus_mode(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+) -> Result<bool, String> {
+    state.focus_mode = !state.focus_mode;
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "focus-mode-changed",
+        serde_json::json!({ "enabled": state.focus_mode }),
+    );
+    reposition_webviews(app, state, true)?;
+    Ok(state.focus_mode)
+}
+
+/// Apply the persisted chrome zoom to a window's chrome webview when it opens.
+pub fn apply_ui_zoom(app: &tauri::AppHandle, state: &TabState) -> Result<(), String> {
+    if (state.ui_zoom - 1.0).abs() < f64::EPSILON {
+        return Ok(());
+    }
+    let chrome = app
+        .get_webview(&state.window_label)
+        .ok_or_else(|| format!("Webview {} not found", state.window_label))?;
+    chrome
+        .set_zoom(state.ui_zoom)
+        .map_err(|e| format!("Failed to set UI zoom: {}", e))
+}
+
+/// Scale the ClawBrowser chrome (tab strip, nav bar, agent panel) without
+/// touching page zoom. The factor is clamped, persisted, and announced via
+/// `ui-zoom-changed` so the frontend can re-report its layout.
+pub fn set_ui_zoom(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    factor: f64,
+) -> Result<f64, String> {
+    if !factor.is_finite() {
+        return Err(format!("Invalid UI zoom factor: {}", factor));
+    }
+    let factor = factor.clamp(MIN_UI_ZOOM, MAX_UI_ZOOM);
+    let chrome = app
+        .get_webview(&state.window_label)
+        .ok_or_else(|| format!("Webview {} not found", state.window_label))?;
+    chrome
+        .set_zoom(factor)
+        .map_err(|e| format!("Failed to set UI zoom: {}", e))?;
+
+    // `chrome_y_offset` is in unzoomed logical pixels, so it stays valid; the
+    // stored CSS bounds are rescaled by `content_bounds` until the frontend
+    // reports its re-laid-out chrome.
+    state.ui_zoom = factor;
+
+    settings::update(|s| s.ui_zoom = factor)?;
+    let _ = app.emit_to(
+        state.window_label.as_str(),
+        "ui-zoom-changed",
+        serde_json::json!({ "factor": factor }),
+    );
+    reposition_webviews(app, state, true)?;
+    Ok(factor)
+}
+
+/// Whether `host` matches `pattern`, where `*` matches any run of characters.
+/// `*.example.com` also matches `example.com` itself.
+fn host_matches(pattern: &str, host: &str) -> bool {
+    if let Some(base) = pattern.strip_prefix("*.") {
+        if host == base {
+            return true;
+        }
+    }
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return false,
+    };
+    let Some(mut remaining) = host.strip_prefix(first) else {
+        return false;
+    };
+    let Some((last, middle)) = rest.split_last() else {
+        // No wildcard: the whole pattern must have matched.
+        return remaining.is_empty();
+    };
+    for part in middle {
+        match remaining.find(part) {
+            Some(idx) => remaining = &remaining[idx + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.ends_with(last)
+}
+
+/// The blocklist pattern matching `url`'s host, if any.
+fn blocked_by(blocklist: &RwLock<Vec<String>>, url: &url::Url) -> Option<String> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let host = host.trim_end_matches('.');
+    let patterns = blocklist.read().ok()?;
+    patterns
+        .iter()
+        .find(|pattern| host_matches(pattern, host))
+        .cloned()
+}
+
+/// Replace the navigation blocklist. Patterns are hosts with optional `*`
+/// wildcards, e.g. `ads.example.com` or `*.doubleclick.net`.
+pub fn set_navigation_blocklist(state: &TabState, patterns: Vec<String>) -> Result<(), String> {
+    let mut normalized = Vec::with_capacity(patterns.len());
+    for pattern in patterns {
+        let pattern = pattern.trim().trim_end_matches('.').to_ascii_lowercase();
+        if pattern.is_empty() || pattern.contains(['/', ':']) {
+            return Err(format!("Invalid host pattern: {:?}", pattern));
+        }
+        normalized.push(pattern);
+    }
+    let mut blocklist = state.navigation_blocklist.write().map_err(|e| e.to_string())?;
+    *blocklist = normalized;
+    Ok(())
+}
+
+pub fn clear_navigation_blocklist(state: &TabState) -> Result<(), String> {
+    let mut blocklist = state.navigation_blocklist.write().map_err(|e| e.to_string())?;
+    blocklist.clear();
+    Ok(())
+}
+
+/// Change which gestures open links in a new tab. The config is persisted,
+/// baked into tabs created from now on, and pushed into open tabs of every
+/// window. Must be called without holding any window's `TabState` lock.
+pub fn set_link_intercept_config(
+    app: &tauri::AppHandle,
+    config: LinkInterceptConfig,
+) -> Result<LinkInterceptConfig, String> {
+    settings::update(|s| s.link_intercept = config)?;
+
+    let config_literal = serde_json::to_string(&config).map_err(|e| e.to_string())?;
+    let script = format!("window.__CLAW_LINK_CONFIG__ = {};", config_literal);
+    for state in all_window_states(app)? {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        state.link_intercept = config;
+        for tab_id in state.tabs.keys() {
+            if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+                let _ = webview.eval(&script);
+            }
+        }
+    }
+    Ok(config)
+}
+
+/// The user's current text selection in a tab, as `tab-selection` reports it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabSelection {
+    /// Empty when nothing is selected.
+    pub text: String,
+    /// Where the selection is, in viewport CSS pixels.
+    pub rect: Option<ElementRect>,
+    /// `text` was cut off at `MAX_SELECTION_CHARS`.
+    pub truncated: bool,
+}
+
+/// Read the tab's current text selection, including selections in form
+/// fields and same-origin iframes.
+pub async fn get_selection(app: &tauri::AppHandle, tab_id: &str) -> Result<TabSelection, String> {
+    let value = eval_with_result(app, tab_id, GET_SELECTION_SCRIPT, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected selection: {}", e))
+}
+
+/// Turn context-menu interception on or off for every open and future tab.
+/// While off, right-clicks show the platform's native menu.
+pub fn set_context_menu_intercept(app: &tauri::AppHandle, enabled: bool) -> Result<(), String> {
+    settings::update(|s| s.context_menu_intercept = enabled)?;
+
+    let script = format!("window.__CLAW_CONTEXT_MENU_ENABLED__ = {};", enabled);
+    for state in all_window_states(app)? {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        state.context_menu_intercept = enabled;
+        for tab_id in state.tabs.keys() {
+            if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+                let _ = webview.eval(&script);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An entry of the app-rendered context menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContextMenuAction {
+    CopyLink,
+    CopySelection,
+    OpenInNewTab,
+    SaveImage,
+}
+
+/// What was under the pointer, as reported by `tab-context-menu`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextMenuTarget {
+    pub link_url: Option<String>,
+    pub image_url: Option<String>,
+    pub selection_text: Option<String>,
+}
+
+/// Carry out a context-menu choice for `tab_id`. Copying and saving run in the
+/// page; opening a link goes through the window's `tab-open-request`, like a
+/// middle-click.
+pub async fn context_menu_action(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    action: ContextMenuAction,
+    target: ContextMenuTarget,
+) -> Result<(), String> {
+    let timeout = Duration::from_millis(EVAL_TIMEOUT_MS);
+    match action {
+        ContextMenuAction::CopyLink | ContextMenuAction::CopySelection => {
+            let text = match action {
+                ContextMenuAction::CopyLink => target.link_url.ok_or("No link to copy")?,
+                _ => target.selection_text.ok_or("No selection to copy")?,
+            };
+            let text_literal = serde_json::to_string(&text).map_err(|e| e.to_string())?;
+            let body = COPY_TEXT_SCRIPT.replace("__TEXT__", &text_literal);
+            eval_with_result(app, tab_id, &body, timeout).await?;
+        }
+        ContextMenuAction::OpenInNewTab => {
+            let url = target.link_url.ok_or("No link to open")?;
+            let window_label = tab_webview(app, tab_id)?.window().label().to_string();
+            let _ = app.emit_to(
+                window_label.as_str(),
+                "tab-open-request",
+                serde_json::json!({ "tabId": tab_id, "url": url, "reason": "context-menu" }),
+            );
+        }
+        ContextMenuAction::SaveImage => {
+            let url = target.image_url.ok_or("No image to save")?;
+            let url_literal = serde_json::to_string(&url).map_err(|e| e.to_string())?;
+            let body = SAVE_IMAGE_SCRIPT.replace("__URL__", &url_literal);
+            eval_with_result(app, tab_id, &body, timeout).await?;
+        }
+    }
+    Ok(())
+}
 
-    let app_handle2 = app.clone();
-    let tab_id2 = id.clone();
-    let builder = builder.on_navigation(move |nav_url| {
-        let url_str = normalize_tab_url(&nav_url);
-        let _ = app_handle2.emit(
-            "tab-navigated",
-            serde_json::json!({
-                "tabId": tab_id2,
-                "url": url_str,
-            }),
-        );
-        true // allow all navigations
+/// Zoom a tab's page content. The factor is clamped, stored on the tab so it
+/// survives tab switches, and announced via `tab-zoom-changed`.
+pub fn set_tab_zoom(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    factor: f64,
+) -> Result<f64, String> {
+    if !factor.is_finite() {
+        return Err(format!("Invalid tab zoom factor: {}", factor));
+    }
+    let factor = factor.clamp(MIN_TAB_ZOOM, MAX_TAB_ZOOM);
+    let tab = state
+        .tabs
+        .get_mut(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    let webview = app
+        .get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Webview for tab {} not found", tab_id))?;
+    webview
+        .set_zoom(factor)
+        .map_err(|e| format!("Failed to set tab zoom: {}", e))?;
+    tab.zoom = factor;
+    if !tab.incognito {
+        remember_site_zoom(&tab.url, factor);
+    }
+
+    let _ = app.emit(
+        "tab-zoom-changed",
+        serde_json::json!({
+            "tabId": tab_id,
+            "factor": factor,
+        }),
+    );
+    Ok(factor)
+}
+
+/// The key for remembered zoom: `scheme://host[:port]` of an http(s) URL.
+/// Blank and internal pages have none, so they never enter the map.
+fn zoom_origin(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return None;
+    }
+    Some(parsed.origin().ascii_serialization())
+}
+
+/// Persist `factor` as the zoom for `url`'s origin; 100% drops the entry.
+fn remember_site_zoom(url: &str, factor: f64) {
+    let Some(origin) = zoom_origin(url) else {
+        return;
+    };
+    let result = settings::update(|settings| {
+        if factor == 1.0 {
+            settings.site_zoom.remove(&origin);
+        } else {
+            settings.site_zoom.insert(origin.clone(), factor);
+        }
     });
+    if let Err(e) = result {
+        log::warn!("[Tabs] Failed to remember zoom for {}: {}", origin, e);
+    }
+}
 
-    let app_handle3 = app.clone();
-    let tab_id3 = id.clone();
-    let builder = builder.on_new_window(move |url, _features| {
-        let url_str = normalize_tab_url(&url);
-        let _ = app_handle3.emit(
-            "tab-open-request",
+/// Give a tab arriving at `url` the zoom remembered for its origin, or 100%
+/// when there is none. Pages without an origin keep the current zoom.
+/// Emits `tab-zoom-changed` when the factor changes.
+fn apply_site_zoom(app: &tauri::AppHandle, state: &mut TabState, tab_id: &str, url: &str) {
+    let Some(origin) = zoom_origin(url) else {
+        return;
+    };
+    let factor = settings::load()
+        .site_zoom
+        .get(&origin)
+        .copied()
+        .filter(|factor| factor.is_finite())
+        .map_or(1.0, |factor| factor.clamp(MIN_TAB_ZOOM, MAX_TAB_ZOOM));
+    let Some(tab) = state.tabs.get_mut(tab_id) else {
+        return;
+    };
+    if tab.zoom == factor {
+        return;
+    }
+    // A hibernated tab has no webview; it picks the zoom up when woken.
+    if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+        if let Err(e) = webview.set_zoom(factor) {
+            log::warn!("[Tabs] Failed to restore zoom for tab {}: {}", tab_id, e);
+            return;
+        }
+    }
+    tab.zoom = factor;
+    let _ = app.emit(
+        "tab-zoom-changed",
+        serde_json::json!({
+            "tabId": tab_id,
+            "factor": factor,
+        }),
+    );
+}
+
+pub fn get_tab_zoom(state: &TabState, tab_id: &str) -> Result<f64, String> {
+    state
+        .tabs
+        .get(tab_id)
+        .map(|tab| tab.zoom)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))
+}
+
+/// Step the active tab's zoom by `steps` increments, or reset it to 100%
+/// when `steps` is 0. Used by the View menu shortcuts.
+pub fn zoom_active_tab(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    steps: i32,
+) -> Result<f64, String> {
+    let tab_id = state.active_tab.clone().ok_or("No active tab")?;
+    let current = get_tab_zoom(state, &tab_id)?;
+    let factor = if steps == 0 {
+        1.0
+    } else {
+        // Round so repeated steps land on clean values like 1.1 rather than 1.0999.
+        ((current + TAB_ZOOM_STEP * f64::from(steps)) * 10.0).round() / 10.0
+    };
+    set_tab_zoom(app, state, &tab_id, factor)
+}
+
+/// Mute or unmute a tab's media elements. The flag is stored on the tab and
+/// reapplied whenever it loads a new document.
+pub fn set_tab_muted(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    muted: bool,
+) -> Result<(), String> {
+    let tab = state
+        .tabs
+        .get_mut(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    let webview = app
+        .get_webview(&format!("tab-{}", tab_id))
+        .ok_or_else(|| format!("Webview for tab {} not found", tab_id))?;
+    webview
+        .eval(set_muted_script(muted))
+        .map_err(|e| format!("Failed to set tab mute: {}", e))?;
+    tab.muted = muted;
+    Ok(())
+}
+
+/// Present tab `tab_id` with `user_agent`, which may also name a preset
+/// (`desktop`, `iphone`, `android`); `None` goes back to the per-OS default.
+/// A loaded page is reloaded so the server sees the change. Returns the user
+/// agent now in effect, `None` for the default.
+pub fn set_user_agent(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    tab_id: &str,
+    user_agent: Option<&str>,
+) -> Result<Option<String>, String> {
+    let user_agent = match user_agent.map(str::trim) {
+        Some("") => return Err("User agent is empty".to_string()),
+        Some(value) if value.chars().any(char::is_control) => {
+            return Err("User agent contains control characters".to_string());
+        }
+        Some(value) => Some(
+            USER_AGENT_PRESETS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(value))
+                .map_or(value, |(_, preset)| *preset)
+                .to_string(),
+        ),
+        None => None,
+    };
+    let tab = state
+        .tabs
+        .get_mut(tab_id)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+    // A hibernated tab picks the override up when its webview is re-created.
+    if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+        set_webview_user_agent(&webview, user_agent.as_deref().or(user_agent_override()))?;
+        if tab.url != "about:blank" && !tab.url.is_empty() {
+            let _ = webview.reload();
+        }
+    }
+    tab.user_agent = user_agent.clone();
+    Ok(user_agent)
+}
+
+/// Reject bounds that cannot describe a layout and clamp ones that overshoot
+/// the window (`max_width`/`max_height`, in chrome CSS pixels). Clamping emits
+/// `layout-warning` so a frontend reporting bad measurements, e.g. mid CSS
+/// transition, shows up instead of silently breaking the layout.
+fn validate_content_bounds(
+    app: &tauri::AppHandle,
+    bounds: ContentBounds,
+    max_width: f64,
+    max_height: f64,
+) -> Result<ContentBounds, String> {
+    let fields = [
+        ("left", bounds.left),
+        ("top", bounds.top),
+        ("width", bounds.width),
+        ("height", bounds.height),
+    ];
+    for (name, value) in fields {
+        if !value.is_finite() {
+            return Err(format!("Invalid content bounds: {} is not finite", name));
+        }
+        if value < 0.0 {
+            return Err(format!("Invalid content bounds: {} is negative ({})", name, value));
+        }
+    }
+
+    let clamped = ContentBounds {
+        left: bounds.left.min(max_width),
+        top: bounds.top.min(max_height),
+        width: bounds.width.min(max_width),
+        height: bounds.height.min(max_height),
+    };
+    if clamped != bounds {
+        log::warn!("[Tabs] clamped content bounds {:?} to {:?}", bounds, clamped);
+        let _ = app.emit(
+            "layout-warning",
             serde_json::json!({
-                "tabId": tab_id3,
-                "url": url_str,
-                "reason": "new-window",
+                "reason": "content-bounds-clamped",
+                "reported": bounds,
+                "applied": clamped,
             }),
         );
-        NewWindowResponse::Deny
+    }
+    Ok(clamped)
+}
+
+/// Logical-pixel gap between the window's inner height and the viewport
+/// height implied by the chrome's reported bounds.
+fn chrome_y_offset(inner_h_logical: f64, bounds: &ContentBounds, ui_zoom: f64) -> f64 {
+    let viewport_h = (bounds.top + bounds.height) * ui_zoom;
+    (inner_h_logical - viewport_h).max(0.0)
+}
+
+pub fn set_content_bounds(
+    app: &tauri::AppHandle,
+    state: &mut TabState,
+    bounds: ContentBounds,
+) -> Result<(), String> {
+    // Compute the title-bar y-offset from the JS data.
+    // JS viewport_height = bounds.top + bounds.height (the visible area).
+    // inner_size.height includes the title bar. The difference is the offset.
+    let window = app
+        .get_window(&state.window_label)
+        .ok_or_else(|| format!("Window {} not found", state.window_label))?;
+    let inner_size = window.inner_size().map_err(|e| e.to_string())?;
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    let inner_w_logical = inner_size.width as f64 / scale;
+    let inner_h_logical = inner_size.height as f64 / scale;
+    let bounds = validate_content_bounds(
+        app,
+        bounds,
+        inner_w_logical / state.ui_zoom,
+        inner_h_logical / state.ui_zoom,
+    )?;
+    let y_off = chrome_y_offset(inner_h_logical, &bounds, state.ui_zoom);
+
+    let first = state.content_bounds.is_none();
+    state.chrome_y_offset = y_off;
+    state.content_bounds = Some(bounds);
+
+    // The first report, and the first of each burst, apply right away; the
+    // rest of a burst is left to one trailing reposition, which picks up
+    // whatever bounds were stored last.
+    let throttle = Duration::from_millis(CONTENT_BOUNDS_THROTTLE_MS);
+    let now = Instant::now();
+    state.bounds_throttle.generation += 1;
+    let elapsed = state.bounds_throttle.applied_at.map(|at| now.duration_since(at));
+    match elapsed {
+        Some(elapsed) if !first && elapsed < throttle => {
+            let generation = state.bounds_throttle.generation;
+            let app = app.clone();
+            let label = state.window_label.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(throttle - elapsed).await;
+                let Ok(state) = window_state(&app, &label) else {
+                    return;
+                };
+                let snapshot = {
+                    let Ok(mut state) = state.lock() else {
+                        return;
+                    };
+                    if state.bounds_throttle.generation != generation {
+                        return;
+                    }
+                    state.bounds_throttle.applied_at = Some(Instant::now());
+                    state.layout_snapshot()
+                };
+                let _ = reposition_snapshot(&app, &snapshot, true);
+            });
+            Ok(())
+        }
+        _ => {
+            state.bounds_throttle.applied_at = Some(now);
+            reposition_webviews(app, state, true)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FaviconPayload {
+    tab_id: String,
+    url: Option<String>,
+}
+
+/// Register the listener that records `tab-favicon` reports on `TabInfo`.
+/// The state update runs off the listener so it never contends for the lock
+/// on the thread delivering the event.
+pub fn listen_for_favicons(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    app.listen("tab-favicon", move |event| {
+        let payload: FaviconPayload = match serde_json::from_str(event.payload()) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        let app = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let Ok(state) = tab_window_state(&app, &payload.tab_id) else {
+                return;
+            };
+            let Ok(mut state) = state.lock() else {
+                return;
+            };
+            if let Some(tab) = state.tabs.get_mut(&payload.tab_id) {
+                tab.favicon = payload.url;
+            }
+        });
+    });
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TitlePayload {
+    tab_id: String,
+    title: String,
+}
+
+/// Register the listener that records `tab-title-changed` reports on `TabInfo`.
+pub fn listen_for_titles(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    app.listen("tab-title-changed", move |event| {
+        let payload: TitlePayload = match serde_json::from_str(event.payload()) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        let app = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let Ok(state) = tab_window_state(&app, &payload.tab_id) else {
+                return;
+            };
+            let Ok(mut state) = state.lock() else {
+                return;
+            };
+            let Some(tab) = state.tabs.get_mut(&payload.tab_id) else {
+                return;
+            };
+            tab.title = payload.title;
+            let visit = (!tab.incognito).then(|| (tab.url.clone(), tab.title.clone()));
+            drop(state);
+            if let Some((url, title)) = visit {
+                history::update_title(&app, &url, &title);
+            }
+        });
+    });
+}
+
+/// Register the listener that forwards render snapshots from the debug capture
+/// script as `claw-debug` events, keeping only the latest snapshot of a tab
+/// when they arrive faster than every `RENDER_COALESCE_MS`.
+pub fn listen_for_debug_renders(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    let latest: Arc<Mutex<HashMap<String, Value>>> = Arc::new(Mutex::new(HashMap::new()));
+    app.listen("claw-debug-render", move |event| {
+        let payload: Value = match serde_json::from_str(event.payload()) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        let tab_id = payload
+            .get("tabId")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+        let flush_scheduled = match latest.lock() {
+            Ok(mut latest) => latest.insert(tab_id.clone(), payload).is_some(),
+            Err(_) => return,
+        };
+        if flush_scheduled {
+            return;
+        }
+        let app = app_handle.clone();
+        let latest = Arc::clone(&latest);
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(RENDER_COALESCE_MS)).await;
+            let payload = latest.lock().ok().and_then(|mut latest| latest.remove(&tab_id));
+            if let Some(payload) = payload {
+                let _ = app.emit("claw-debug", payload);
+            }
+        });
+    });
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AudioReadyPayload {
+    tab_id: String,
+}
+
+/// Register the listener that re-mutes a muted tab's new documents as soon as
+/// their audio script reports in.
+pub fn listen_for_audio_ready(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    app.listen("tab-audio-ready", move |event| {
+        let payload: AudioReadyPayload = match serde_json::from_str(event.payload()) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        let app = app_handle.clone();
+        tauri::async_runtime::spawn(async move {
+            let muted = match tab_window_state(&app, &payload.tab_id) {
+                Ok(state) => match state.lock() {
+                    Ok(state) => state.tabs.get(&payload.tab_id).is_some_and(|tab| tab.muted),
+                    Err(_) => false,
+                },
+                Err(_) => false,
+            };
+            if !muted {
+                return;
+            }
+            if let Some(webview) = app.get_webview(&format!("tab-{}", payload.tab_id)) {
+                let _ = webview.eval(set_muted_script(true));
+            }
+        });
+    });
+}
+
+/// Register the listener that resolves pending `eval_with_result` calls.
+pub fn listen_for_eval_results(app: &tauri::AppHandle) {
+    let app_handle = app.clone();
+    app.listen("claw-eval-result", move |event| {
+        let payload: EvalResultPayload = match serde_json::from_str(event.payload()) {
+            Ok(payload) => payload,
+            Err(_) => return,
+        };
+        let sender = match app_handle.state::<Mutex<EvalState>>().lock() {
+            Ok(mut state) => state.pending.remove(&payload.request_id),
+            Err(_) => None,
+        };
+        if let Some(sender) = sender {
+            let _ = sender.send(payload);
+        }
+    });
+}
+
+fn forget_pending_eval(app: &tauri::AppHandle, request_id: &str) {
+    if let Ok(mut state) = app.state::<Mutex<EvalState>>().lock() {
+        state.pending.remove(request_id);
+    }
+}
+
+/// Run `body` as an async function body in a tab and wait for its return value.
+/// Unlike `run_js_in_tab`, this resolves with whatever the script returns
+/// (promises are awaited) or the message of whatever it throws.
+pub async fn eval_with_result(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    body: &str,
+    timeout: Duration,
+) -> Result<Value, String> {
+    eval_payload(app, tab_id, body, timeout).await?.into_result()
+}
+
+/// Like `eval_with_result`, but hands back the script's full report. `Err` is
+/// only for failures to run it or hear back.
+async fn eval_payload(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    body: &str,
+    timeout: Duration,
+) -> Result<EvalResultPayload, String> {
+    let label = format!("tab-{}", tab_id);
+    let webview = app
+        .get_webview(&label)
+        .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let (sender, receiver) = oneshot::channel();
+    {
+        let eval_state = app.state::<Mutex<EvalState>>();
+        let mut eval_state = eval_state.lock().map_err(|e| e.to_string())?;
+        eval_state.pending.insert(request_id.clone(), sender);
+    }
+
+    let request_id_literal = serde_json::to_string(&request_id).map_err(|e| e.to_string())?;
+    let script = tab_script(EVAL_RESULT_SCRIPT, tab_id)
+        .replace("__REQUEST_ID__", &request_id_literal)
+        .replace("__BODY__", body);
+    if let Err(e) = webview.eval(script) {
+        forget_pending_eval(app, &request_id);
+        return Err(format!("JS execution failed: {}", e));
+    }
+
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(Ok(payload)) => Ok(payload),
+        Ok(Err(_)) => Err(format!("JS evaluation in tab {} was dropped", tab_id)),
+        Err(_) => {
+            forget_pending_eval(app, &request_id);
+            Err(format!(
+                "JS evaluation in tab {} timed out after {}ms",
+                tab_id,
+                timeout.as_millis()
+            ))
+        }
+    }
+}
+
+fn emit_find_result(app: &tauri::AppHandle, tab_id: &str, find: &FindState) {
+    let _ = app.emit(
+        "tab-find-result",
+        serde_json::json!({
+            "tabId": tab_id,
+            "query": find.query,
+            "matchCase": find.match_case,
+            "activeMatch": find.active_match,
+            "totalMatches": find.total_matches,
+        }),
+    );
+}
+
+/// Track whether a tab is mid-load and record the URL its main frame is
+/// loading, which after redirects differs from the requested one. Runs off
+/// the page-load callback like `reset_find_state`.
+fn record_page_load(app: &tauri::AppHandle, tab_id: &str, loading: bool, url: String) {
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let Ok(state) = tab_window_state(&app, &tab_id) else {
+            return;
+        };
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        let global_user_content = Arc::clone(&state.global_user_content);
+        let Some(tab) = state.tabs.get_mut(&tab_id) else {
+            return;
+        };
+        // Links followed in the page land on new origins without going
+        // through `navigate_tab`.
+        let new_origin_url = (loading && zoom_origin(&tab.url) != zoom_origin(&url)).then(|| url.clone());
+        tab.url = url;
+        if !loading {
+            if let Some(webview) = app.get_webview(&format!("tab-{}", tab_id)) {
+                if let Some(scroll) = tab.scroll.take() {
+                    let _ = webview.eval(format!(
+                        "window.scrollTo({{ left: {}, top: {}, behavior: 'instant' }});",
+                        scroll.x, scroll.y
+                    ));
+                }
+                if let Ok(global) = global_user_content.read() {
+                    apply_user_content(&webview, &global);
+                }
+                apply_user_content(&webview, &tab.user_content);
+            }
+            if tab.reader_mode {
+                let app = app.clone();
+                let tab_id = tab_id.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = set_reader_mode(&app, &tab_id, true).await {
+                        log::info!("[Tabs] Reader mode off for tab {}: {}", tab_id, e);
+                    }
+                });
+            }
+        }
+        let visit = (!loading && !tab.incognito).then(|| (tab.url.clone(), tab.title.clone()));
+        if loading {
+            if let Some(url) = new_origin_url {
+                apply_site_zoom(&app, &mut state, &tab_id, &url);
+            }
+            state.loading.insert(tab_id);
+        } else {
+            state.loading.remove(&tab_id);
+            state.loaded.insert(tab_id);
+        }
+        drop(state);
+        if let Some((url, title)) = visit {
+            history::record_visit(&app, &url, &title);
+        }
+    });
+}
+
+/// Record a same-document URL change, which never reaches `on_page_load`.
+fn set_tab_url(app: &tauri::AppHandle, tab_id: &str, url: String) {
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let Ok(state) = tab_window_state(&app, &tab_id) else {
+            return;
+        };
+        let Ok(mut state) = state.lock() else {
+            return;
+        };
+        if let Some(tab) = state.tabs.get_mut(&tab_id) {
+            tab.url = url;
+        }
+    });
+}
+
+/// Drop the find session of a tab whose document is being replaced.
+/// Runs off the navigation callback so it never contends for the state lock there.
+fn reset_find_state(app: &tauri::AppHandle, tab_id: &str) {
+    let app = app.clone();
+    let tab_id = tab_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        let Ok(state) = tab_window_state(&app, &tab_id) else {
+            return;
+        };
+        let removed = match state.lock() {
+            Ok(mut state) => state.find.remove(&tab_id).is_some(),
+            Err(_) => false,
+        };
+        if removed {
+            emit_find_result(&app, &tab_id, &FindState::default());
+        }
+    });
+}
+
+/// Highlight `query` in the page and select the match at `current` (1-based).
+/// Returns the updated find state with `active_match` wrapped into range.
+async fn run_find(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    query: &str,
+    match_case: bool,
+    current: usize,
+) -> Result<FindState, String> {
+    let query_literal = serde_json::to_string(query).map_err(|e| e.to_string())?;
+    let index = current.saturating_sub(1);
+    let script = FIND_SCRIPT
+        .replace("__QUERY__", &query_literal)
+        .replace("__INDEX__", &index.to_string())
+        .replace("__MATCH_CASE__", if match_case { "true" } else { "false" });
+    let result = eval_with_result(app, tab_id, &script, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    let total = result.get("total").and_then(Value::as_u64).unwrap_or(0) as usize;
+
+    let find = FindState {
+        query: query.to_string(),
+        match_case,
+        active_match: if total == 0 { 0 } else { index % total + 1 },
+        total_matches: total,
+    };
+
+    {
+        let state = tab_window_state(app, tab_id)?;
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        if !state.tabs.contains_key(tab_id) {
+            return Err(format!("Tab {} not found", tab_id));
+        }
+        state.find.insert(tab_id.to_string(), find.clone());
+    }
+    emit_find_result(app, tab_id, &find);
+    Ok(find)
+}
+
+fn current_find(app: &tauri::AppHandle, tab_id: &str) -> Result<Option<FindState>, String> {
+    let state = tab_window_state(app, tab_id)?;
+    let state = state.lock().map_err(|e| e.to_string())?;
+    if !state.tabs.contains_key(tab_id) {
+        return Err(format!("Tab {} not found", tab_id));
+    }
+    Ok(state.find.get(tab_id).cloned())
+}
+
+/// Select the match after (or before) the current one, wrapping at either end.
+async fn step_find(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    find: FindState,
+    forward: bool,
+) -> Result<FindState, String> {
+    if find.total_matches == 0 {
+        return Ok(find);
+    }
+    let current = if forward {
+        find.active_match % find.total_matches + 1
+    } else if find.active_match <= 1 {
+        find.total_matches
+    } else {
+        find.active_match - 1
+    };
+    run_find(app, tab_id, &find.query, find.match_case, current).await
+}
+
+/// Search a tab for `query`, highlighting all matches. Repeating the active
+/// query advances in the given direction instead of restarting; a new query
+/// selects the first match.
+pub async fn find_in_page(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    query: &str,
+    forward: bool,
+    match_case: bool,
+) -> Result<FindState, String> {
+    match current_find(app, tab_id)? {
+        Some(find) if find.query == query && find.match_case == match_case && find.total_matches > 0 => {
+            step_find(app, tab_id, find, forward).await
+        }
+        _ => run_find(app, tab_id, query, match_case, 1).await,
+    }
+}
+
+/// Move to the next match, wrapping past the last one.
+/// A no-op returning an empty state when no find session is active.
+pub async fn find_next(app: &tauri::AppHandle, tab_id: &str) -> Result<FindState, String> {
+    match current_find(app, tab_id)? {
+        Some(find) => step_find(app, tab_id, find, true).await,
+        None => Ok(FindState::default()),
+    }
+}
+
+/// Move to the previous match, wrapping past the first one.
+/// A no-op returning an empty state when no find session is active.
+pub async fn find_prev(app: &tauri::AppHandle, tab_id: &str) -> Result<FindState, String> {
+    match current_find(app, tab_id)? {
+        Some(find) => step_find(app, tab_id, find, false).await,
+        None => Ok(FindState::default()),
+    }
+}
+
+/// End the find session in a tab and remove its highlights.
+pub async fn find_stop(app: &tauri::AppHandle, tab_id: &str) -> Result<(), String> {
+    current_find(app, tab_id)?;
+    let script = FIND_SCRIPT
+        .replace("__QUERY__", "null")
+        .replace("__INDEX__", "0")
+        .replace("__MATCH_CASE__", "false");
+    eval_with_result(app, tab_id, &script, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    {
+        let state = tab_window_state(app, tab_id)?;
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        state.find.remove(tab_id);
+    }
+    emit_find_result(app, tab_id, &FindState::default());
+    Ok(())
+}
+
+/// Count the words in a tab's main content and estimate reading time at
+/// `wpm` words per minute (defaults to `DEFAULT_READING_WPM`).
+pub async fn get_tab_reading_stats(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    wpm: Option<u32>,
+) -> Result<ReadingStats, String> {
+    let wpm = wpm.filter(|w| *w > 0).unwrap_or(DEFAULT_READING_WPM) as usize;
+    let word_count = extract_readable(app, tab_id).await?.word_count;
+    let estimated_minutes = word_count.div_ceil(wpm) as u32;
+    Ok(ReadingStats {
+        word_count,
+        estimated_minutes,
+    })
+}
+
+/// Approximate memory and DOM size of a tab.
+pub async fn tab_resource_usage(app: &tauri::AppHandle, tab_id: &str) -> Result<TabResourceUsage, String> {
+    let value = eval_with_result(
+        app,
+        tab_id,
+        RESOURCE_USAGE_SCRIPT,
+        Duration::from_millis(EVAL_TIMEOUT_MS),
+    )
+    .await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected resource usage: {}", e))
+}
+
+/// Emit `tab-resource-update` for every loaded tab in every window on a slow
+/// interval, so the UI can flag heavy tabs without polling.
+pub fn start_resource_monitor(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_millis(RESOURCE_POLL_INTERVAL_MS)).await;
+            let mut tab_ids = Vec::new();
+            for state in all_window_states(&app).unwrap_or_default() {
+                if let Ok(state) = state.lock() {
+                    tab_ids.extend(
+                        state
+                            .tabs
+                            .keys()
+                            .filter(|id| state.loaded.contains(*id) && !state.loading.contains(*id))
+                            .cloned(),
+                    );
+                }
+            }
+            for tab_id in tab_ids {
+                let Ok(usage) = tab_resource_usage(&app, &tab_id).await else {
+                    continue;
+                };
+                let _ = app.emit(
+                    "tab-resource-update",
+                    serde_json::json!({
+                        "tabId": tab_id,
+                        "usage": usage,
+                    }),
+                );
+            }
+        }
     });
+}
+
+/// Extract the page's main article (title, byline, text and excerpt) the way
+/// reader modes do, falling back to the whole body's visible text.
+pub async fn extract_readable(app: &tauri::AppHandle, tab_id: &str) -> Result<ReadableContent, String> {
+    let body = READABLE_SCRIPT.replace("__MAX_CHARS__", &MAX_READABLE_CHARS.to_string());
+    let value = eval_with_result(app, tab_id, &body, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected readable content: {}", e))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReaderModeResult {
+    reader_mode: bool,
+}
+
+/// Show the tab's page as a clean article, or restore the original page.
+/// Fails, leaving reader mode off, when the page has no extractable article.
+/// Emits `tab-reader-mode-changed`.
+async fn set_reader_mode(app: &tauri::AppHandle, tab_id: &str, enable: bool) -> Result<bool, String> {
+    let content = if enable {
+        let content = extract_readable(app, tab_id).await;
+        match content {
+            Ok(content) if content.length >= MIN_READER_MODE_CHARS => Some(content),
+            Ok(_) => None,
+            Err(e) => {
+                record_reader_mode(app, tab_id, false);
+                return Err(e);
+            }
+        }
+    } else {
+        None
+    };
+    if enable && content.is_none() {
+        record_reader_mode(app, tab_id, false);
+        return Err("No readable article on this page".to_string());
+    }
+
+    let content_literal = serde_json::to_string(&content).map_err(|e| e.to_string())?;
+    let body = READER_MODE_SCRIPT
+        .replace("__ENABLE__", if enable { "true" } else { "false" })
+        .replace("__CONTENT__", &content_literal);
+    let value = eval_with_result(app, tab_id, &body, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    let result: ReaderModeResult =
+        serde_json::from_value(value).map_err(|e| format!("Unexpected reader mode result: {}", e))?;
+    record_reader_mode(app, tab_id, result.reader_mode);
+    Ok(result.reader_mode)
+}
+
+/// Store the tab's reader-mode flag and emit `tab-reader-mode-changed` if it changed.
+fn record_reader_mode(app: &tauri::AppHandle, tab_id: &str, enabled: bool) {
+    let Ok(state) = tab_window_state(app, tab_id) else {
+        return;
+    };
+    let Ok(mut state) = state.lock() else {
+        return;
+    };
+    let Some(tab) = state.tabs.get_mut(tab_id) else {
+        return;
+    };
+    if tab.reader_mode == enabled {
+        return;
+    }
+    tab.reader_mode = enabled;
+    let _ = app.emit(
+        "tab-reader-mode-changed",
+        serde_json::json!({ "tabId": tab_id, "enabled": enabled }),
+    );
+}
 
-    // Hide all existing content webviews and move off-screen
-    let offscreen = tauri::Rect {
-        position: PhysicalPosition::new(-10000_i32, -10000_i32).into(),
-        size: PhysicalSize::new(0_u32, 0_u32).into(),
+/// Turn reader mode on or off for a tab. Returns whether it is now on.
+pub async fn toggle_reader_mode(app: &tauri::AppHandle, tab_id: &str) -> Result<bool, String> {
+    let enabled = {
+        let state = tab_window_state(app, tab_id)?;
+        let state = state.lock().map_err(|e| e.to_string())?;
+        state
+            .tabs
+            .get(tab_id)
+            .ok_or_else(|| format!("Tab {} not found", tab_id))?
+            .reader_mode
     };
-    for existing_id in state.tabs.keys() {
-        let existing_label = format!("tab-{}", existing_id);
-        if let Some(webview) = app.get_webview(&existing_label) {
-            let _ = webview.set_bounds(offscreen);
-            let _ = webview.hide();
-        }
+    set_reader_mode(app, tab_id, !enabled).await
+}
+
+/// Read a trusted automation script from `<workspace>/scripts` and run it in a
+/// tab. The file is evaluated as an async function body, so it can `await`
+/// and `return` a JSON-serializable result.
+pub async fn run_js_file_in_tab(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    relative_path: &str,
+) -> Result<Value, String> {
+    if !relative_path.ends_with(".js") {
+        return Err(format!("Script {} is not a .js file", relative_path));
     }
+    let scripts_dir = workspace::workspace_dir()
+        .ok_or("Workspace directory not found")?
+        .join("scripts");
+    let path = workspace::resolve_within(&scripts_dir, relative_path)?;
+    let code = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read script {}: {}", relative_path, e))?;
+    eval_with_result(app, tab_id, &code, Duration::from_millis(EVAL_TIMEOUT_MS)).await
+}
 
-    // Add the new webview as a child of the main window
-    let webview = window
-        .add_child(builder, position, size)
-        .map_err(|e| format!("Failed to create webview: {}", e))?;
+/// Run `ELEMENT_ACTION_SCRIPT` for `selector`, clicking the element when
+/// `click` is set, and return its viewport rect.
+async fn element_action(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    selector: &str,
+    click: bool,
+) -> Result<ElementRect, String> {
+    let selector_literal = serde_json::to_string(selector).map_err(|e| e.to_string())?;
+    let body = ELEMENT_ACTION_SCRIPT
+        .replace("__SELECTOR__", &selector_literal)
+        .replace("__CLICK__", if click { "true" } else { "false" });
+    let value = eval_with_result(app, tab_id, &body, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected element rect: {}", e))
+}
 
-    // Immediately disable auto-resize before anything else can override our position
-    let _ = webview.set_auto_resize(false);
+/// Read a tab's scroll offset and extent.
+pub async fn get_scroll(app: &tauri::AppHandle, tab_id: &str) -> Result<TabScroll, String> {
+    let value = eval_with_result(app, tab_id, GET_SCROLL_SCRIPT, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected scroll position: {}", e))
+}
 
-    // Check bounds right after add_child
-    apply_bounds(&window, &webview, state)?;
-    devtools::watch_webview_devtools(app.clone(), label.clone());
+/// Scroll a tab's document to (`x`, `y`), clamped to the document, smoothly
+/// when `smooth` is set. Returns the position reached.
+pub async fn set_scroll(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    x: f64,
+    y: f64,
+    smooth: bool,
+) -> Result<TabScroll, String> {
+    if !x.is_finite() || !y.is_finite() {
+        return Err(format!("Invalid scroll position: {}, {}", x, y));
+    }
+    let body = SET_SCROLL_SCRIPT
+        .replace("__SMOOTH__", if smooth { "true" } else { "false" })
+        .replace("__SMOOTH_WAIT_MS__", &SMOOTH_SCROLL_WAIT_MS.to_string())
+        .replace("__X__", &x.to_string())
+        .replace("__Y__", &y.to_string());
+    let timeout = Duration::from_millis(EVAL_TIMEOUT_MS + SMOOTH_SCROLL_WAIT_MS);
+    let value = eval_with_result(app, tab_id, &body, timeout).await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected scroll position: {}", e))
+}
 
-    let _ = webview.set_focus();
+/// Scroll the first element matching `selector` to the middle of the
+/// viewport. Returns its rect afterwards; errors when nothing matches.
+pub async fn scroll_to_element(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    selector: &str,
+) -> Result<ElementRect, String> {
+    element_action(app, tab_id, selector, false).await
+}
 
-    state.tabs.insert(
-        id.clone(),
-        TabInfo {
-            id: id.clone(),
-            url: url.to_string(),
-            title: String::from("New Tab"),
-        },
-    );
-    state.active_tab = Some(id.clone());
+/// Scroll the first element matching `selector` into view and click its
+/// center with a pointerdown/mousedown/pointerup/mouseup/click sequence.
+/// Returns the clicked element's rect; errors when nothing matches.
+pub async fn click_element(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    selector: &str,
+) -> Result<ElementRect, String> {
+    element_action(app, tab_id, selector, true).await
+}
 
-    Ok(id)
+/// Wait until `selector` matches an element (and, with `visible`, until it is
+/// rendered) for up to `timeout_ms`. Returns the element's rect.
+pub async fn wait_for_selector(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    selector: &str,
+    timeout_ms: Option<u64>,
+    visible: bool,
+) -> Result<ElementRect, String> {
+    let timeout_ms = timeout_ms
+        .unwrap_or(WAIT_FOR_SELECTOR_TIMEOUT_MS)
+        .min(MAX_WAIT_FOR_SELECTOR_TIMEOUT_MS);
+    let selector_literal = serde_json::to_string(selector).map_err(|e| e.to_string())?;
+    let body = WAIT_FOR_SELECTOR_SCRIPT
+        .replace("__SELECTOR__", &selector_literal)
+        .replace("__VISIBLE__", if visible { "true" } else { "false" })
+        .replace("__TIMEOUT_MS__", &timeout_ms.to_string());
+    let timeout = Duration::from_millis(timeout_ms + WAIT_FOR_SELECTOR_GRACE_MS);
+    let value = eval_with_result(app, tab_id, &body, timeout).await?;
+    serde_json::from_value(value).map_err(|e| format!("Unexpected element rect: {}", e))
 }
 
-/// Close a content webview tab.
-/// Returns the new active tab ID (if any) so the frontend can stay in sync.
-pub fn close_tab(
+/// Wait until none of the page's `fetch`/XHR requests has been in flight for
+/// `idle_ms`, for up to `timeout_ms`. Returns how long it waited. A page that
+/// navigates away mid-wait fails with the eval timeout.
+pub async fn wait_for_network_idle(
     app: &tauri::AppHandle,
-    state: &mut TabState,
     tab_id: &str,
-) -> Result<Option<String>, String> {
-    let label = format!("tab-{}", tab_id);
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.close();
-    }
+    idle_ms: Option<u64>,
+    timeout_ms: Option<u64>,
+) -> Result<u64, String> {
+    let timeout_ms = timeout_ms
+        .unwrap_or(WAIT_FOR_LOAD_TIMEOUT_MS)
+        .min(MAX_WAIT_FOR_LOAD_TIMEOUT_MS);
+    let body = WAIT_FOR_NETWORK_IDLE_SCRIPT
+        .replace("__IDLE_MS__", &idle_ms.unwrap_or(NETWORK_IDLE_MS).to_string())
+        .replace("__TIMEOUT_MS__", &timeout_ms.to_string());
+    let timeout = Duration::from_millis(timeout_ms + WAIT_FOR_SELECTOR_GRACE_MS);
+    let value = eval_with_result(app, tab_id, &body, timeout).await?;
+    value
+        .as_u64()
+        .ok_or_else(|| format!("Unexpected network idle result: {}", value))
+}
 
-    state.tabs.remove(tab_id);
+/// Wait until the tab's document has been parsed, for up to `timeout_ms`.
+/// Returns at once when it already has.
+pub async fn wait_for_dom_content_loaded(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    timeout_ms: Option<u64>,
+) -> Result<(), String> {
+    let timeout_ms = timeout_ms
+        .unwrap_or(WAIT_FOR_LOAD_TIMEOUT_MS)
+        .min(MAX_WAIT_FOR_LOAD_TIMEOUT_MS);
+    let body = WAIT_FOR_DOM_CONTENT_LOADED_SCRIPT.replace("__TIMEOUT_MS__", &timeout_ms.to_string());
+    let timeout = Duration::from_millis(timeout_ms + WAIT_FOR_SELECTOR_GRACE_MS);
+    eval_with_result(app, tab_id, &body, timeout).await?;
+    Ok(())
+}
 
-    if state.active_tab.as_deref() == Some(tab_id) {
-        // Activate the next available tab
-        state.active_tab = state.tabs.keys().next().cloned();
-        if let Some(ref new_active) = state.active_tab {
-            let new_label = format!("tab-{}", new_active);
-            if let Some(webview) = app.get_webview(&new_label) {
-                if let Some(window) = app.get_window("main") {
-                    let _ = apply_bounds(&window, &webview, state);
+/// Fill the input, textarea, `<select>` or contenteditable element matching
+/// `selector` with `text`, firing the events frameworks listen for. With
+/// `simulate_typing`, key events are sent per character for sites that need
+/// them. Returns the element's resulting value so callers can verify it.
+pub async fn fill_input(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    selector: &str,
+    text: &str,
+    simulate_typing: bool,
+) -> Result<String, String> {
+    let selector_literal = serde_json::to_string(selector).map_err(|e| e.to_string())?;
+    let text_literal = serde_json::to_string(text).map_err(|e| e.to_string())?;
+    let body = FILL_INPUT_SCRIPT
+        .replace("__SELECTOR__", &selector_literal)
+        .replace("__TEXT__", &text_literal)
+        .replace("__SIMULATE_TYPING__", if simulate_typing { "true" } else { "false" });
+    let value = eval_with_result(app, tab_id, &body, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Unexpected input value".to_string())
+}
+
+/// Scroll a tab without animation and return the vertical offset it landed on.
+async fn scroll_tab_to(app: &tauri::AppHandle, tab_id: &str, x: f64, y: f64) -> Result<f64, String> {
+    let body = format!(
+        "window.scrollTo({{ left: {}, top: {}, behavior: 'instant' }});\n  return window.scrollY;",
+        x, y
+    );
+    let value = eval_with_result(app, tab_id, &body, Duration::from_millis(EVAL_TIMEOUT_MS)).await?;
+    value.as_f64().ok_or_else(|| "Unexpected scroll position".to_string())
+}
+
+/// Capture the whole document by scrolling one viewport at a time and stitching.
+async fn capture_full_page(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    webview: &Webview,
+) -> Result<Vec<u8>, String> {
+    let metrics: ScrollMetrics = serde_json::from_value(
+        eval_with_result(app, tab_id, SCROLL_METRICS_SCRIPT, Duration::from_millis(EVAL_TIMEOUT_MS)).await?,
+    )
+    .map_err(|e| format!("Unexpected scroll metrics: {}", e))?;
+    if metrics.scroll_height <= metrics.viewport_height || metrics.viewport_height <= 0.0 {
+        return capture::capture_webview_png(webview, None).await;
+    }
+
+    let mut segments = Vec::new();
+    let mut result = Ok(());
+    let mut target = 0.0;
+    // Device pixels per CSS pixel, known once the first segment is in.
+    let mut scale = None;
+    loop {
+        let segment = async {
+            let offset = scroll_tab_to(app, tab_id, metrics.x, target).await?;
+            tokio::time::sleep(Duration::from_millis(SCROLL_SETTLE_MS)).await;
+            Ok::<_, String>((offset, capture::capture_webview_png(webview, None).await?))
+        };
+        match segment.await {
+            Ok((offset, png)) => {
+                if scale.is_none() {
+                    match capture::png_height(&png) {
+                        Ok(height) => scale = Some(height as f64 / metrics.viewport_height),
+                        Err(e) => {
+                            result = Err(e);
+                            break;
+                        }
+                    }
                 }
-                let _ = webview.show();
-                let _ = webview.set_focus();
+                let previous = segments.last().map(|(prev, _)| *prev);
+                segments.push((offset, png));
+                target = offset + metrics.viewport_height;
+                // Stop at the bottom, if the page refused to scroll further,
+                // or once the next segment would start past the height cap.
+                if target >= metrics.scroll_height
+                    || previous.is_some_and(|prev| offset <= prev)
+                    || target * scale.unwrap_or(1.0) >= MAX_FULL_PAGE_HEIGHT_PX as f64
+                {
+                    break;
+                }
+            }
+            Err(e) => {
+                result = Err(e);
+                break;
             }
         }
     }
 
-    Ok(state.active_tab.clone())
+    // Put the reader back where they were even if a segment failed.
+    let _ = scroll_tab_to(app, tab_id, metrics.x, metrics.y).await;
+    result?;
+    capture::stitch_segments(
+        &segments,
+        metrics.scroll_height,
+        metrics.viewport_height,
+        MAX_FULL_PAGE_HEIGHT_PX,
+    )
 }
 
-/// Switch to a tab -- show the target webview, hide all others.
-pub fn switch_tab(
+/// Capture a tab as base64-encoded PNG: the visible viewport, or with
+/// `full_page` the whole document, scrolled and stitched.
+pub async fn capture_tab_screenshot(
     app: &tauri::AppHandle,
-    state: &mut TabState,
     tab_id: &str,
-) -> Result<(), String> {
-    if !state.tabs.contains_key(tab_id) {
-        return Err(format!("Tab {} not found", tab_id));
+    full_page: bool,
+) -> Result<String, String> {
+    {
+        let state = tab_window_state(app, tab_id)?;
+        let state = state.lock().map_err(|e| e.to_string())?;
+        if !state.tabs.contains_key(tab_id) {
+            return Err(format!("Tab {} not found", tab_id));
+        }
+        if !state.loaded.contains(tab_id) {
+            return Err(format!("Tab {} has not finished loading", tab_id));
+        }
+    }
+    let webview = tab_webview(app, tab_id)?;
+    let png = if full_page {
+        capture_full_page(app, tab_id, &webview).await?
+    } else {
+        capture::capture_webview_png(&webview, None).await?
+    };
+    Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+/// A file name for a PDF of the page titled `title`.
+fn pdf_file_name(title: &str) -> String {
+    let stem: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_') { c } else { '_' })
+        .collect();
+    let stem = stem.trim();
+    if stem.is_empty() {
+        "page.pdf".to_string()
+    } else {
+        format!("{}.pdf", stem)
     }
+}
 
-    let offscreen = tauri::Rect {
-        position: PhysicalPosition::new(-10000_i32, -10000_i32).into(),
-        size: PhysicalSize::new(0_u32, 0_u32).into(),
+/// Render a tab to a PDF at `path` (absolute), or under the workspace
+/// `downloads/` directory named after the page title. Returns the file path.
+pub async fn print_tab_to_pdf(
+    app: &tauri::AppHandle,
+    tab_id: &str,
+    path: Option<String>,
+    options: capture::PdfOptions,
+) -> Result<String, String> {
+    if let Some(paper_size) = options.paper_size.as_deref() {
+        if !capture::PDF_PAPER_SIZES.contains(&paper_size) {
+            return Err(format!("Unsupported paper size: {}", paper_size));
+        }
+    }
+    let title = {
+        let state = tab_window_state(app, tab_id)?;
+        let state = state.lock().map_err(|e| e.to_string())?;
+        let tab = state
+            .tabs
+            .get(tab_id)
+            .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+        if !state.loaded.contains(tab_id) {
+            return Err(format!("Tab {} has not finished loading", tab_id));
+        }
+        tab.title.clone()
     };
 
-    // Hide all content webviews and move off-screen
-    for existing_id in state.tabs.keys() {
-        if existing_id == tab_id {
-            continue;
+    let path = match path {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                return Err(format!("PDF path must be absolute: {}", path.display()));
+            }
+            if !path.parent().is_some_and(|parent| parent.is_dir()) {
+                return Err(format!("Directory for {} does not exist", path.display()));
+            }
+            path
         }
-        let label = format!("tab-{}", existing_id);
-        if let Some(webview) = app.get_webview(&label) {
-            let _ = webview.set_bounds(offscreen);
-            let _ = webview.hide();
+        None => {
+            let dir = workspace::workspace_dir()
+                .ok_or("Workspace directory not found")?
+                .join("downloads");
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            downloads::unique_path(&dir, &pdf_file_name(&title))
         }
-    }
+    };
 
-    // Show the target webview
-    let label = format!("tab-{}", tab_id);
-    if let Some(webview) = app.get_webview(&label) {
-        if let Some(window) = app.get_window("main") {
-            let _ = apply_bounds(&window, &webview, state);
+    let webview = tab_webview(app, tab_id)?;
+    capture::render_webview_pdf(&webview, &path, &options).await?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Document-start scripts to bake into the tab's webview when it is built:
+/// the global ones plus, for a woken tab, its own.
+fn document_start_scripts(state: &TabState, tab_id: &str) -> Vec<UserScript> {
+    let global = state
+        .global_user_content
+        .read()
+        .map(|content| content.scripts.clone())
+        .unwrap_or_default();
+    let own = state
+        .tabs
+        .get(tab_id)
+        .map(|tab| tab.user_content.scripts.clone())
+        .unwrap_or_default();
+    global
+        .into_iter()
+        .chain(own)
+        .filter(|script| script.run_at == ScriptRunAt::DocumentStart)
+        .collect()
+}
+
+/// Apply styles and document-end scripts to a freshly loaded document.
+/// Document-start scripts have already run natively.
+fn apply_user_content(webview: &Webview, content: &UserContent) {
+    for style in &content.styles {
+        let _ = webview.eval(user_style_script(style));
+    }
+    for script in &content.scripts {
+        if script.run_at == ScriptRunAt::DocumentEnd {
+            let _ = webview.eval(user_script_source(script));
         }
-        let _ = webview.show();
-        let _ = webview.set_focus();
     }
+}
 
-    state.active_tab = Some(tab_id.to_string());
-    Ok(())
+/// Register a document-start script on an existing webview, for every
+/// document it loads from now on.
+#[cfg(target_os = "macos")]
+fn add_document_start_script(webview: &Webview, source: String) -> Result<(), String> {
+    use objc2::MainThreadMarker;
+    use objc2_foundation::NSString;
+    use objc2_web_kit::{WKUserScript, WKUserScriptInjectionTime, WKWebView};
+
+    webview
+        .with_webview(move |platform| unsafe {
+            let Some(mtm) = MainThreadMarker::new() else {
+                return;
+            };
+            let view: &WKWebView = &*platform.inner().cast();
+            let script = WKUserScript::initWithSource_injectionTime_forMainFrameOnly(
+                WKUserScript::alloc(mtm),
+                &NSString::from_str(&source),
+                WKUserScriptInjectionTime::AtDocumentStart,
+                true,
+            );
+            view.configuration().userContentController().addUserScript(&script);
+        })
+        .map_err(|e| format!("Failed to add user script: {}", e))
 }
 
-/// Hide all content webviews without changing the active tab state.
-/// Moves each webview far off-screen so it cannot intercept pointer events
-/// even if the native layer remains in the window hierarchy.
-pub fn hide_all_tabs(
-    app: &tauri::AppHandle,
-    state: &TabState,
-) -> Result<(), String> {
-    let offscreen = tauri::Rect {
-        position: PhysicalPosition::new(-10000_i32, -10000_i32).into(),
-        size: PhysicalSize::new(0_u32, 0_u32).into(),
+/// Register a document-start script on an existing webview, for every
+/// document it loads from now on.
+#[cfg(target_os = "linux")]
+fn add_document_start_script(webview: &Webview, source: String) -> Result<(), String> {
+    use webkit2gtk::{UserContentInjectedFrames, UserContentManagerExt, UserScriptInjectionTime, WebViewExt};
+
+    webview
+        .with_webview(move |platform| {
+            let Some(manager) = platform.inner().user_content_manager() else {
+                log::error!("[Tabs] Webview has no user content manager");
+                return;
+            };
+            let script = webkit2gtk::UserScript::new(
+                &source,
+                UserContentInjectedFrames::TopFrame,
+                UserScriptInjectionTime::Start,
+                &[],
+                &[],
+            );
+            manager.add_script(&script);
+        })
+        .map_err(|e| format!("Failed to add user script: {}", e))
+}
+
+/// WebView2 needs `AddScriptToExecuteOnDocumentCreated`, which isn't reachable
+/// without the COM bindings.
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+fn add_document_start_script(_webview: &Webview, _source: String) -> Result<(), String> {
+    Err("Document-start user scripts are not supported on this platform".to_string())
+}
+
+/// Webviews of every tab that isn't hibernated, across all windows.
+fn open_tab_webviews(app: &tauri::AppHandle) -> Result<Vec<Webview>, String> {
+    let mut webviews = Vec::new();
+    for state in all_window_states(app)? {
+        let state = state.lock().map_err(|e| e.to_string())?;
+        webviews.extend(
+            state
+                .tabs
+                .keys()
+                .filter_map(|id| app.get_webview(&format!("tab-{}", id))),
+        );
+    }
+    Ok(webviews)
+}
+
+fn global_user_content(app: &tauri::AppHandle) -> Result<Arc<RwLock<UserContent>>, String> {
+    let windows = app.state::<Mutex<WindowTabs>>();
+    let windows = windows.lock().map_err(|e| e.to_string())?;
+    Ok(Arc::clone(&windows.global_user_content))
+}
+
+/// Inject `css` into one tab, or with no `tab_id` into every current and
+/// future tab. It is reapplied after each navigation. Returns the style id
+/// for `remove_user_style`.
+pub fn add_user_style(app: &tauri::AppHandle, tab_id: Option<&str>, css: String) -> Result<String, String> {
+    let style = UserStyle {
+        id: uuid::Uuid::new_v4().to_string(),
+        css,
     };
-    for existing_id in state.tabs.keys() {
-        let label = format!("tab-{}", existing_id);
-        if let Some(webview) = app.get_webview(&label) {
-            let _ = webview.set_bounds(offscreen);
-            let _ = webview.hide();
+    let script = user_style_script(&style);
+    let id = style.id.clone();
+    match tab_id {
+        Some(tab_id) => {
+            let state = tab_window_state(app, tab_id)?;
+            let mut state = state.lock().map_err(|e| e.to_string())?;
+            let tab = state
+                .tabs
+                .get_mut(tab_id)
+                .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+            tab_webview(app, tab_id)?
+                .eval(&script)
+                .map_err(|e| format!("Failed to add user style: {}", e))?;
+            tab.user_content.styles.push(style);
+        }
+        None => {
+            global_user_content(app)?
+                .write()
+                .map_err(|e| e.to_string())?
+                .styles
+                .push(style);
+            for webview in open_tab_webviews(app)? {
+                let _ = webview.eval(&script);
+            }
         }
     }
-    Ok(())
+    Ok(id)
 }
 
-/// Navigate a content webview to a new URL.
-pub fn navigate_tab(
-    app: &tauri::AppHandle,
-    state: &mut TabState,
-    tab_id: &str,
-    url: &str,
-) -> Result<(), String> {
-    if let Some(tab) = state.tabs.get_mut(tab_id) {
-        tab.url = url.to_string();
-    } else {
-        return Err(format!("Tab {} not found", tab_id));
+/// Remove a style added with `add_user_style` from the tabs it applies to.
+pub fn remove_user_style(app: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    let script = remove_user_style_script(id);
+    let removed_global = {
+        let shared = global_user_content(app)?;
+        let mut global = shared.write().map_err(|e| e.to_string())?;
+        let before = global.styles.len();
+        global.styles.retain(|style| style.id != id);
+        global.styles.len() != before
+    };
+    if removed_global {
+        for webview in open_tab_webviews(app)? {
+            let _ = webview.eval(&script);
+        }
+        return Ok(());
     }
 
-    let label = format!("tab-{}", tab_id);
-    if let Some(webview) = app.get_webview(&label) {
-        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
-        webview
-            .navigate(parsed)
-            .map_err(|e| format!("Navigation failed: {}", e))?;
+    for state in all_window_states(app)? {
+        let mut state = state.lock().map_err(|e| e.to_string())?;
+        let owner = state
+            .tabs
+            .values_mut()
+            .find(|tab| tab.user_content.styles.iter().any(|style| style.id == id));
+        if let Some(tab) = owner {
+            tab.user_content.styles.retain(|style| style.id != id);
+            if let Some(webview) = app.get_webview(&format!("tab-{}", tab.id)) {
+                let _ = webview.eval(&script);
+            }
+            return Ok(());
+        }
     }
-
-    Ok(())
+    Err(format!("User style {} not found", id))
 }
 
-/// Execute JavaScript in a content webview (agent DOM access).
-/// Uses the standard Tauri webview JS execution API -- the intended mechanism
-/// for trusted agent code to interact with page content (form filling, extraction, etc.).
-/// Only callable from the trusted sidecar process, never from untrusted user input.
-pub fn run_js_in_tab(
+/// Run `js` in one tab, or with no `tab_id` in every current and future tab,
+/// on each document it loads. `DocumentEnd` scripts also run right away;
+/// `DocumentStart` ones are registered natively and first run on the next
+/// load. Returns the script id.
+pub fn add_user_script(
     app: &tauri::AppHandle,
-    tab_id: &str,
-    code: &str,
-) -> Result<(), String> {
-    let label = format!("tab-{}", tab_id);
-    if let Some(webview) = app.get_webview(&label) {
-        webview
-            .eval(code)
-            .map_err(|e| format!("JS execution failed: {}", e))?;
-        Ok(())
-    } else {
-        Err(format!("Tab {} not found", tab_id))
+    tab_id: Option<&str>,
+    js: String,
+    run_at: ScriptRunAt,
+) -> Result<String, String> {
+    let script = UserScript {
+        id: uuid::Uuid::new_v4().to_string(),
+        js,
+        run_at,
+    };
+    let source = user_script_source(&script);
+    let id = script.id.clone();
+    let apply = |webview: &Webview| match run_at {
+        ScriptRunAt::DocumentStart => add_document_start_script(webview, source.clone()),
+        ScriptRunAt::DocumentEnd => webview
+            .eval(&source)
+            .map_err(|e| format!("Failed to run user script: {}", e)),
+    };
+    match tab_id {
+        Some(tab_id) => {
+            let state = tab_window_state(app, tab_id)?;
+            let mut state = state.lock().map_err(|e| e.to_string())?;
+            let tab = state
+                .tabs
+                .get_mut(tab_id)
+                .ok_or_else(|| format!("Tab {} not found", tab_id))?;
+            apply(&tab_webview(app, tab_id)?)?;
+            tab.user_content.scripts.push(script);
+        }
+        None => {
+            let webviews = open_tab_webviews(app)?;
+            if run_at == ScriptRunAt::DocumentStart {
+                // Fails before anything is recorded where it's unsupported.
+                for webview in &webviews {
+                    apply(webview)?;
+                }
+            }
+            global_user_content(app)?
+                .write()
+                .map_err(|e| e.to_string())?
+                .scripts
+                .push(script);
+            if run_at == ScriptRunAt::DocumentEnd {
+                for webview in &webviews {
+                    let _ = apply(webview);
+                }
+            }
+        }
     }
+    Ok(id)
 }
 
-/// Reposition only the active content webview after a window resize.
-/// Non-active webviews are left off-screen to avoid intercepting pointer events.
-pub fn reposition_webviews(
-    app: &tauri::AppHandle,
-    state: &TabState,
-) -> Result<(), String> {
-    let active_id = match &state.active_tab {
-        Some(id) => id.clone(),
-        None => return Ok(()),
-    };
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
 
-    let window = app
-        .get_window("main")
-        .ok_or("Main window not found")?;
+    use super::{resolve_address_input, TabInfo, TabState, UserContent};
 
-    let (position, size) = content_bounds(&window, state)?;
-    let bounds = tauri::Rect {
-        position: position.into(),
-        size: size.into(),
-    };
+    const SEARCH: &str = "https://search.example/?q={query}";
 
-    let label = format!("tab-{}", active_id);
-    if let Some(webview) = app.get_webview(&label) {
-        let _ = webview.set_auto_resize(false);
-        let _ = webview.set_bounds(bounds);
+    fn resolve(input: &str) -> String {
+        resolve_address_input(input, SEARCH)
     }
 
-    Ok(())
-}
+    #[test]
+    fn keeps_urls_with_a_scheme() {
+        assert_eq!(resolve("https://example.com/a?b=1"), "https://example.com/a?b=1");
+        assert_eq!(resolve("ftp://files.example"), "ftp://files.example");
+        assert_eq!(resolve("  http://example.com  "), "http://example.com");
+    }
 
-pub fn set_content_bounds(
-    app: &tauri::AppHandle,
-    state: &mut TabState,
-    bounds: ContentBounds,
-) -> Result<(), String> {
-    // Compute the title-bar y-offset from the JS data.
-    // JS viewport_height = bounds.top + bounds.height (the visible area).
-    // inner_size.height includes the title bar. The difference is the offset.
-    let window = app.get_window("main").ok_or("Main window not found")?;
-    let inner_size = window.inner_size().map_err(|e| e.to_string())?;
-    let scale = window.scale_factor().map_err(|e| e.to_string())?;
-    let inner_h_logical = inner_size.height as f64 / scale;
-    let viewport_h = bounds.top + bounds.height;
-    let y_off = (inner_h_logical - viewport_h).max(0.0);
+    #[test]
+    fn keeps_opaque_schemes() {
+        assert_eq!(resolve("about:blank"), "about:blank");
+        assert_eq!(resolve("data:text/plain,hi"), "data:text/plain,hi");
+        assert_eq!(resolve("mailto:a@example.com"), "mailto:a@example.com");
+        assert_eq!(resolve("VIEW-SOURCE:https://example.com"), "VIEW-SOURCE:https://example.com");
+    }
 
-    state.chrome_y_offset = y_off;
-    state.content_bounds = Some(bounds);
-    reposition_webviews(app, state)
+    #[test]
+    fn searches_javascript_urls() {
+        assert_eq!(resolve("javascript:alert(1)"), "https://search.example/?q=javascript%3Aalert%281%29");
+    }
+
+    #[test]
+    fn adds_https_to_domains() {
+        assert_eq!(resolve("example.com"), "https://example.com");
+        assert_eq!(resolve("example.com/path?q=1#top"), "https://example.com/path?q=1#top");
+        assert_eq!(resolve("example.com:8443"), "https://example.com:8443");
+    }
+
+    #[test]
+    fn adds_http_to_local_hosts() {
+        assert_eq!(resolve("localhost"), "http://localhost");
+        assert_eq!(resolve("LOCALHOST:3000/app"), "http://LOCALHOST:3000/app");
+        assert_eq!(resolve("127.0.0.1:8080"), "http://127.0.0.1:8080");
+        assert_eq!(resolve("192.168.1.1"), "http://192.168.1.1");
+        assert_eq!(resolve("[::1]"), "http://[::1]");
+        assert_eq!(resolve("[::1]:8080/x"), "http://[::1]:8080/x");
+    }
+
+    #[test]
+    fn treats_single_words_with_a_port_or_path_as_intranet_hosts() {
+        assert_eq!(resolve("wiki:8080"), "http://wiki:8080");
+        assert_eq!(resolve("intranet/"), "http://intranet/");
+        assert_eq!(resolve("intranet/docs?page=2"), "http://intranet/docs?page=2");
+    }
+
+    #[test]
+    fn searches_numbers() {
+        assert_eq!(resolve("42"), "https://search.example/?q=42");
+        assert_eq!(resolve("3.14"), "https://search.example/?q=3.14");
+        assert_eq!(resolve("1.2.3"), "https://search.example/?q=1.2.3");
+        assert_eq!(resolve("42/7"), "https://search.example/?q=42%2F7");
+    }
+
+    #[test]
+    fn searches_everything_else() {
+        assert_eq!(resolve("rust"), "https://search.example/?q=rust");
+        assert_eq!(resolve("rust lang.org"), "https://search.example/?q=rust+lang.org");
+        assert_eq!(resolve("foo:bar"), "https://search.example/?q=foo%3Abar");
+        assert_eq!(resolve("wiki:"), "https://search.example/?q=wiki%3A");
+        assert_eq!(resolve(""), "https://search.example/?q=");
+    }
+
+    fn tab(id: &str) -> TabInfo {
+        TabInfo {
+            id: id.to_string(),
+            url: "https://example.com".to_string(),
+            requested_url: "https://example.com".to_string(),
+            title: String::new(),
+            favicon: None,
+            zoom: 1.0,
+            incognito: false,
+            muted: false,
+            hibernated: false,
+            last_active_ms: 0,
+            scroll: None,
+            user_content: UserContent::default(),
+            reader_mode: false,
+            user_agent: None,
+            pinned: false,
+        }
+    }
+
+    fn tab_state() -> TabState {
+        TabState::new("main", Arc::default(), Arc::default())
+    }
+
+    #[test]
+    fn layout_snapshot_splits_active_and_background_tabs() {
+        let mut state = tab_state();
+        for id in ["a", "b", "c"] {
+            state.tabs.insert(id.to_string(), tab(id));
+        }
+        state.active_tab = Some("b".to_string());
+        state.chrome_y_offset = 28.0;
+        state.focus_mode = true;
+
+        let snapshot = state.layout_snapshot();
+        assert_eq!(snapshot.window_label, "main");
+        assert_eq!(snapshot.active_tab.as_deref(), Some("b"));
+        assert_eq!(snapshot.background_tabs, ["a", "c"]);
+        assert_eq!(snapshot.layout.chrome_y_offset, 28.0);
+        assert!(snapshot.layout.focus_mode);
+    }
+
+    /// Resizes reposition from a snapshot taken under the `TabState` lock and
+    /// make their webview calls after releasing it. A webview call waits on
+    /// the event loop, which may be running a command that needs the lock, so
+    /// holding it across the call would deadlock while tabs open and close.
+    #[test]
+    fn repositioning_from_snapshots_survives_tab_churn() {
+        let state = Arc::new(Mutex::new(tab_state()));
+
+        // Stands in for the event loop: each webview call is answered only
+        // after a command there has taken the lock.
+        let (webview_calls, event_loop_queue) = mpsc::channel::<mpsc::Sender<()>>();
+        let event_loop = thread::spawn({
+            let state = Arc::clone(&state);
+            move || {
+                for reply in event_loop_queue {
+                    let _command = state.lock().unwrap();
+                    let _ = reply.send(());
+                }
+            }
+        });
+
+        // Creates and closes tabs, switching to each new one.
+        let churn = thread::spawn({
+            let state = Arc::clone(&state);
+            move || {
+                for i in 0..500 {
+                    let mut state = state.lock().unwrap();
+                    let id = format!("tab-{}", i);
+                    state.tabs.insert(id.clone(), tab(&id));
+                    state.active_tab = Some(id);
+                    if i >= 3 {
+                        state.tabs.shift_remove(&format!("tab-{}", i - 3));
+                    }
+                }
+            }
+        });
+
+        for _ in 0..500 {
+            let snapshot = state.lock().unwrap().layout_snapshot();
+            if let Some(active) = &snapshot.active_tab {
+                assert!(!snapshot.background_tabs.contains(active));
+            }
+            assert!(snapshot.background_tabs.len() <= 2);
+
+            let (reply, done) = mpsc::channel();
+            webview_calls.send(reply).unwrap();
+            done.recv_timeout(Duration::from_secs(5))
+                .expect("webview call deadlocked on the TabState lock");
+        }
+
+        churn.join().unwrap();
+        drop(webview_calls);
+        event_loop.join().unwrap();
+    }
 }
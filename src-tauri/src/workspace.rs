@@ -0,0 +1,66 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde_json::Value;
+
+/// Resolve the ClawBrowser workspace directory: `workspacePath` from
+/// `~/.clawbrowser/config.json` when set, otherwise `~/.clawbrowser/workspace`.
+pub fn workspace_dir() -> Option<PathBuf> {
+    let home = home_dir()?;
+    let config_path = home.join(".clawbrowser").join("config.json");
+    if let Ok(raw) = fs::read_to_string(config_path) {
+        if let Ok(value) = serde_json::from_str::<Value>(&raw) {
+            if let Some(workspace_path) = value.get("workspacePath").and_then(|path| path.as_str()) {
+                if !workspace_path.is_empty() {
+                    return Some(PathBuf::from(workspace_path));
+                }
+            }
+        }
+    }
+    Some(home.join(".clawbrowser").join("workspace"))
+}
+
+/// Resolve `relative` inside `base`, refusing absolute paths, `..` segments
+/// and symlinks that escape `base`. The target must already exist.
+pub fn resolve_within(base: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative_path = Path::new(relative);
+    let escapes = relative_path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if relative.is_empty() || escapes {
+        return Err(format!("Path {} is outside {}", relative, base.display()));
+    }
+
+    let base = base
+        .canonicalize()
+        .map_err(|e| format!("Cannot access {}: {}", base.display(), e))?;
+    let target = base
+        .join(relative_path)
+        .canonicalize()
+        .map_err(|e| format!("Cannot access {}: {}", relative, e))?;
+    if !target.starts_with(&base) {
+        return Err(format!("Path {} is outside {}", relative, base.display()));
+    }
+    Ok(target)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    if let Ok(home) = std::env::var("HOME") {
+        if !home.is_empty() {
+            return Some(PathBuf::from(home));
+        }
+    }
+    if let Ok(home) = std::env::var("USERPROFILE") {
+        if !home.is_empty() {
+            return Some(PathBuf::from(home));
+        }
+    }
+    let drive = std::env::var("HOMEDRIVE").ok();
+    let path = std::env::var("HOMEPATH").ok();
+    match (drive, path) {
+        (Some(drive), Some(path)) if !drive.is_empty() && !path.is_empty() => {
+            Some(PathBuf::from(format!("{drive}{path}")))
+        }
+        _ => None,
+    }
+}
@@ -1,16 +1,8 @@
 import { invoke } from '@tauri-apps/api/core';
 import { listen, UnlistenFn } from '@tauri-apps/api/event';
-import { Command, Child } from '@tauri-apps/plugin-shell';
 import type { DomAutomationResult, DomAutomationRequest } from '../automation/domTypes';
 import type { AgentControlSettings } from './types';
 
-interface JsonRpcRequest {
-  jsonrpc: string;
-  method: string;
-  params: unknown;
-  id: number;
-}
-
 interface JsonRpcResponse {
   jsonrpc: string;
   result?: unknown;
@@ -29,91 +21,58 @@ const DEFAULT_TIMEOUT_MS = 30_000;
 const METHOD_TIMEOUTS: Record<string, number> = {
   agentQuery: 600_000,
 };
+// Responses can arrive before sidecar_send resolves with their id; keep a few around.
+const MAX_EARLY_RESPONSES = 100;
 
 export class SidecarBridge {
-  private child: Child | null = null;
   private pendingRequests: Map<number, {
     resolve: (value: unknown) => void;
     reject: (reason: Error) => void;
     timeoutId: number;
   }> = new Map();
+  private earlyResponses: Map<number, JsonRpcResponse> = new Map();
   private notificationHandlers: NotificationHandler[] = [];
-  private unlistenRequest: UnlistenFn | null = null;
   private unlistenMessage: UnlistenFn | null = null;
+  private unlistenStatus: UnlistenFn | null = null;
   private ready = false;
 
   async start(): Promise<void> {
-    // Listen for sidecar-request events from Rust -- write them to sidecar stdin
-    this.unlistenRequest = await listen<JsonRpcRequest>('sidecar-request', (event) => {
-      this.writeToSidecar(event.payload);
-    });
-
-    // Listen for sidecar-message events from Rust -- handle responses/notifications
+    // Rust owns the sidecar process and emits each parsed stdout line as sidecar-message
     this.unlistenMessage = await listen<JsonRpcResponse | SidecarNotification>('sidecar-message', (event) => {
       this.handleMessage(event.payload);
     });
 
-    // Spawn the sidecar process via Tauri shell plugin
-    const sidecarName = import.meta.env.VITE_SIDECAR_NAME || 'sidecar/clawbrowser-agent';
-    const command = Command.sidecar(sidecarName);
-
-    command.stdout.on('data', (line: string) => {
-      const trimmed = line.trim();
-      if (!trimmed) return;
-      // Relay stdout lines to Rust via sidecar_receive
-      invoke('sidecar_receive', { message: trimmed }).catch((err) => {
-        console.error('sidecar_receive failed:', err);
-      });
-    });
-
-    command.stderr.on('data', (line: string) => {
-      console.warn('[sidecar stderr]', line);
-    });
-
-    command.on('error', (error: string) => {
-      console.error('[sidecar error]', error);
-      this.ready = false;
-    });
-
-    command.on('close', (data: { code: number | null; signal: number | null }) => {
-      console.warn('[sidecar closed]', data);
-      this.ready = false;
-      // Reject all pending requests
-      for (const [, pending] of this.pendingRequests) {
-        clearTimeout(pending.timeoutId);
-        pending.reject(new Error('Sidecar process exited'));
+    this.unlistenStatus = await listen<{ status: string; code?: number | null; signal?: number | null }>('sidecar-status', (event) => {
+      if (event.payload.status === 'ready') {
+        // Rust restarts a crashed sidecar on its own
+        this.ready = true;
+        return;
       }
-      this.pendingRequests.clear();
+      if (event.payload.status !== 'exited') return;
+      console.warn('[sidecar closed]', event.payload);
+      this.ready = false;
+      this.rejectAllPending('Sidecar process exited');
     });
 
-    this.child = await command.spawn();
-
-    // Notify Rust that sidecar is starting
-    await invoke('start_sidecar');
+    const sidecarName = import.meta.env.VITE_SIDECAR_NAME || 'sidecar/clawbrowser-agent';
+    await invoke('start_sidecar', { name: sidecarName });
 
     this.ready = true;
   }
 
   async stop(): Promise<void> {
-    if (this.unlistenRequest) {
-      this.unlistenRequest();
-      this.unlistenRequest = null;
-    }
     if (this.unlistenMessage) {
       this.unlistenMessage();
       this.unlistenMessage = null;
     }
-    if (this.child) {
-      await this.child.kill();
-      this.child = null;
+    if (this.unlistenStatus) {
+      this.unlistenStatus();
+      this.unlistenStatus = null;
     }
     this.ready = false;
-
-    for (const [, pending] of this.pendingRequests) {
-      clearTimeout(pending.timeoutId);
-      pending.reject(new Error('Sidecar stopped'));
-    }
-    this.pendingRequests.clear();
+    this.rejectAllPending('Sidecar stopped');
+    // Rust owns the process, so it has to be told to shut it down.
+    await invoke('stop_sidecar');
   }
 
   async send(method: string, params: unknown = {}, options: { timeoutMs?: number } = {}): Promise<unknown> {
@@ -121,14 +80,18 @@ export class SidecarBridge {
       throw new Error('Sidecar not started');
     }
 
-    // Rust assigns the request ID and emits sidecar-request for us to write to stdin
-    const id: number = await invoke('sidecar_send', { method, params });
+    const timeoutMs = typeof options.timeoutMs === 'number'
+      ? options.timeoutMs
+      : (METHOD_TIMEOUTS[method] ?? DEFAULT_TIMEOUT_MS);
 
-    const promise = new Promise<unknown>((resolve, reject) => {
-      const timeoutMs = typeof options.timeoutMs === 'number'
-        ? options.timeoutMs
-        : (METHOD_TIMEOUTS[method] ?? DEFAULT_TIMEOUT_MS);
+    // Rust assigns the request ID, writes the request to the sidecar's stdin
+    // and drops its pending entry after the same timeout
+    const id: number = await invoke('sidecar_send', { method, params, timeoutMs });
+    if (!this.ready) {
+      throw new Error('Sidecar stopped');
+    }
 
+    const promise = new Promise<unknown>((resolve, reject) => {
       const timeoutId = window.setTimeout(() => {
         const pending = this.pendingRequests.get(id);
         if (!pending) return;
@@ -139,9 +102,23 @@ export class SidecarBridge {
       this.pendingRequests.set(id, { resolve, reject, timeoutId });
     });
 
+    const early = this.earlyResponses.get(id);
+    if (early) {
+      this.earlyResponses.delete(id);
+      this.settle(early);
+    }
+
     return promise;
   }
 
+  /** Send a JSON-RPC notification; the sidecar doesn't reply to it. */
+  async notify(method: string, params: unknown = {}): Promise<void> {
+    if (!this.ready) {
+      throw new Error('Sidecar not started');
+    }
+    await invoke('sidecar_notify', { method, params });
+  }
+
   onNotification(handler: NotificationHandler): void {
     this.notificationHandlers.push(handler);
   }
@@ -204,7 +181,7 @@ export class SidecarBridge {
   }
 
   async tabUpdate(tabCount: number, activeTabTitle: string): Promise<void> {
-    await this.send('tabUpdate', { tabCount, activeTabTitle });
+    await this.notify('tabUpdate', { tabCount, activeTabTitle });
   }
 
   async triggerReflection(): Promise<{ status: string; summary?: string; memoriesAdded?: number }> {
@@ -310,15 +287,15 @@ export class SidecarBridge {
   }
 
   async logSystemEvent(level: string, message: string): Promise<void> {
-    await this.send('logSystemEvent', { level, message });
+    await this.notify('logSystemEvent', { level, message });
   }
 
-  private writeToSidecar(request: JsonRpcRequest): void {
-    if (!this.child) return;
-    const line = JSON.stringify(request) + '\n';
-    this.child.write(line).catch((err) => {
-      console.error('Failed to write to sidecar stdin:', err);
-    });
+  private rejectAllPending(message: string): void {
+    for (const [, pending] of this.pendingRequests) {
+      clearTimeout(pending.timeoutId);
+      pending.reject(new Error(message));
+    }
+    this.pendingRequests.clear();
   }
 
   private handleMessage(payload: JsonRpcResponse | SidecarNotification | string): void {
@@ -337,14 +314,11 @@ export class SidecarBridge {
     // Check if it's a response (has id)
     if ('id' in msg && (msg as JsonRpcResponse).id !== undefined) {
       const response = msg as JsonRpcResponse;
-      const pending = this.pendingRequests.get(response.id);
-      if (pending) {
-        clearTimeout(pending.timeoutId);
-        this.pendingRequests.delete(response.id);
-        if (response.error) {
-          pending.reject(new Error(response.error.message));
-        } else {
-          pending.resolve(response.result);
+      if (!this.settle(response)) {
+        this.earlyResponses.set(response.id, response);
+        if (this.earlyResponses.size > MAX_EARLY_RESPONSES) {
+          const oldest = this.earlyResponses.keys().next().value;
+          if (oldest !== undefined) this.earlyResponses.delete(oldest);
         }
       }
     } else if ('method' in msg) {
@@ -355,4 +329,17 @@ export class SidecarBridge {
       }
     }
   }
+
+  private settle(response: JsonRpcResponse): boolean {
+    const pending = this.pendingRequests.get(response.id);
+    if (!pending) return false;
+    clearTimeout(pending.timeoutId);
+    this.pendingRequests.delete(response.id);
+    if (response.error) {
+      pending.reject(new Error(response.error.message));
+    } else {
+      pending.resolve(response.result);
+    }
+    return true;
+  }
 }
@@ -19,6 +19,7 @@ type DebugPayload = {
   lineno?: number;
   colno?: number;
   stack?: string;
+  dropped?: number;
 };
 
 type ConsoleLevel = 'log' | 'info' | 'warn' | 'error' | 'debug';
@@ -225,6 +226,12 @@ export class DebugCapture {
         });
         break;
       }
+      case 'dropped': {
+        // The page's rate limiter withheld this many events.
+        const entry = `[tab-dropped] ${this.formatPairs({ ...base, dropped: payload.dropped })}`;
+        this.log('warn', entry);
+        break;
+      }
       default: {
         const entry = `[tab-event] ${this.formatPairs({ ...base, type: payload.type })}`;
         this.log('info', entry);
@@ -18,6 +18,14 @@ import { MatrixBackground } from './ui/MatrixBackground';
 import type { AgentControlSettings } from './agent/types';
 
 const WEBVIEW_AUTOMATION_ENABLED = false;
+/** `tab-open-request` reasons that open the link in a background tab. */
+const BACKGROUND_OPEN_REASONS = new Set([
+  'shift-click',
+  'ctrl-click',
+  'meta-click',
+  'middle-click',
+  'context-menu',
+]);
 
 async function bootstrap(): Promise<void> {
   if (!WEBVIEW_AUTOMATION_ENABLED) {
@@ -271,30 +279,50 @@ async function bootstrap(): Promise<void> {
       });
     });
 
-    await listen('close-active-tab', () => {
-      const activeId = tabManager.getActiveTabId();
-      if (!activeId) return;
-      tabManager.closeTab(activeId).catch((err) => {
-        console.error('Failed to close tab:', err);
-      });
-    });
-
     await listen<{ tabId: string; url?: string; reason?: string }>('tab-open-request', (event) => {
       if (onboardingActive) {
         console.warn('Tab open request ignored during onboarding.');
         return;
       }
-      const { url, reason } = event.payload || {};
+      const { tabId, url, reason } = event.payload || {};
       if (!url) {
         console.warn('Tab open request missing URL');
         return;
       }
-      tabManager.createTab(url).catch((err) => {
+      // Modifier- and middle-clicked links open next to their tab without
+      // switching away; pages opening windows get a foreground tab.
+      const opened = reason && BACKGROUND_OPEN_REASONS.has(reason)
+        ? tabManager.createTab(url, tabId, { background: true })
+        : tabManager.createTab(url);
+      opened.catch((err) => {
         console.error(`Failed to open new tab (${reason || 'request'}):`, err);
       });
     });
   }
 
+  if (WEBVIEW_AUTOMATION_ENABLED) {
+    // New Tab (Cmd/Ctrl+T) and New Tab to the Right come from the native menu.
+    await listen<{ afterTabId: string | null }>('open-new-tab', (event) => {
+      if (onboardingActive) {
+        return;
+      }
+      const afterTabId = event.payload?.afterTabId ?? undefined;
+      tabManager.createTab('about:blank', afterTabId).catch((err) => {
+        console.error('Failed to create tab:', err);
+      });
+    });
+
+    // A user-initiated close hit a page with unsaved changes.
+    await listen<{ tabId: string }>('tab-close-confirm', (event) => {
+      const tabId = event.payload?.tabId;
+      if (!tabId) return;
+      const proceed = window.confirm('This page has unsaved changes. Close the tab anyway?');
+      tabManager.confirmCloseTab(tabId, proceed).catch((err) => {
+        console.error('Failed to confirm tab close:', err);
+      });
+    });
+  }
+
   // Agent panel
   new AgentPanel(agentPanelEl, sidecar, tabManager);
   const matrixBackground = new MatrixBackground(agentPanelEl, {
@@ -334,17 +362,6 @@ async function bootstrap(): Promise<void> {
 
       if (!hasPrimaryModifier || event.altKey) return;
 
-      if (key === 't' && !event.shiftKey) {
-        event.preventDefault();
-        if (onboardingActive) {
-          return;
-        }
-        tabManager.createTab('about:blank').catch((err) => {
-          console.error('Failed to create tab:', err);
-        });
-        return;
-      }
-
       if (key === 'l' && !event.shiftKey) {
         event.preventDefault();
         navBar.focusUrlInput();
@@ -354,6 +371,11 @@ async function bootstrap(): Promise<void> {
 
   if (WEBVIEW_AUTOMATION_ENABLED) {
     registerShortcuts();
+    // Cmd/Ctrl+L and Alt+D from the native menu, which also fire while a
+    // content webview has focus.
+    await listen('focus-address-bar', () => {
+      navBar.focusUrlInput();
+    });
   }
 
   // Voice input (appended to nav bar)
@@ -460,11 +482,19 @@ async function bootstrap(): Promise<void> {
         scheduleSync();
         return;
       }
-      invoke('reposition_tabs').catch((err) => {
+      invoke('reposition_tabs', { allTabs: true }).catch((err) => {
         console.error('Reposition failed:', err);
       });
     });
 
+    // Moved to a display with a different scale factor: re-report bounds so
+    // the backend's title-bar offset matches the new DPI.
+    await listen('content-bounds-requested', () => {
+      if (hasWebviewLayout) {
+        scheduleSync();
+      }
+    });
+
     // Create initial tab
     if (!onboardingActive && tabManager.getTabs().length === 0) {
       await tabManager.createTab('about:blank');
@@ -34,7 +34,7 @@ export class TabBar {
 
     for (const tab of tabs) {
       const tabEl = document.createElement('div');
-      tabEl.className = 'tab' + (tab.id === activeId ? ' active' : '');
+      tabEl.className = 'tab' + (tab.id === activeId ? ' active' : '') + (tab.pinned ? ' pinned' : '');
       tabEl.dataset.tabId = tab.id;
 
       // Prevent tab from being draggable (window drag region)
@@ -45,16 +45,19 @@ export class TabBar {
       titleSpan.textContent = tab.title || 'New Tab';
       tabEl.appendChild(titleSpan);
 
-      const closeBtn = document.createElement('button');
-      closeBtn.className = 'tab-close';
-      closeBtn.textContent = '\u00D7';
-      closeBtn.title = 'Close tab';
-      closeBtn.addEventListener('click', (e) => {
-        e.stopPropagation();
-        if (this.locked) return;
-        this.tabManager.closeTab(tab.id);
-      });
-      tabEl.appendChild(closeBtn);
+      // Pinned tabs have no close button so they aren't closed by accident.
+      if (!tab.pinned) {
+        const closeBtn = document.createElement('button');
+        closeBtn.className = 'tab-close';
+        closeBtn.textContent = '\u00D7';
+        closeBtn.title = 'Close tab';
+        closeBtn.addEventListener('click', (e) => {
+          e.stopPropagation();
+          if (this.locked) return;
+          this.tabManager.requestCloseTab(tab.id);
+        });
+        tabEl.appendChild(closeBtn);
+      }
 
       tabEl.addEventListener('click', () => {
         if (this.locked) return;
@@ -7,6 +7,8 @@ export interface Tab {
   title: string;
   history: string[];
   historyIndex: number;
+  /** Kept at the front of the strip and spared by bulk closes. */
+  pinned?: boolean;
 }
 
 export type TabChangeListener = (tabs: Tab[], activeId: string | null) => void;
@@ -15,6 +17,12 @@ export class TabManager {
   private tabs: Map<string, Tab> = new Map();
   private activeTabId: string | null = null;
   private listeners: TabChangeListener[] = [];
+  /** Bumped on every switchTab call; only the latest switch may apply. */
+  private switchSeq = 0;
+  /** Tail of the in-flight switch_tab calls, so they reach the backend in order. */
+  private switchQueue: Promise<void> = Promise.resolve();
+  /** Tabs whose current page has reported a real `document.title`. */
+  private pageTitled: Set<string> = new Set();
 
   async init(): Promise<void> {
     await listen<{ tabId: string; url: string }>('tab-loaded', (event) => {
@@ -22,7 +30,19 @@ export class TabManager {
       const tab = this.tabs.get(tabId);
       if (tab) {
         tab.url = url;
-        tab.title = this.titleFromUrl(url);
+        if (!this.pageTitled.has(tabId)) {
+          tab.title = this.titleFromUrl(url);
+        }
+        this.notify();
+      }
+    });
+
+    await listen<{ tabId: string; title: string }>('tab-title-changed', (event) => {
+      const { tabId, title } = event.payload;
+      const tab = this.tabs.get(tabId);
+      if (tab) {
+        tab.title = title;
+        this.pageTitled.add(tabId);
         this.notify();
       }
     });
@@ -32,6 +52,7 @@ export class TabManager {
       const tab = this.tabs.get(tabId);
       if (tab) {
         tab.url = url;
+        this.pageTitled.delete(tabId);
         // Push to history if it's a new navigation (not back/forward)
         if (tab.historyIndex === tab.history.length - 1) {
           tab.history.push(url);
@@ -40,6 +61,44 @@ export class TabManager {
         this.notify();
       }
     });
+
+    await listen<{ tabId: string; activeTabId: string | null }>('tab-closed', (event) => {
+      const { tabId, activeTabId } = event.payload;
+      if (!this.tabs.delete(tabId)) return;
+      this.pageTitled.delete(tabId);
+      if (this.activeTabId === tabId) {
+        this.activeTabId = activeTabId;
+      }
+      this.notify();
+    });
+
+    // Reorders made by the backend, e.g. when a tab is pinned.
+    await listen<{ order: string[] }>('tabs-reordered', (event) => {
+      this.applyOrder(event.payload.order, this.activeTabId);
+    });
+
+    await listen<{ tabId: string; pinned: boolean }>('tab-pinned', (event) => {
+      const tab = this.tabs.get(event.payload.tabId);
+      if (tab) {
+        tab.pinned = event.payload.pinned;
+        this.notify();
+      }
+    });
+
+    // Bulk closes (close other tabs / tabs to the right) arrive as one event.
+    await listen<{ order: string[]; activeTabId: string | null }>('tabs-changed', (event) => {
+      const { order, activeTabId } = event.payload;
+      this.applyOrder(order, activeTabId);
+    });
+
+    // Switches made by the backend, e.g. from the Cmd/Ctrl+1..9 shortcuts.
+    await listen<{ tabId: string }>('tab-activated', (event) => {
+      const { tabId } = event.payload;
+      if (!this.tabs.has(tabId) || this.activeTabId === tabId) return;
+      this.switchSeq++;
+      this.activeTabId = tabId;
+      this.notify();
+    });
   }
 
   private titleFromUrl(url: string): string {
@@ -79,8 +138,22 @@ export class TabManager {
     return this.tabs.get(id);
   }
 
-  async createTab(url: string = 'about:blank'): Promise<string> {
-    const id: string = await invoke('create_tab', { url });
+  /**
+   * Open a tab, at the end of the strip or right after `afterTabId`. A
+   * `background` tab leaves the active tab in front, as for a middle-clicked
+   * link, unless there is no active tab yet.
+   */
+  async createTab(
+    url: string = 'about:blank',
+    afterTabId?: string,
+    options: { background?: boolean } = {},
+  ): Promise<string> {
+    const order = Array.from(this.tabs.keys());
+    const index = afterTabId && this.tabs.has(afterTabId) ? order.indexOf(afterTabId) + 1 : undefined;
+    const args: Record<string, unknown> = { url };
+    if (index !== undefined) args.index = index;
+    if (options.background) args.background = true;
+    const id: string = await invoke('create_tab', args);
     const tab: Tab = {
       id,
       url,
@@ -88,8 +161,12 @@ export class TabManager {
       history: [url],
       historyIndex: 0,
     };
+    order.splice(index ?? order.length, 0, id);
     this.tabs.set(id, tab);
-    this.activeTabId = id;
+    this.tabs = new Map(order.map((tabId) => [tabId, this.tabs.get(tabId)!]));
+    if (!options.background || !this.activeTabId) {
+      this.activeTabId = id;
+    }
     this.notify();
     return id;
   }
@@ -97,6 +174,7 @@ export class TabManager {
   async closeTab(id: string): Promise<void> {
     const newActiveId: string | null = await invoke('close_tab', { tabId: id });
     this.tabs.delete(id);
+    this.pageTitled.delete(id);
 
     if (this.activeTabId === id) {
       // Use the backend's choice of next active tab to stay in sync
@@ -106,9 +184,80 @@ export class TabManager {
     this.notify();
   }
 
+  /**
+   * Close a tab on the user's behalf. If the page has unsaved changes the
+   * backend emits `tab-close-confirm` instead; answer with `confirmCloseTab`.
+   * The tab is dropped when `tab-closed` arrives.
+   */
+  async requestCloseTab(id: string): Promise<boolean> {
+    return invoke('request_close_tab', { tabId: id });
+  }
+
+  /** Answer a `tab-close-confirm` for `id`. */
+  async confirmCloseTab(id: string, proceed: boolean): Promise<void> {
+    await invoke('confirm_close_tab', { tabId: id, proceed });
+  }
+
+  /** Close every unpinned tab but `keepId`, which becomes active. */
+  async closeOtherTabs(keepId: string): Promise<void> {
+    const closed: string[] = await invoke('close_other_tabs', { keepTabId: keepId });
+    this.applyOrder(Array.from(this.tabs.keys()).filter((tabId) => !closed.includes(tabId)), keepId);
+  }
+
+  /** Close the unpinned tabs after `id`; `id` becomes active if the active tab closed. */
+  async closeTabsToRight(id: string): Promise<void> {
+    if (!this.tabs.has(id)) return;
+    const closed: string[] = await invoke('close_tabs_to_right', { tabId: id });
+    const kept = Array.from(this.tabs.keys()).filter((tabId) => !closed.includes(tabId));
+    this.applyOrder(kept, this.activeTabId && kept.includes(this.activeTabId) ? this.activeTabId : id);
+  }
+
+  /** Pin a tab, moving it to the end of the pinned group. */
+  async pinTab(id: string): Promise<void> {
+    await this.setPinned(id, true);
+  }
+
+  /** Unpin a tab, moving it to the start of the unpinned tabs. */
+  async unpinTab(id: string): Promise<void> {
+    await this.setPinned(id, false);
+  }
+
+  private async setPinned(id: string, pinned: boolean): Promise<void> {
+    const tab = this.tabs.get(id);
+    if (!tab || !!tab.pinned === pinned) return;
+    await invoke(pinned ? 'pin_tab' : 'unpin_tab', { tabId: id });
+    const order = Array.from(this.tabs.keys()).filter((tabId) => tabId !== id);
+    const pinnedCount = order.filter((tabId) => this.tabs.get(tabId)?.pinned).length;
+    order.splice(pinnedCount, 0, id);
+    tab.pinned = pinned;
+    this.applyOrder(order, this.activeTabId);
+  }
+
+  /** Keep only the tabs in `order`, in that order, and set the active tab. */
+  private applyOrder(order: string[], activeTabId: string | null): void {
+    const kept = order.filter((tabId) => this.tabs.has(tabId));
+    for (const tabId of this.tabs.keys()) {
+      if (!kept.includes(tabId)) this.pageTitled.delete(tabId);
+    }
+    this.tabs = new Map(kept.map((tabId) => [tabId, this.tabs.get(tabId)!]));
+    if (activeTabId === null || this.tabs.has(activeTabId)) {
+      this.activeTabId = activeTabId;
+    }
+    this.notify();
+  }
+
   async switchTab(id: string): Promise<void> {
     if (!this.tabs.has(id)) return;
-    await invoke('switch_tab', { tabId: id });
+    const seq = ++this.switchSeq;
+    // Rapid clicks queue up behind the in-flight switch. Superseded switches
+    // are skipped, so the backend only shows the tab that was clicked last.
+    const run = this.switchQueue.then(async () => {
+      if (seq !== this.switchSeq) return;
+      await invoke('switch_tab', { tabId: id });
+    });
+    this.switchQueue = run.catch(() => {});
+    await run;
+    if (seq !== this.switchSeq) return;
     this.activeTabId = id;
     this.notify();
   }
@@ -118,8 +267,8 @@ export class TabManager {
     const tab = this.tabs.get(this.activeTabId);
     if (!tab) return;
 
-    const resolved = this.resolveUrl(url);
-    await invoke('navigate_tab', { tabId: this.activeTabId, url: resolved });
+    // The backend turns address-bar input into a URL or a search.
+    const resolved: string = await invoke('navigate_tab', { tabId: this.activeTabId, url });
     tab.url = resolved;
 
     // Truncate forward history and push new entry
@@ -160,8 +309,7 @@ export class TabManager {
       throw new Error(`Tab ${tabId} not found`);
     }
 
-    const resolved = this.resolveUrl(url);
-    await invoke('navigate_tab', { tabId, url: resolved });
+    const resolved: string = await invoke('navigate_tab', { tabId, url });
     tab.url = resolved;
 
     tab.history = tab.history.slice(0, tab.historyIndex + 1);
@@ -184,18 +332,4 @@ export class TabManager {
     const tab = this.getActiveTab();
     return !!tab && tab.historyIndex < tab.history.length - 1;
   }
-
-  private resolveUrl(input: string): string {
-    const trimmed = input.trim();
-    // Already a full URL
-    if (/^https?:\/\//i.test(trimmed)) {
-      return trimmed;
-    }
-    // Looks like a domain (contains a dot, no spaces)
-    if (/^[^\s]+\.[^\s]+$/.test(trimmed)) {
-      return `https://${trimmed}`;
-    }
-    // Treat as search query
-    return `https://www.google.com/search?q=${encodeURIComponent(trimmed)}`;
-  }
 }
@@ -6,16 +6,6 @@ const mocks = vi.hoisted(() => ({
   listen: vi.fn(),
   listeners: new Map<string, (event: { payload: any }) => void>(),
   unlistenFns: [] as Array<ReturnType<typeof vi.fn>>,
-  sidecarPath: '',
-  stdoutHandler: null as null | ((line: string) => void),
-  stderrHandler: null as null | ((line: string) => void),
-  errorHandler: null as null | ((error: string) => void),
-  closeHandler: null as null | ((data: { code: number | null; signal: number | null }) => void),
-  child: {
-    write: vi.fn().mockResolvedValue(undefined),
-    kill: vi.fn().mockResolvedValue(undefined),
-  },
-  spawn: vi.fn(),
 }));
 
 vi.mock('@tauri-apps/api/core', () => ({
@@ -31,65 +21,23 @@ vi.mock('@tauri-apps/api/event', () => ({
   },
 }));
 
-vi.mock('@tauri-apps/plugin-shell', () => ({
-  Command: {
-    sidecar: (path: string) => {
-      mocks.sidecarPath = path;
-      return {
-        stdout: {
-          on: (_event: string, handler: (line: string) => void) => {
-            mocks.stdoutHandler = handler;
-          },
-        },
-        stderr: {
-          on: (_event: string, handler: (line: string) => void) => {
-            mocks.stderrHandler = handler;
-          },
-        },
-        on: (event: string, handler: (data: any) => void) => {
-          if (event === 'error') mocks.errorHandler = handler;
-          if (event === 'close') mocks.closeHandler = handler;
-        },
-        spawn: mocks.spawn,
-      };
-    },
-  },
-}));
-
 describe('SidecarBridge', () => {
   beforeEach(() => {
     mocks.invoke.mockReset();
     mocks.listen.mockReset();
     mocks.listeners.clear();
     mocks.unlistenFns.length = 0;
-    mocks.stdoutHandler = null;
-    mocks.stderrHandler = null;
-    mocks.errorHandler = null;
-    mocks.closeHandler = null;
-    mocks.child.write.mockClear();
-    mocks.child.kill.mockClear();
-    mocks.spawn.mockImplementation(async () => mocks.child);
   });
 
-  it('starts, wires listeners, and handles stdout', async () => {
-    let nextId = 1;
-    mocks.invoke.mockImplementation(async (cmd: string) => {
-      if (cmd === 'start_sidecar') return undefined;
-      if (cmd === 'sidecar_send') return nextId++;
-      if (cmd === 'sidecar_receive') return undefined;
-      return undefined;
-    });
+  it('starts the sidecar in Rust and wires listeners', async () => {
+    mocks.invoke.mockResolvedValue(undefined);
 
     const bridge = new SidecarBridge();
     await bridge.start();
 
-    expect(mocks.listeners.has('sidecar-request')).toBe(true);
     expect(mocks.listeners.has('sidecar-message')).toBe(true);
-    expect(mocks.invoke).toHaveBeenCalledWith('start_sidecar');
-    expect(mocks.sidecarPath).toBe('sidecar/clawbrowser-agent');
-
-    mocks.stdoutHandler?.(' hello ');
-    expect(mocks.invoke).toHaveBeenCalledWith('sidecar_receive', { message: 'hello' });
+    expect(mocks.listeners.has('sidecar-status')).toBe(true);
+    expect(mocks.invoke).toHaveBeenCalledWith('start_sidecar', { name: 'sidecar/clawbrowser-agent' });
   });
 
   it('sends requests and resolves responses', async () => {
@@ -104,7 +52,7 @@ describe('SidecarBridge', () => {
     await bridge.start();
 
     const responsePromise = bridge.send('ping', { foo: 'bar' });
-    expect(mocks.invoke).toHaveBeenCalledWith('sidecar_send', { method: 'ping', params: { foo: 'bar' } });
+    expect(mocks.invoke).toHaveBeenCalledWith('sidecar_send', { method: 'ping', params: { foo: 'bar' }, timeoutMs: 30_000 });
 
     await new Promise(resolve => setTimeout(resolve, 0));
     const messageHandler = mocks.listeners.get('sidecar-message');
@@ -115,6 +63,20 @@ describe('SidecarBridge', () => {
     await expect(responsePromise).resolves.toEqual({ pong: true });
   });
 
+  it('sends notifications without allocating a request', async () => {
+    mocks.invoke.mockResolvedValue(undefined);
+
+    const bridge = new SidecarBridge();
+    await bridge.start();
+    await bridge.tabUpdate(2, 'Example');
+
+    expect(mocks.invoke).toHaveBeenCalledWith('sidecar_notify', {
+      method: 'tabUpdate',
+      params: { tabCount: 2, activeTabTitle: 'Example' },
+    });
+    expect(mocks.invoke).not.toHaveBeenCalledWith('sidecar_send', expect.anything());
+  });
+
   it('rejects errors from the sidecar response', async () => {
     let nextId = 1;
     mocks.invoke.mockImplementation(async (cmd: string) => {
@@ -136,19 +98,58 @@ describe('SidecarBridge', () => {
     await expect(responsePromise).rejects.toThrow('nope');
   });
 
-  it('writes sidecar-request payloads to stdin', async () => {
-    mocks.invoke.mockResolvedValue(undefined);
+  it('resolves responses that arrive before sidecar_send returns', async () => {
+    mocks.invoke.mockImplementation(async (cmd: string) => {
+      if (cmd === 'sidecar_send') {
+        mocks.listeners.get('sidecar-message')?.({
+          payload: { jsonrpc: '2.0', id: 7, result: { pong: true } },
+        });
+        return 7;
+      }
+      return undefined;
+    });
+
+    const bridge = new SidecarBridge();
+    await bridge.start();
+
+    await expect(bridge.send('ping')).resolves.toEqual({ pong: true });
+  });
+
+  it('rejects pending requests when the sidecar exits', async () => {
+    mocks.invoke.mockImplementation(async (cmd: string) => {
+      if (cmd === 'sidecar_send') return 1;
+      return undefined;
+    });
+
     const bridge = new SidecarBridge();
     await bridge.start();
 
-    const requestHandler = mocks.listeners.get('sidecar-request');
-    requestHandler?.({
-      payload: { jsonrpc: '2.0', id: 9, method: 'doThing', params: { a: 1 } },
+    const pending = bridge.send('pending');
+    await new Promise(resolve => setTimeout(resolve, 0));
+    mocks.listeners.get('sidecar-status')?.({ payload: { status: 'exited', code: 1, signal: null } });
+
+    await expect(pending).rejects.toThrow('Sidecar process exited');
+    await expect(bridge.send('ping')).rejects.toThrow('Sidecar not started');
+  });
+
+  it('accepts requests again once Rust restarts the sidecar', async () => {
+    mocks.invoke.mockImplementation(async (cmd: string) => {
+      if (cmd === 'sidecar_send') return 1;
+      return undefined;
     });
 
-    expect(mocks.child.write).toHaveBeenCalledWith(
-      JSON.stringify({ jsonrpc: '2.0', id: 9, method: 'doThing', params: { a: 1 } }) + '\n'
-    );
+    const bridge = new SidecarBridge();
+    await bridge.start();
+
+    const statusHandler = mocks.listeners.get('sidecar-status');
+    statusHandler?.({ payload: { status: 'exited', code: 1, signal: null } });
+    statusHandler?.({ payload: { status: 'ready' } });
+
+    const response = bridge.send('ping');
+    await new Promise(resolve => setTimeout(resolve, 0));
+    mocks.listeners.get('sidecar-message')?.({ payload: { jsonrpc: '2.0', id: 1, result: { pong: true } } });
+
+    await expect(response).resolves.toEqual({ pong: true });
   });
 
   it('notifies subscribers on sidecar notifications', async () => {
@@ -165,6 +166,16 @@ describe('SidecarBridge', () => {
     expect(handler).toHaveBeenCalledWith('agentReady', { ok: true });
   });
 
+  it('stops the sidecar process in Rust on stop', async () => {
+    mocks.invoke.mockResolvedValue(undefined);
+
+    const bridge = new SidecarBridge();
+    await bridge.start();
+    await bridge.stop();
+
+    expect(mocks.invoke).toHaveBeenCalledWith('stop_sidecar');
+  });
+
   it('rejects pending requests on stop', async () => {
     let nextId = 1;
     mocks.invoke.mockImplementation(async (cmd: string) => {
@@ -180,7 +191,6 @@ describe('SidecarBridge', () => {
     await bridge.stop();
 
     await expect(pending).rejects.toThrow('Sidecar stopped');
-    expect(mocks.child.kill).toHaveBeenCalledTimes(1);
     expect(mocks.unlistenFns.length).toBe(2);
     for (const unlisten of mocks.unlistenFns) {
       expect(unlisten).toHaveBeenCalledTimes(1);
@@ -212,6 +222,6 @@ describe('SidecarBridge', () => {
     });
 
     await expect(cancelPromise).resolves.toBeUndefined();
-    expect(mocks.invoke).toHaveBeenCalledWith('sidecar_send', { method: 'swarmCancel', params: {} });
+    expect(mocks.invoke).toHaveBeenCalledWith('sidecar_send', { method: 'swarmCancel', params: {}, timeoutMs: 30_000 });
   });
 });
@@ -0,0 +1,62 @@
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import { rustScript } from './rust-script';
+
+// request_close_tab asks the page-side guard whether closing would lose anything.
+function shouldConfirm(): Promise<boolean> {
+  return new Function(`return (async () => {${rustScript('CLOSE_GUARD_CHECK_SCRIPT')}})();`)();
+}
+
+describe('CLOSE_GUARD_SCRIPT', () => {
+  const originalAdd = window.addEventListener;
+  const originalRemove = window.removeEventListener;
+
+  beforeEach(() => {
+    delete (window as any).__CLAW_CLOSE_GUARD__;
+    document.body.innerHTML = '<form><input id="name"><input id="query" type="search"></form>';
+    new Function(rustScript('CLOSE_GUARD_SCRIPT'))();
+  });
+
+  afterEach(() => {
+    delete (window as any).__CLAW_CLOSE_GUARD__;
+    window.onbeforeunload = null;
+    window.addEventListener = originalAdd;
+    window.removeEventListener = originalRemove;
+  });
+
+  it('lets clean pages close', async () => {
+    window.addEventListener('beforeunload', () => {
+      navigator.userAgent;
+    });
+
+    expect(await shouldConfirm()).toBe(false);
+  });
+
+  it('asks when a beforeunload handler objects', async () => {
+    const handler = (event: BeforeUnloadEvent) => {
+      event.preventDefault();
+      event.returnValue = 'Unsaved changes';
+    };
+    window.addEventListener('beforeunload', handler);
+    expect(await shouldConfirm()).toBe(true);
+
+    window.removeEventListener('beforeunload', handler);
+    expect(await shouldConfirm()).toBe(false);
+  });
+
+  it('asks when onbeforeunload returns a message', async () => {
+    window.onbeforeunload = () => 'Unsaved changes';
+
+    expect(await shouldConfirm()).toBe(true);
+  });
+
+  it('asks after a form field is edited until the form is submitted', async () => {
+    document.getElementById('query')!.dispatchEvent(new Event('input', { bubbles: true }));
+    expect(await shouldConfirm()).toBe(false);
+
+    document.getElementById('name')!.dispatchEvent(new Event('input', { bubbles: true }));
+    expect(await shouldConfirm()).toBe(true);
+
+    document.querySelector('form')!.dispatchEvent(new Event('submit', { bubbles: true }));
+    expect(await shouldConfirm()).toBe(false);
+  });
+});
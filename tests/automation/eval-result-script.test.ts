@@ -0,0 +1,73 @@
+import { describe, it, expect, vi, beforeEach, afterEach } from 'vitest';
+import { rustScript } from './rust-script';
+
+// Builds the wrapper run_js_in_tab / run_js_in_tab_json inject, as tabs.rs does.
+function buildRunJsScript(code: string): string {
+  const body = rustScript('RUN_JS_BODY')
+    .replace('__BLOCKED__', JSON.stringify('__CLAW_EVAL_BLOCKED__'))
+    .replace('__CODE__', JSON.stringify(code));
+  return rustScript('EVAL_RESULT_SCRIPT')
+    .replace('__EMIT_PRELUDE__', rustScript('EMIT_PRELUDE'))
+    .replace('__REQUEST_ID__', JSON.stringify('req-1'))
+    .replace('__BODY__', body);
+}
+
+async function evaluate(code: string): Promise<Record<string, unknown>> {
+  const emit = vi.fn();
+  (window as any).__TAURI__ = { event: { emit } };
+  new Function(buildRunJsScript(code))();
+  await vi.waitFor(() => expect(emit).toHaveBeenCalledTimes(1));
+  expect(emit.mock.calls[0][0]).toBe('claw-eval-result');
+  return emit.mock.calls[0][1];
+}
+
+describe('EVAL_RESULT_SCRIPT', () => {
+  beforeEach(() => {
+    delete (window as any).__TAURI__;
+  });
+
+  afterEach(() => {
+    delete (window as any).__TAURI__;
+  });
+
+  it('reports a thrown error with its name, message and stack', async () => {
+    const payload = await evaluate("throw new Error('x')");
+
+    expect(payload).toMatchObject({
+      requestId: 'req-1',
+      ok: false,
+      error: 'x',
+      exception: { name: 'Error', message: 'x', stack: expect.any(String) },
+    });
+  });
+
+  it('reports a thrown non-error value by its type', async () => {
+    const payload = await evaluate("throw 'plain'");
+
+    expect(payload).toMatchObject({
+      ok: false,
+      exception: { name: 'string', message: 'plain', stack: null },
+    });
+  });
+
+  it('tells undefined apart from null', async () => {
+    expect(await evaluate('undefined')).toMatchObject({ ok: true, value: null, undefined: true });
+    const nullPayload = await evaluate('null');
+    expect(nullPayload).toMatchObject({ ok: true, value: null });
+    expect(nullPayload.undefined).toBeUndefined();
+  });
+
+  it('awaits returned promises', async () => {
+    expect(await evaluate('Promise.resolve({ answer: 42 })')).toMatchObject({
+      ok: true,
+      value: { answer: 42 },
+    });
+  });
+
+  it('rejects results that are not JSON-serializable', async () => {
+    const payload = await evaluate('(() => {})');
+
+    expect(payload).toMatchObject({ ok: false, error: 'Result is not JSON-serializable: function' });
+    expect(payload.exception).toBeUndefined();
+  });
+});
@@ -0,0 +1,61 @@
+import { describe, it, expect, beforeEach, afterEach } from 'vitest';
+import { rustScript } from './rust-script';
+
+// Runs the waiter wait_for_network_idle evaluates against the injected tracker.
+function waitForIdle(idleMs: number, timeoutMs: number): Promise<number> {
+  const body = rustScript('WAIT_FOR_NETWORK_IDLE_SCRIPT')
+    .replace('__IDLE_MS__', String(idleMs))
+    .replace('__TIMEOUT_MS__', String(timeoutMs));
+  return new Function(`return (async () => {${body}})();`)();
+}
+
+describe('NETWORK_ACTIVITY_SCRIPT', () => {
+  const originalFetch = window.fetch;
+  let release: () => void;
+
+  beforeEach(() => {
+    delete (window as any).__CLAW_NETWORK__;
+    (window as any).fetch = () => new Promise<void>((resolve) => {
+      release = resolve;
+    });
+    new Function(rustScript('NETWORK_ACTIVITY_SCRIPT'))();
+  });
+
+  afterEach(() => {
+    delete (window as any).__CLAW_NETWORK__;
+    (window as any).fetch = originalFetch;
+  });
+
+  it('counts fetches while they are in flight', async () => {
+    const tracker = (window as any).__CLAW_NETWORK__;
+    const pending = window.fetch('https://example.com/data');
+
+    expect(tracker.inFlight()).toBe(1);
+    release();
+    await pending;
+    await Promise.resolve();
+    expect(tracker.inFlight()).toBe(0);
+  });
+
+  it('waits for in-flight requests before reporting idle', async () => {
+    const pending = window.fetch('https://example.com/data');
+    let idle = false;
+    const waiting = waitForIdle(20, 1000).then((waited) => {
+      idle = true;
+      return waited;
+    });
+
+    await new Promise((resolve) => setTimeout(resolve, 50));
+    expect(idle).toBe(false);
+
+    release();
+    await pending;
+    expect(await waiting).toBeGreaterThanOrEqual(50);
+  });
+
+  it('rejects when the network never goes idle', async () => {
+    window.fetch('https://example.com/slow');
+
+    await expect(waitForIdle(20, 50)).rejects.toThrow('1 requests in flight');
+  });
+});
@@ -0,0 +1,12 @@
+import { readFileSync } from 'node:fs';
+import { fileURLToPath } from 'node:url';
+
+// Page scripts injected by the Rust backend live as raw-string consts in
+// tabs.rs; tests pull them out of the source so they exercise the real code.
+const TABS_RS = readFileSync(fileURLToPath(new URL('../../src-tauri/src/tabs.rs', import.meta.url)), 'utf8');
+
+export function rustScript(name: string): string {
+  const match = TABS_RS.match(new RegExp(`const ${name}: &str = r#"([\\s\\S]*?)"#;`));
+  if (!match) throw new Error(`${name} not found in tabs.rs`);
+  return match[1];
+}
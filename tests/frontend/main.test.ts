@@ -180,7 +180,7 @@ describe('main bootstrap', () => {
     expect(mocks.sidecarAgentQuery).toHaveBeenCalledWith('hello');
 
     expect(mocks.resizeHandler).toBeNull();
-    expect(mocks.invoke).not.toHaveBeenCalledWith('reposition_tabs');
+    expect(mocks.invoke).not.toHaveBeenCalledWith('reposition_tabs', expect.anything());
     expect(matrixMocks.options?.watermark?.lines).toEqual([
       'CLAWBROWSER',
       'THE SMARTEST CHILD OF OPENCLAW.',
@@ -10,6 +10,7 @@ describe('TabBar', () => {
   let tabManager: TabManager & {
     createTab: ReturnType<typeof vi.fn>;
     closeTab: ReturnType<typeof vi.fn>;
+    requestCloseTab: ReturnType<typeof vi.fn>;
     switchTab: ReturnType<typeof vi.fn>;
   };
 
@@ -30,6 +31,7 @@ describe('TabBar', () => {
       getActiveTabId: () => activeId,
       createTab: vi.fn(),
       closeTab: vi.fn(),
+      requestCloseTab: vi.fn(),
       switchTab: vi.fn(),
     } as any;
   });
@@ -47,7 +49,8 @@ describe('TabBar', () => {
 
     const closeBtn = tabEls[1].querySelector('.tab-close') as HTMLButtonElement;
     closeBtn.click();
-    expect(tabManager.closeTab).toHaveBeenCalledWith('tab-2');
+    expect(tabManager.requestCloseTab).toHaveBeenCalledWith('tab-2');
+    expect(tabManager.closeTab).not.toHaveBeenCalled();
     expect(tabManager.switchTab).toHaveBeenCalledTimes(1);
 
     const newTabBtn = container.querySelector('.new-tab-btn') as HTMLButtonElement;
@@ -11,8 +11,10 @@ vi.mock('@tauri-apps/api/event', () => ({
 
 import { TabManager } from '../../src/tabs/TabManager';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
 
 const mockedInvoke = vi.mocked(invoke);
+const mockedListen = vi.mocked(listen);
 
 describe('TabManager', () => {
   let tabManager: TabManager;
@@ -21,11 +23,11 @@ describe('TabManager', () => {
     vi.clearAllMocks();
     tabManager = new TabManager();
     // Default mock: create_tab returns a UUID
-    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+    mockedInvoke.mockImplementation(async (cmd: string, args?: unknown) => {
       if (cmd === 'create_tab') return 'tab-uuid-1';
       if (cmd === 'close_tab') return null;
       if (cmd === 'switch_tab') return undefined;
-      if (cmd === 'navigate_tab') return undefined;
+      if (cmd === 'navigate_tab') return (args as { url: string }).url;
       if (cmd === 'run_js_in_tab') return '';
       if (cmd === 'list_tabs') return [];
       if (cmd === 'get_active_tab') return null;
@@ -53,6 +55,114 @@ describe('TabManager', () => {
     expect(tabs[0].historyIndex).toBe(0);
   });
 
+  it('should place a tab to the right of another', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    await tabManager.createTab('about:blank', 'tab-uuid-1');
+
+    expect(mockedInvoke).toHaveBeenCalledWith('create_tab', { url: 'about:blank', index: 1 });
+    expect(tabManager.getTabs().map((tab) => tab.id)).toEqual(['tab-uuid-1', 'tab-uuid-3', 'tab-uuid-2']);
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-3');
+  });
+
+  it('should open a background tab without switching to it', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    await tabManager.switchTab('tab-uuid-1');
+    await tabManager.createTab('https://link.com', 'tab-uuid-1', { background: true });
+
+    expect(mockedInvoke).toHaveBeenCalledWith('create_tab', {
+      url: 'https://link.com',
+      index: 1,
+      background: true,
+    });
+    expect(tabManager.getTabs().map((tab) => tab.id)).toEqual(['tab-uuid-1', 'tab-uuid-3', 'tab-uuid-2']);
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-1');
+  });
+
+  it('should close every tab but the kept one', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      if (cmd === 'close_other_tabs') return ['tab-uuid-1', 'tab-uuid-3'];
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    await tabManager.createTab('https://third.com');
+    await tabManager.closeOtherTabs('tab-uuid-2');
+
+    expect(mockedInvoke).toHaveBeenCalledWith('close_other_tabs', { keepTabId: 'tab-uuid-2' });
+    expect(tabManager.getTabs().map((tab) => tab.id)).toEqual(['tab-uuid-2']);
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-2');
+  });
+
+  it('should close tabs to the right and keep a surviving active tab', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      if (cmd === 'close_tabs_to_right') return ['tab-uuid-2', 'tab-uuid-3'];
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    await tabManager.createTab('https://third.com');
+    await tabManager.closeTabsToRight('tab-uuid-1');
+
+    expect(mockedInvoke).toHaveBeenCalledWith('close_tabs_to_right', { tabId: 'tab-uuid-1' });
+    expect(tabManager.getTabs().map((tab) => tab.id)).toEqual(['tab-uuid-1']);
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-1');
+  });
+
+  it('should move a pinned tab ahead of unpinned ones and keep it on close others', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      if (cmd === 'close_other_tabs') return ['tab-uuid-1'];
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    await tabManager.createTab('https://third.com');
+    await tabManager.pinTab('tab-uuid-3');
+
+    expect(mockedInvoke).toHaveBeenCalledWith('pin_tab', { tabId: 'tab-uuid-3' });
+    expect(tabManager.getTabs().map((tab) => tab.id)).toEqual(['tab-uuid-3', 'tab-uuid-1', 'tab-uuid-2']);
+
+    await tabManager.closeOtherTabs('tab-uuid-2');
+    expect(tabManager.getTabs().map((tab) => tab.id)).toEqual(['tab-uuid-3', 'tab-uuid-2']);
+  });
+
   it('should close a tab and call invoke', async () => {
     await tabManager.createTab('https://example.com');
     await tabManager.closeTab('tab-uuid-1');
@@ -103,6 +213,95 @@ describe('TabManager', () => {
     expect(tabManager.getActiveTabId()).toBe('tab-uuid-1');
   });
 
+  it('should follow tab switches made by the backend', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      return undefined;
+    });
+    await tabManager.init();
+    const call = mockedListen.mock.calls.find(([event]) => event === 'tab-activated');
+    const handler = call![1] as (event: { payload: { tabId: string } }) => void;
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    handler({ payload: { tabId: 'tab-uuid-1' } });
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-1');
+
+    handler({ payload: { tabId: 'unknown-tab' } });
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-1');
+  });
+
+  it('should only apply the last of several back-to-back switches', async () => {
+    let callCount = 0;
+    mockedInvoke.mockImplementation(async (cmd: string, _args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+    await tabManager.createTab('https://third.com');
+    mockedInvoke.mockClear();
+
+    await Promise.all([
+      tabManager.switchTab('tab-uuid-1'),
+      tabManager.switchTab('tab-uuid-2'),
+      tabManager.switchTab('tab-uuid-3'),
+    ]);
+
+    const switches = mockedInvoke.mock.calls.filter(([cmd]) => cmd === 'switch_tab');
+    expect(switches).toEqual([['switch_tab', { tabId: 'tab-uuid-3' }]]);
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-3');
+  });
+
+  it('should not send a switch until the in-flight one settles', async () => {
+    let callCount = 0;
+    let releaseFirst: () => void = () => {};
+    const order: string[] = [];
+    mockedInvoke.mockImplementation(async (cmd: string, args?: unknown) => {
+      if (cmd === 'create_tab') {
+        callCount++;
+        return `tab-uuid-${callCount}`;
+      }
+      if (cmd === 'switch_tab') {
+        const { tabId } = args as { tabId: string };
+        order.push(`start:${tabId}`);
+        if (tabId === 'tab-uuid-1') {
+          await new Promise<void>((resolve) => { releaseFirst = resolve; });
+        }
+        order.push(`end:${tabId}`);
+      }
+      return undefined;
+    });
+
+    await tabManager.createTab('https://first.com');
+    await tabManager.createTab('https://second.com');
+
+    const first = tabManager.switchTab('tab-uuid-1');
+    await Promise.resolve();
+    const second = tabManager.switchTab('tab-uuid-2');
+    await Promise.resolve();
+    expect(order).toEqual(['start:tab-uuid-1']);
+
+    releaseFirst();
+    await Promise.all([first, second]);
+
+    expect(order).toEqual([
+      'start:tab-uuid-1',
+      'end:tab-uuid-1',
+      'start:tab-uuid-2',
+      'end:tab-uuid-2',
+    ]);
+    expect(tabManager.getActiveTabId()).toBe('tab-uuid-2');
+  });
+
   it('should navigate and update history', async () => {
     await tabManager.createTab('https://start.com');
     await tabManager.navigate('https://page2.com');
@@ -118,29 +317,35 @@ describe('TabManager', () => {
     expect(tab?.historyIndex).toBe(1);
   });
 
-  it('should resolve URLs correctly', async () => {
-    await tabManager.createTab('about:blank');
-
-    // Domain without protocol
-    await tabManager.navigate('example.com');
-    expect(mockedInvoke).toHaveBeenCalledWith('navigate_tab', {
-      tabId: 'tab-uuid-1',
-      url: 'https://example.com',
+  it('should pass typed input to the backend and record the URL it resolves', async () => {
+    const resolved: Record<string, string> = {
+      'localhost:3000': 'http://localhost:3000/',
+      'how to use tauri': 'https://search.example/?q=how%20to%20use%20tauri',
+    };
+    mockedInvoke.mockImplementation(async (cmd: string, args?: unknown) => {
+      if (cmd === 'create_tab') return 'tab-uuid-1';
+      if (cmd === 'navigate_tab') return resolved[(args as { url: string }).url];
+      return undefined;
     });
+    await tabManager.createTab('about:blank');
 
-    // Search query
-    await tabManager.navigate('how to use tauri');
+    await tabManager.navigate('localhost:3000');
     expect(mockedInvoke).toHaveBeenCalledWith('navigate_tab', {
       tabId: 'tab-uuid-1',
-      url: 'https://www.google.com/search?q=how%20to%20use%20tauri',
+      url: 'localhost:3000',
     });
+    expect(tabManager.getActiveTab()?.url).toBe('http://localhost:3000/');
 
-    // Full URL
-    await tabManager.navigate('https://rust-lang.org');
+    await tabManager.navigateTab('tab-uuid-1', 'how to use tauri');
     expect(mockedInvoke).toHaveBeenCalledWith('navigate_tab', {
       tabId: 'tab-uuid-1',
-      url: 'https://rust-lang.org',
+      url: 'how to use tauri',
     });
+    expect(tabManager.getActiveTab()?.history).toEqual([
+      'about:blank',
+      'http://localhost:3000/',
+      'https://search.example/?q=how%20to%20use%20tauri',
+    ]);
   });
 
   it('should handle back/forward navigation', async () => {